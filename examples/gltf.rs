@@ -1,5 +1,5 @@
 use cgmath::Quaternion;
-use forte_engine::{component_app::EngineComponent, create_app, lights::{lights::LightUniform, LightEngine}, math::{quaternion::QuaternionExt, transforms::Transform}, models::{gltf::GLTFLoader, Model}, primitives::{cameras::Camera, transforms::TransformRaw}, run_app};
+use forte_engine::{component_app::EngineComponent, create_app, lights::{lights::Light, LightEngine}, math::{quaternion::QuaternionExt, transforms::Transform}, models::{gltf::GLTFLoader, Model}, primitives::{cameras::{Camera, Projection}, transforms::TransformRaw}, run_app};
 use gltf::Gltf;
 
 pub struct TestComponent {
@@ -14,8 +14,7 @@ impl EngineComponent<(&mut RenderEngine, &mut LightEngine)> for TestComponent {
         // generate camera
         let mut camera = Camera::new(
             &engine, 
-            engine.config.width as f32 / engine.config.height as f32,
-            45.0, 0.1, 100.0
+            Projection::Perspective { fovy: 45.0, znear: 0.1, zfar: 100.0 }
         );
         camera.position = (0.0, 0.0, 5.0).into();
         camera.update(engine);
@@ -28,7 +27,7 @@ impl EngineComponent<(&mut RenderEngine, &mut LightEngine)> for TestComponent {
         }];
 
         let gltf = Gltf::from_slice(include_bytes!("mine.gltf.glb")).expect("Could not load binary gltf");
-        let gltf = GLTFLoader::unpack_static_gltf(engine, gltf);
+        let gltf = GLTFLoader::unpack_static_gltf(engine, gltf).expect("Could not unpack gltf");
 
         Self {
             instance_buffer: TransformRaw::buffer_from_generic(engine, &instances),
@@ -39,15 +38,14 @@ impl EngineComponent<(&mut RenderEngine, &mut LightEngine)> for TestComponent {
 
     fn start(&mut self, (_, light_engine): (&mut RenderEngine, &mut LightEngine)) {
         light_engine.set_ambient_color([0.5, 0.5, 0.5]);
-        light_engine.add_light(0, LightUniform::new(
+        light_engine.add_light(Light::point(
             [
                 5.0, 
                 5.0, 
                 5.0
             ], 
             [1.0, 1.0, 1.0],
-            [0.0, 1.0, 0.0],
-            f32::MAX, 1.0, 1000.0
+            200.0
         ));
     }
 
@@ -65,7 +63,7 @@ impl EngineComponent<(&mut RenderEngine, &mut LightEngine)> for TestComponent {
     
     fn render<'rpass>(&'rpass mut self, engine: &'rpass RenderEngine, pass: &mut wgpu::RenderPass<'rpass>) {
         self.camera.bind(pass, engine, 0);
-        self.model.draw(pass, &self.instance_buffer, 1);
+        self.model.draw(pass, engine, &self.instance_buffer, 1);
     }
 
     fn exit(&mut self, _: (&mut RenderEngine, &mut LightEngine)) {}
@@ -80,15 +78,16 @@ create_app! {
     },
 
     PASSES {
-        0: {
+        "main": {
+            COLOR: ["forte.surface"],
+            DEPTH: "forte.depth",
             PARTS: [
                 {
                     PIPELINE: "forte.gltf",
                     PREPARE: [light_engine],
                     RENDER: test,
                 }
-            ],
-            DEPTH: true
+            ]
         }
     }
 }
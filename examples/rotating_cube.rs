@@ -1,5 +1,5 @@
 use cgmath::{Rotation3, Quaternion};
-use forte_engine::{end_render, inputs::winit_input::EngineInput, math::transforms::Transform, pass, primitives::{cameras::Camera, mesh::Mesh, textures::Texture, transforms::TransformRaw, vertices::Vertex}, render::{pipelines::Pipeline, render_engine::*}, run_app, start_render, utils::{camera_controller::CameraController, resources::Handle}, EngineApp};
+use forte_engine::{end_render, inputs::winit_input::EngineInput, math::transforms::Transform, pass, primitives::{cameras::{Camera, Projection}, mesh::Mesh, textures::Texture, transforms::TransformRaw, vertices::Vertex}, render::{pipelines::Pipeline, render_engine::*}, run_app, start_render, utils::{camera_controller::CameraController, resources::Handle}, EngineApp};
 use wgpu::util::DeviceExt;
 use winit::event::ElementState;
 
@@ -58,8 +58,7 @@ impl EngineApp for MainApp {
         // generate camera
         let mut camera = Camera::new(
             &engine, 
-            engine.config.width as f32 / engine.config.height as f32,
-            45.0, 0.1, 100.0
+            Projection::Perspective { fovy: 45.0, znear: 0.1, zfar: 100.0 }
         );
         camera.position = (0.0, 0.0, 5.0).into();
         camera.update(&mut engine);
@@ -98,13 +97,13 @@ impl EngineApp for MainApp {
     fn start(&mut self) {}
 
     fn input(&mut self, input: EngineInput) {
-        // display all inputs except mouse move
+        // display all inputs except mouse movement
         match input {
             EngineInput::KeyInput(key_code, state) => {
                 let pressed = matches!(state, ElementState::Pressed);
                 self.controller.key_input(key_code, pressed);
             }
-            EngineInput::MouseMove(..) => {}
+            EngineInput::MouseMove(..) | EngineInput::MouseMotion(..) => {}
             _ => println!("Received input {:?}", input)
         }
     }
@@ -0,0 +1,122 @@
+use cgmath::Quaternion;
+use forte_engine::{component_app::EngineComponent, create_app, math::{quaternion::QuaternionExt, transforms::Transform}, primitives::{cameras::{Camera, Projection, Viewport}, mesh::Mesh, textures::Texture, transforms::TransformRaw, vertices::Vertex}, render::pipelines::Pipeline, run_app, utils::resources::Handle};
+
+const VERTICES: &[Vertex] = &[
+    Vertex { position: [ 0.5, -0.5, -0.5], tex_coords: [0.4131759, 0.00759614], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [ 0.5, -0.5,  0.5], tex_coords: [0.0048659444, 0.43041354], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [-0.5, -0.5,  0.5], tex_coords: [0.28081453, 0.949397], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [0.85967, 0.84732914], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [ 0.5,  0.5, -0.5], tex_coords: [0.9414737, 0.2652641], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [ 0.5,  0.5,  0.5], tex_coords: [0.28081453, 0.949397], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [-0.5,  0.5,  0.5], tex_coords: [0.85967, 0.84732914], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [-0.5,  0.5, -0.5], tex_coords: [0.9414737, 0.2652641], normal: [0.0, 0.0, 0.0] },
+];
+
+const INDICES: &[u16] = &[
+    1, 2, 3,
+    4, 7, 6,
+    4, 5, 1,
+    1, 5, 6,
+    6, 7, 3,
+    4, 0, 3,
+    0, 1, 3,
+    5, 4, 6,
+    0, 4, 1,
+    2, 1, 6,
+    2, 6, 3,
+    7, 4, 3
+];
+
+/// Draws a rotating cube from a perspective camera on the left of the window, and from orthographic front and top cameras stacked on the right.
+pub struct SplitScreenComponent {
+    mesh: Handle<Mesh>,
+    texture: Handle<Texture>,
+    cameras: Vec<Camera>,
+    instance_buffer: wgpu::Buffer
+}
+
+impl EngineComponent<&mut RenderEngine> for SplitScreenComponent {
+    fn create(engine: &mut RenderEngine) -> Self {
+        engine.verify_pipeline_exists("split_screen", |engine| {
+            Pipeline::new(
+                "split_screen", engine, include_str!("rotating_cube.wgsl"),
+                &[Vertex::desc(), TransformRaw::desc()],
+                &[
+                    &engine.device.create_bind_group_layout(&Camera::BIND_LAYOUT),
+                    &engine.device.create_bind_group_layout(&Texture::BIND_LAYOUT),
+                ],
+                true
+            )
+        });
+
+        // the perspective camera fills the left half of the window
+        let mut perspective = Camera::new(engine, Projection::Perspective { fovy: 45.0, znear: 0.1, zfar: 100.0 });
+        perspective.viewport = Viewport::new(0.0, 0.0, 0.5, 1.0);
+        perspective.position = (2.0, 1.5, 3.0).into();
+        perspective.look_at((0.0, 0.0, 0.0).into(), cgmath::Vector3::unit_y());
+
+        // the orthographic cameras are stacked on the right half
+        let mut front = Camera::new(engine, Projection::Orthographic { size: 2.5, znear: 0.1, zfar: 10.0 });
+        front.viewport = Viewport::new(0.5, 0.0, 0.5, 0.5);
+        front.order = 1;
+        front.position = (0.0, 0.0, 5.0).into();
+
+        let mut top = Camera::new(engine, Projection::Orthographic { size: 2.5, znear: 0.1, zfar: 10.0 });
+        top.viewport = Viewport::new(0.5, 0.5, 0.5, 0.5);
+        top.order = 2;
+        top.position = (0.0, 5.0, 0.0).into();
+        top.look_at((0.0, 0.0, 0.0).into(), -cgmath::Vector3::unit_z());
+
+        // render the cameras in order
+        let mut cameras = vec![top, front, perspective];
+        Camera::sort_by_order(&mut cameras);
+
+        Self {
+            mesh: engine.create_mesh("split_screen_cube", VERTICES, INDICES),
+            texture: engine.create_texture("split_screen_cube", include_bytes!("rotating_cube.png")),
+            cameras,
+            instance_buffer: TransformRaw::buffer_from_generic(engine, &[Transform::default()])
+        }
+    }
+
+    fn start(&mut self, _: &mut RenderEngine) {}
+
+    fn update(&mut self, engine: &mut RenderEngine) {
+        let angle = engine.time_since_start * 45.0;
+        TransformRaw::update_buffer_generic(engine, &self.instance_buffer, &[Transform { rotation: Quaternion::euler_deg(angle, angle, 0.0), ..Default::default() }]);
+    }
+
+    fn render<'rpass>(&'rpass mut self, engine: &'rpass RenderEngine, pass: &mut wgpu::RenderPass<'rpass>) {
+        // each camera limits drawing to its viewport until the next camera is bound
+        for camera in self.cameras.iter_mut() {
+            camera.bind(pass, engine, 0);
+            engine.draw_textured_mesh(pass, &self.mesh, &self.texture, &self.instance_buffer, 1);
+        }
+    }
+
+    fn exit(&mut self, _: &mut RenderEngine) {}
+}
+
+create_app! {
+    CLEAR_COLOR = wgpu::Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 },
+
+    APP {
+        split_screen: SplitScreenComponent[render_engine]
+    },
+
+    PASSES {
+        "main": {
+            COLOR: ["forte.surface"],
+            DEPTH: "forte.depth",
+            PARTS: [
+                {
+                    PIPELINE: "split_screen",
+                    PREPARE: [],
+                    RENDER: split_screen,
+                }
+            ]
+        }
+    }
+}
+
+fn main() { pollster::block_on(run_app::<App>()) }
@@ -1,5 +1,5 @@
 use cgmath::Quaternion;
-use forte_engine::{component_app::EngineComponent, create_app, egui::EguiEngine, math::{quaternion::QuaternionExt, transforms::Transform}, primitives::{cameras::Camera, mesh::Mesh, textures::Texture, transforms::TransformRaw, vertices::Vertex}, render::pipelines::Pipeline, run_app, ui::{elements::UIElement, style::{Color, PositionSetting, Sizing, Style}, UIEngine}, utils::resources::Handle};
+use forte_engine::{component_app::EngineComponent, create_app, egui::EguiEngine, math::{quaternion::QuaternionExt, transforms::Transform}, primitives::{cameras::{Camera, Projection}, mesh::Mesh, textures::Texture, transforms::TransformRaw, vertices::Vertex}, render::pipelines::Pipeline, run_app, ui::{elements::UIElement, style::{Color, PositionSetting, Sizing, Style}, UIEngine}, utils::resources::Handle};
 
 const VERTICES: &[Vertex] = &[
     Vertex { position: [ 0.5, -0.5, -0.5], tex_coords: [0.4131759, 0.00759614], normal: [0.0, 0.0, 0.0] },
@@ -42,8 +42,7 @@ impl EngineComponent<(&mut RenderEngine, &mut UIEngine, &mut EguiEngine)> for Te
         // generate camera
         let mut camera = Camera::new(
             &engine, 
-            engine.config.width as f32 / engine.config.height as f32,
-            45.0, 0.1, 100.0
+            Projection::Perspective { fovy: 45.0, znear: 0.1, zfar: 100.0 }
         );
         camera.position = (0.0, 0.0, 5.0).into();
         camera.update(engine);
@@ -147,30 +146,16 @@ create_app! {
     },
 
     PASSES {
-        0: {
+        "main": {
+            COLOR: ["forte.surface"],
+            DEPTH: "forte.depth",
             PARTS: [
                 {
                     PIPELINE: "forte.test",
                     PREPARE: [],
                     RENDER: test,
                 }
-            ],
-            DEPTH: true
-        },
-        1: {
-            PARTS: [
-                {
-                    PIPELINE: "forte.ui",
-                    PREPARE: [],
-                    RENDER: ui_engine,
-                },
-                {
-                    PIPELINE: "forte.ui",
-                    PREPARE: [],
-                    RENDER: egui,
-                }
-            ],
-            DEPTH: false
+            ]
         }
     }
 }
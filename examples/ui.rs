@@ -68,18 +68,7 @@ create_app! {
         test: TestComponent[render_engine, ui_engine]
     },
 
-    PASSES {
-        0: {
-            PARTS: [
-                {
-                    PIPELINE: "forte.ui",
-                    PREPARE: [],
-                    RENDER: ui_engine,
-                }
-            ],
-            DEPTH: false
-        }
-    }
+    PASSES {}
 }
 
 fn main() { pollster::block_on(run_app::<App>()) }
\ No newline at end of file
@@ -0,0 +1,79 @@
+struct AutoExposure {
+    histogram: vec4<f32>, // FORMAT: min log2 luminance, log2 luminance range, low percentile, high percentile
+    adaption: vec4<f32> // FORMAT: min exposure in stops, max exposure in stops, adaption speed, seconds since the last frame
+}
+@group(0) @binding(0)
+var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> settings: AutoExposure;
+@group(0) @binding(2)
+var<storage, read_write> histogram: array<atomic<u32>, 256>;
+@group(0) @binding(3)
+var<storage, read_write> exposure: vec4<f32>; // FORMAT: exposure multiplier, exposure in stops, average luminance, unused
+
+var<workgroup> local_histogram: array<atomic<u32>, 256>;
+var<workgroup> counts: array<u32, 256>;
+
+// the histogram bin of the given color, bin 0 holds colors too dark to count
+fn luminance_bin(color: vec3<f32>) -> u32 {
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    if (luminance < 0.0001) { return 0u; }
+    let t = clamp((log2(luminance) - settings.histogram.x) / settings.histogram.y, 0.0, 1.0);
+    return u32(t * 254.0 + 1.0);
+}
+
+// the log2 luminance in the middle of the given histogram bin
+fn bin_log_luminance(bin: u32) -> f32 {
+    return (f32(bin) - 0.5) / 254.0 * settings.histogram.y + settings.histogram.x;
+}
+
+// counts the luminance of each pixel into the histogram, one invocation per pixel
+@compute @workgroup_size(16, 16)
+fn cs_histogram(@builtin(global_invocation_id) id: vec3<u32>, @builtin(local_invocation_index) index: u32) {
+    atomicStore(&local_histogram[index], 0u);
+    workgroupBarrier();
+
+    let size = textureDimensions(hdr_texture);
+    if (id.x < size.x && id.y < size.y) {
+        let color = textureLoad(hdr_texture, vec2<i32>(id.xy), 0).rgb;
+        atomicAdd(&local_histogram[luminance_bin(color)], 1u);
+    }
+    workgroupBarrier();
+
+    atomicAdd(&histogram[index], atomicLoad(&local_histogram[index]));
+}
+
+// averages the luminance between the percentiles of the histogram and moves the exposure towards the exposure that brings it to middle grey, one invocation per bin
+@compute @workgroup_size(256)
+fn cs_average(@builtin(local_invocation_index) index: u32) {
+    // read and clear the histogram for the next frame
+    counts[index] = atomicLoad(&histogram[index]);
+    atomicStore(&histogram[index], 0u);
+    workgroupBarrier();
+    if (index != 0u) { return; }
+
+    var total = 0u;
+    for (var bin = 1u; bin < 256u; bin += 1u) { total += counts[bin]; }
+    let low = f32(total) * settings.histogram.z;
+    let high = f32(total) * settings.histogram.w;
+
+    // only the part of each bin between the percentiles counts towards the average
+    var seen = 0.0;
+    var sum = 0.0;
+    var weight = 0.0;
+    for (var bin = 1u; bin < 256u; bin += 1u) {
+        let count = f32(counts[bin]);
+        let inside = max(min(seen + count, high) - max(seen, low), 0.0);
+        sum += inside * bin_log_luminance(bin);
+        weight += inside;
+        seen += count;
+    }
+    if (weight <= 0.0) { return; }
+
+    // middle grey has a luminance of 0.18
+    let average = sum / weight;
+    let target_stops = clamp(log2(0.18) - average, settings.adaption.x, settings.adaption.y);
+    let blend = 1.0 - exp(-settings.adaption.w * settings.adaption.z);
+    let stops = mix(exposure.y, target_stops, blend);
+    exposure = vec4<f32>(exp2(stops), stops, exp2(average), 0.0);
+}
@@ -0,0 +1,60 @@
+// the frustum planes of the camera and the bounds of the mesh being culled
+struct Culling {
+    planes: array<vec4<f32>, 6>,
+    center: vec4<f32>, // FORMAT: center of the mesh bounds, unused
+    extents: vec4<f32>, // FORMAT: half the size of the mesh bounds, unused
+    counts: vec4<u32> // FORMAT: number of instances, unused, unused, unused
+}
+
+// the arguments of draw_indexed_indirect, the instance count is incremented for every visible instance
+struct DrawIndexedIndirect {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32
+}
+
+// the number of floats in a TransformRaw, a model matrix followed by a normal matrix
+const TRANSFORM_FLOATS: u32 = 25u;
+
+@group(0) @binding(0)
+var<uniform> culling: Culling;
+@group(0) @binding(1)
+var<storage, read> instances: array<f32>;
+@group(0) @binding(2)
+var<storage, read_write> visible: array<f32>;
+@group(0) @binding(3)
+var<storage, read_write> draw: DrawIndexedIndirect;
+
+// resets the number of visible instances before they are culled
+@compute @workgroup_size(1)
+fn cs_reset() {
+    atomicStore(&draw.instance_count, 0u);
+}
+
+// tests one instance against the frustum and copies it to the end of the visible instances if any of it can be seen
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= culling.counts.x) { return; }
+    let base = id.x * TRANSFORM_FLOATS;
+
+    // move the bounds of the mesh with the instance's model matrix
+    let x = vec3<f32>(instances[base], instances[base + 1u], instances[base + 2u]);
+    let y = vec3<f32>(instances[base + 4u], instances[base + 5u], instances[base + 6u]);
+    let z = vec3<f32>(instances[base + 8u], instances[base + 9u], instances[base + 10u]);
+    let w = vec3<f32>(instances[base + 12u], instances[base + 13u], instances[base + 14u]);
+    let center = x * culling.center.x + y * culling.center.y + z * culling.center.z + w;
+    let extents = abs(x) * culling.extents.x + abs(y) * culling.extents.y + abs(z) * culling.extents.z;
+
+    // skip the instance if its bounds are completely outside any plane
+    for (var i = 0u; i < 6u; i++) {
+        let plane = culling.planes[i];
+        if (dot(plane.xyz, center) + plane.w + dot(abs(plane.xyz), extents) < 0.0) { return; }
+    }
+
+    let slot = atomicAdd(&draw.instance_count, 1u) * TRANSFORM_FLOATS;
+    for (var i = 0u; i < TRANSFORM_FLOATS; i++) {
+        visible[slot + i] = instances[base + i];
+    }
+}
@@ -0,0 +1,34 @@
+#import ./model.wgsl as Model
+#import ./morph.wgsl as Morph
+
+struct VertexInput {
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(12) tangent: vec4<f32>
+}
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: Model::InstanceInput
+) -> Model::VertexOutput {
+    // apply morph targets before any transforms
+    let morphed = Morph::morph(model.vertex_index, model.instance_index, model.position, model.normal);
+
+    // the models position, rotation, scale, combined with the nodes world transform
+    let model_matrix = Model::instance_model_matrix(instance) * Model::node.model;
+
+    // used to rotate the normals
+    let node_normal = Model::node.normal;
+    let normal_matrix = Model::instance_normal_matrix(instance) * mat3x3<f32>(node_normal[0].xyz, node_normal[1].xyz, node_normal[2].xyz);
+
+    return Model::vertex_output(model_matrix, normal_matrix, morphed.position, model.tex_coords, morphed.normal, model.tangent);
+}
+
+@fragment
+fn fs_main(in: Model::VertexOutput) -> @location(0) vec4<f32> {
+    return Model::shade(in);
+}
@@ -0,0 +1,30 @@
+#import ./model.wgsl as Model
+#import ./skin.wgsl as Skin
+
+struct SkinnedVertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(12) tangent: vec4<f32>,
+    @location(3) joints: vec4<u32>,
+    @location(4) weights: vec4<f32>
+}
+
+@vertex
+fn vs_main(
+    model: SkinnedVertexInput,
+    instance: Model::InstanceInput
+) -> Model::VertexOutput {
+    let skin_matrix = Skin::skin_matrix(model.joints, model.weights);
+    let model_matrix = Model::instance_model_matrix(instance) * skin_matrix;
+
+    // used to rotate the normals, joints are assumed to be uniformly scaled
+    let normal_matrix = Model::instance_normal_matrix(instance) * mat3x3<f32>(skin_matrix[0].xyz, skin_matrix[1].xyz, skin_matrix[2].xyz);
+
+    return Model::vertex_output(model_matrix, normal_matrix, model.position, model.tex_coords, model.normal, model.tangent);
+}
+
+@fragment
+fn fs_main(in: Model::VertexOutput) -> @location(0) vec4<f32> {
+    return Model::shade(in);
+}
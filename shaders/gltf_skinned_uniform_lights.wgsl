@@ -0,0 +1,18 @@
+#define UNIFORM_LIGHTS
+#import ./gltf_skinned.wgsl as GltfSkinned
+#import ./model.wgsl as Model
+
+// gltf_skinned.wgsl for devices without storage buffers, like WebGL, lights are read from a uniform buffer instead
+
+@vertex
+fn vs_main(
+    model: GltfSkinned::SkinnedVertexInput,
+    instance: Model::InstanceInput
+) -> Model::VertexOutput {
+    return GltfSkinned::vs_main(model, instance);
+}
+
+@fragment
+fn fs_main(in: Model::VertexOutput) -> @location(0) vec4<f32> {
+    return GltfSkinned::fs_main(in);
+}
@@ -0,0 +1,18 @@
+#define UNIFORM_LIGHTS
+#import ./gltf.wgsl as Gltf
+#import ./model.wgsl as Model
+
+// gltf.wgsl for devices without storage buffers, like WebGL, lights are read from a uniform buffer instead
+
+@vertex
+fn vs_main(
+    model: Gltf::VertexInput,
+    instance: Model::InstanceInput
+) -> Model::VertexOutput {
+    return Gltf::vs_main(model, instance);
+}
+
+@fragment
+fn fs_main(in: Model::VertexOutput) -> @location(0) vec4<f32> {
+    return Gltf::fs_main(in);
+}
@@ -1,39 +1,191 @@
 struct Light {
     position: vec3<f32>,
-    range: f32,
-    color: vec3<f32>,
-    exponent: f32,
-    direction: vec3<f32>, 
-    cutoff: f32
+    inverse_range_squared: f32, // 0 if the light has no range
+    color: vec3<f32>, // multiplied by the lights intensity
+    kind: u32, // 0 = directional, 1 = point, 2 = spot
+    direction: vec3<f32>,
+    shadow_layer: i32, // -1 if the light casts no shadows
+    spot_scale: f32,
+    spot_offset: f32
 }
+#ifdef UNIFORM_LIGHTS
+// devices without storage buffers, like WebGL, store a limited number of lights in a uniform buffer and light every fragment with all of them
 @group(2) @binding(0)
-var<uniform> lights: array<Light, 1024u>;
+var<uniform> lights: array<Light, 256u>;
+#else
+@group(2) @binding(0)
+var<storage, read> lights: array<Light>;
+#endif
 @group(2) @binding(1)
 var<uniform> num_lights: vec4<u32>;
 @group(2) @binding(2)
 var<uniform> ambient_light: vec4<f32>;
 
+struct Shadows {
+    matrices: array<mat4x4<f32>, 32u>,
+    layers: array<vec4<f32>, 32u>, // FORMAT: texel size in world units or at a distance of 1, 1 if perspective
+    settings: vec4<f32> // FORMAT: normal_bias, pcf_radius, cascades, resolution
+}
+@group(2) @binding(3)
+var shadow_maps: texture_depth_2d_array;
+@group(2) @binding(4)
+var shadow_sampler: sampler_comparison;
+@group(2) @binding(5)
+var<uniform> shadows: Shadows;
+
+// the view is split into a grid of clusters, each listing the lights that reach it
+struct Clusters {
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+    inverse_projection: mat4x4<f32>,
+    grid: vec4<u32>, // FORMAT: clusters along x, y and z (all 0 until the clusters are built), max lights per cluster
+    depth: vec4<f32> // FORMAT: near, far, log(far / near), unused
+}
+#ifndef UNIFORM_LIGHTS
+@group(2) @binding(6)
+var<uniform> clusters: Clusters;
+@group(2) @binding(7)
+var<storage, read> cluster_counts: array<u32>;
+@group(2) @binding(8)
+var<storage, read> cluster_indices: array<u32>;
+#endif
+
+// the cluster index of positions outside the view the clusters were built for, like those seen by other cameras, which are lit by every light
+const NO_CLUSTER: u32 = 0xffffffffu;
+
+// the index of the cluster containing the given position, or NO_CLUSTER if it is outside the clusters
+fn cluster_index(position: vec3<f32>) -> u32 {
+#ifdef UNIFORM_LIGHTS
+    return 0u;
+#else
+    let grid = clusters.grid;
+    let view_position = clusters.view * vec4<f32>(position, 1.0);
+    let clip = clusters.projection * view_position;
+    if (clip.w <= 0.0 || -view_position.z > clusters.depth.y) { return NO_CLUSTER; }
+    let unclamped_uv = clip.xy / clip.w * vec2<f32>(0.5, -0.5) + 0.5;
+    if (any(unclamped_uv < vec2<f32>(0.0)) || any(unclamped_uv > vec2<f32>(1.0))) { return NO_CLUSTER; }
+    let uv = min(unclamped_uv, vec2<f32>(0.9999));
+    let tile = vec2<u32>(uv * vec2<f32>(grid.xy));
+
+    // clusters are sliced exponentially along the view depth so that they stay roughly cube shaped
+    let depth = max(-view_position.z, clusters.depth.x);
+    let slice = u32(clamp(log(depth / clusters.depth.x) / clusters.depth.z * f32(grid.z), 0.0, f32(grid.z - 1u)));
+    return tile.x + tile.y * grid.x + slice * grid.x * grid.y;
+#endif
+}
+
+// the number of lights that reach the given cluster
+fn cluster_light_count(cluster: u32) -> u32 {
+#ifdef UNIFORM_LIGHTS
+    return min(num_lights.x, 256u);
+#else
+    if (clusters.grid.x == 0u || cluster == NO_CLUSTER) { return num_lights.x; }
+    return cluster_counts[cluster];
+#endif
+}
+
+// the index in lights of the given light of the given cluster
+fn cluster_light_index(cluster: u32, index: u32) -> u32 {
+#ifdef UNIFORM_LIGHTS
+    return index;
+#else
+    if (clusters.grid.x == 0u || cluster == NO_CLUSTER) { return index; }
+    return cluster_indices[cluster * clusters.grid.w + index];
+#endif
+}
+
+// filters the shadow map at the given layer around the given position, returning 1 if lit, 0 if in shadow, and -1 if the position is outside the map
+fn sample_shadow_layer(layer: i32, position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    // push the position along its normal by a number of texels to prevent surfaces shadowing themselves
+    let params = shadows.layers[layer];
+    var texel = params.x;
+    if (params.y > 0.0) { texel *= abs((shadows.matrices[layer] * vec4<f32>(position, 1.0)).w); }
+    let offset_position = position + normal * shadows.settings.x * texel;
+
+    let clip = shadows.matrices[layer] * vec4<f32>(offset_position, 1.0);
+    if (clip.w <= 0.0) { return -1.0; }
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0) { return -1.0; }
+
+    // percentage closer filtering, average the comparisons of the texels around the position
+    let radius = i32(shadows.settings.y);
+    let texel_size = 1.0 / shadows.settings.w;
+    var lit = 0.0;
+    for (var x = -radius; x <= radius; x += 1) {
+        for (var y = -radius; y <= radius; y += 1) {
+            lit += textureSampleCompareLevel(shadow_maps, shadow_sampler, uv + vec2<f32>(f32(x), f32(y)) * texel_size, layer, ndc.z);
+        }
+    }
+    let samples = f32((2 * radius + 1) * (2 * radius + 1));
+    return lit / samples;
+}
+
+// calculates how much of the given light reaches the given position, 1 if fully lit and 0 if fully shadowed
+fn shadow(light: Light, position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if (light.shadow_layer < 0) { return 1.0; }
+
+    // directional lights use the first cascade that contains the position
+    if (light.kind == 0u) {
+        for (var cascade = 0; cascade < i32(shadows.settings.z); cascade += 1) {
+            let lit = sample_shadow_layer(light.shadow_layer + cascade, position, normal);
+            if (lit >= 0.0) { return lit; }
+        }
+        return 1.0;
+    }
+
+    // point lights pick the face of their cube facing the position, ordered +x, -x, +y, -y, +z, -z
+    var layer = light.shadow_layer;
+    if (light.kind == 1u) {
+        let delta = position - light.position;
+        let size = abs(delta);
+        if (size.x >= size.y && size.x >= size.z) { layer += select(1, 0, delta.x > 0.0); }
+        else if (size.y >= size.z) { layer += select(3, 2, delta.y > 0.0); }
+        else { layer += select(5, 4, delta.z > 0.0); }
+    }
+
+    let lit = sample_shadow_layer(layer, position, normal);
+    return select(lit, 1.0, lit < 0.0);
+}
+
+// the direction from the given position towards the given light
+fn light_direction(light: Light, position: vec3<f32>) -> vec3<f32> {
+    if (light.kind == 0u) { return -light.direction; }
+    return normalize(light.position - position);
+}
+
+// how much of the given lights intensity reaches the given position, lights fall off with the inverse square of their distance and smoothly reach 0 at their range
+fn light_attenuation(light: Light, position: vec3<f32>) -> f32 {
+    if (light.kind == 0u) { return 1.0; }
+
+    let delta = light.position - position;
+    let distance_squared = max(dot(delta, delta), 0.0001);
+    let range_factor = distance_squared * light.inverse_range_squared;
+    let range_window = clamp(1.0 - range_factor * range_factor, 0.0, 1.0);
+    var attenuation = range_window * range_window / distance_squared;
+
+    // spot lights fade out between their inner and outer cones
+    if (light.kind == 2u) {
+        let cone = clamp(dot(light.direction, -normalize(delta)) * light.spot_scale + light.spot_offset, 0.0, 1.0);
+        attenuation *= cone * cone;
+    }
+    return attenuation;
+}
+
 fn calculate_lights(view_pos: vec3<f32>, position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
     var light_color = vec3<f32>(0.0, 0.0, 0.0);
-    for (var i = 0u; i < num_lights.x; i += 1u) {
+    let cluster = cluster_index(position);
+    let count = cluster_light_count(cluster);
+    for (var i = 0u; i < count; i += 1u) {
         // calculate basic light values
-        let light = lights[i];
-        let delta = light.position - position;
-        let distance = length(delta);
-        if (distance > light.range) { continue; }
-
-        // do cutoff checks if necessary
-        if (light.cutoff < 100.0 && dot(light.direction, normalize(position - light.position)) < light.cutoff) { continue; }
+        let light = lights[cluster_light_index(cluster, i)];
+        let light_strength = light_attenuation(light, position);
+        if (light_strength <= 0.0) { continue; }
 
         // get some directions to make our lives easy
-        let light_dir = normalize(delta);
+        let light_dir = light_direction(light, position);
         let view_dir = normalize(view_pos - position);
         let half_dir = normalize(view_dir + light_dir);
-        let reflect_dir = reflect(-light_dir, normal);
-
-        // calculate the lights "strength"
-        var light_strength = 1.0;
-        if (light.exponent != 0.0) { light_strength = 1.0 / (pow(distance, light.exponent) + 1.0); }
 
         // calculate the diffuse component
         let diffuse_strength = max(dot(normal, light_dir), 0.0);
@@ -44,7 +196,68 @@ fn calculate_lights(view_pos: vec3<f32>, position: vec3<f32>, normal: vec3<f32>)
         let specular_color = specular_strength * light.color;
 
         // calculate and append final light color
-        light_color += (diffuse_color + specular_color) * light_strength;
+        light_color += (diffuse_color + specular_color) * light_strength * shadow(light, position, normal);
     }
     return light_color + ambient_light.xyz;
-}
\ No newline at end of file
+}
+const PI: f32 = 3.14159265359;
+
+// GGX / Trowbridge-Reitz normal distribution, how many microfacets face the half vector
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denominator * denominator);
+}
+
+// smith geometry term with schlick-ggx, how many microfacets are not shadowed or masked
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return ggx_v * ggx_l;
+}
+
+// schlick fresnel approximation, how much light is reflected instead of refracted
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// calculates the light reflected towards the viewer using a cook-torrance brdf, the ambient light is scaled by the given occlusion
+fn calculate_pbr_lights(view_pos: vec3<f32>, position: vec3<f32>, normal: vec3<f32>, albedo: vec3<f32>, metallic: f32, roughness: f32, occlusion: f32, receive_shadows: bool) -> vec3<f32> {
+    let view_dir = normalize(view_pos - position);
+    let n_dot_v = max(dot(normal, view_dir), 0.0001);
+
+    // dielectrics reflect 4% of light head on, metals reflect their albedo
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+
+    var light_color = vec3<f32>(0.0, 0.0, 0.0);
+    let cluster = cluster_index(position);
+    let count = cluster_light_count(cluster);
+    for (var i = 0u; i < count; i += 1u) {
+        // calculate basic light values
+        let light = lights[cluster_light_index(cluster, i)];
+        let attenuation = light_attenuation(light, position);
+        if (attenuation <= 0.0) { continue; }
+
+        let light_dir = light_direction(light, position);
+        let n_dot_l = max(dot(normal, light_dir), 0.0);
+        if (n_dot_l <= 0.0) { continue; }
+        let half_dir = normalize(view_dir + light_dir);
+
+        var radiance = light.color * attenuation;
+        if (receive_shadows) { radiance *= shadow(light, position, normal); }
+
+        // specular reflection
+        let fresnel = fresnel_schlick(max(dot(half_dir, view_dir), 0.0), f0);
+        let distribution = distribution_ggx(max(dot(normal, half_dir), 0.0), roughness);
+        let geometry = geometry_smith(n_dot_v, n_dot_l, roughness);
+        let specular = distribution * geometry * fresnel / (4.0 * n_dot_v * n_dot_l + 0.0001);
+
+        // refracted light is diffused, metals have no diffuse
+        let diffuse = (vec3<f32>(1.0) - fresnel) * (1.0 - metallic) * albedo / PI;
+
+        light_color += (diffuse + specular) * radiance * n_dot_l;
+    }
+    return light_color + ambient_light.xyz * albedo * occlusion;
+}
@@ -0,0 +1,68 @@
+#import ./light.wgsl as Lights
+
+@group(0) @binding(0)
+var<uniform> clusters: Lights::Clusters;
+@group(0) @binding(1)
+var<storage, read> lights: array<Lights::Light>;
+@group(0) @binding(2)
+var<uniform> num_lights: vec4<u32>;
+@group(0) @binding(3)
+var<storage, read_write> cluster_counts: array<u32>;
+@group(0) @binding(4)
+var<storage, read_write> cluster_indices: array<u32>;
+
+// the view space point at the given normalized device coordinates and depth in the given view
+fn unproject(ndc: vec3<f32>) -> vec3<f32> {
+    let point = clusters.inverse_projection * vec4<f32>(ndc, 1.0);
+    return point.xyz / point.w;
+}
+
+// the view space point that projects onto the given normalized device coordinates at the given view depth, two points along the line of points that project onto the coordinates are used so that any projection works
+fn view_point(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+    let start = unproject(vec3<f32>(ndc, 0.25));
+    let end = unproject(vec3<f32>(ndc, 0.75));
+    return mix(start, end, (-depth - start.z) / (end.z - start.z));
+}
+
+// returns true if the given light can reach the given view space box
+fn light_reaches(light: Lights::Light, box_min: vec3<f32>, box_max: vec3<f32>) -> bool {
+    // directional lights and lights without a range reach everything
+    if (light.kind == 0u || light.inverse_range_squared <= 0.0) { return true; }
+
+    let center = (clusters.view * vec4<f32>(light.position, 1.0)).xyz;
+    let delta = clamp(center, box_min, box_max) - center;
+    return dot(delta, delta) * light.inverse_range_squared <= 1.0;
+}
+
+// lists the lights that reach each cluster, one invocation per cluster
+@compute @workgroup_size(4, 4, 4)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let grid = clusters.grid;
+    if (any(id >= grid.xyz)) { return; }
+    let cluster = id.x + id.y * grid.x + id.z * grid.x * grid.y;
+
+    // find the bounds of the cluster in view space, tiles are ordered from the top left of the screen
+    let tile_size = 2.0 / vec2<f32>(grid.xy);
+    let ndc_min = vec2<f32>(-1.0 + f32(id.x) * tile_size.x, 1.0 - f32(id.y + 1u) * tile_size.y);
+    let ndc_max = ndc_min + tile_size;
+    let near = clusters.depth.x * exp(clusters.depth.z * f32(id.z) / f32(grid.z));
+    let far = clusters.depth.x * exp(clusters.depth.z * f32(id.z + 1u) / f32(grid.z));
+    var box_min = vec3<f32>(3.4e38);
+    var box_max = vec3<f32>(-3.4e38);
+    for (var corner = 0u; corner < 8u; corner += 1u) {
+        let ndc = select(ndc_min, ndc_max, vec2<bool>((corner & 1u) != 0u, (corner & 2u) != 0u));
+        let point = view_point(ndc, select(near, far, (corner & 4u) != 0u));
+        box_min = min(box_min, point);
+        box_max = max(box_max, point);
+    }
+
+    // list the lights that reach the cluster, lights past the limit of the cluster are ignored
+    var count = 0u;
+    for (var i = 0u; i < num_lights.x && count < grid.w; i += 1u) {
+        if (light_reaches(lights[i], box_min, box_max)) {
+            cluster_indices[cluster * grid.w + count] = i;
+            count += 1u;
+        }
+    }
+    cluster_counts[cluster] = count;
+}
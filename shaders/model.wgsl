@@ -0,0 +1,128 @@
+#import ./light.wgsl as Lights
+
+struct CameraUniform {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct NodeUniform {
+    model: mat4x4<f32>,
+    normal: mat4x4<f32>,
+};
+@group(3) @binding(0)
+var<uniform> node: NodeUniform;
+
+// naga_oil does not allow identifiers ending in a digit in imported modules, so the matrix columns are named x, y, z, w
+struct InstanceInput {
+    @location(5) model_matrix_x: vec4<f32>,
+    @location(6) model_matrix_y: vec4<f32>,
+    @location(7) model_matrix_z: vec4<f32>,
+    @location(8) model_matrix_w: vec4<f32>,
+    @location(9) normal_matrix_x: vec3<f32>,
+    @location(10) normal_matrix_y: vec3<f32>,
+    @location(11) normal_matrix_z: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) world_tangent: vec4<f32>,
+}
+
+// the models position, rotation, scale from the instance buffer
+fn instance_model_matrix(instance: InstanceInput) -> mat4x4<f32> {
+    return mat4x4<f32>(
+        instance.model_matrix_x,
+        instance.model_matrix_y,
+        instance.model_matrix_z,
+        instance.model_matrix_w,
+    );
+}
+
+// used to rotate the normals of the instance
+fn instance_normal_matrix(instance: InstanceInput) -> mat3x3<f32> {
+    return mat3x3<f32>(
+        instance.normal_matrix_x,
+        instance.normal_matrix_y,
+        instance.normal_matrix_z,
+    );
+}
+
+// transforms a vertex into world and clip space with the given matrices, tangents follow the surface so they are transformed by the model matrix
+fn vertex_output(model_matrix: mat4x4<f32>, normal_matrix: mat3x3<f32>, position: vec3<f32>, tex_coords: vec2<f32>, normal: vec3<f32>, tangent: vec4<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.tex_coords = tex_coords;
+    out.world_normal = normal_matrix * normal;
+    out.world_tangent = vec4<f32>((model_matrix * vec4<f32>(tangent.xyz, 0.0)).xyz, tangent.w);
+    var world_position = model_matrix * vec4<f32>(position, 1.0);
+    out.world_position = world_position.xyz;
+    out.clip_position = camera.view_proj * world_position;
+    return out;
+}
+
+@group(1) @binding(0)
+var diffuse_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var diffuse_sampler: sampler;
+@group(1) @binding(2)
+var roughness_texture: texture_2d<f32>;
+@group(1) @binding(3)
+var roughness_sampler: sampler;
+@group(1) @binding(4)
+var emissive_texture: texture_2d<f32>;
+@group(1) @binding(5)
+var emissive_sampler: sampler;
+@group(1) @binding(6)
+var normal_texture: texture_2d<f32>;
+@group(1) @binding(7)
+var normal_sampler: sampler;
+@group(1) @binding(8)
+var occlusion_texture: texture_2d<f32>;
+@group(1) @binding(9)
+var occlusion_sampler: sampler;
+@group(1) @binding(10)
+var<uniform> diffuse_color: vec4<f32>;
+@group(1) @binding(11)
+var<uniform> emissive_color: vec4<f32>;
+@group(1) @binding(12)
+var<uniform> metadata: vec4<f32>; // FORMAT: metallic_factor, roughness_factor, alpha_mode, alpha_cutoff
+@group(1) @binding(13)
+var<uniform> shadow_flags: vec4<f32>; // FORMAT: receive_shadows, unused, unused, unused
+
+// calculates the world normal of a fragment, using the sampled tangent space normal if the fragment has a tangent
+fn surface_normal(in: VertexOutput, mapped: vec3<f32>) -> vec3<f32> {
+    let normal = normalize(in.world_normal);
+    let tangent = in.world_tangent.xyz - normal * dot(normal, in.world_tangent.xyz);
+    if (dot(tangent, tangent) < 0.000001) { return normal; }
+
+    let t = normalize(tangent);
+    let b = cross(normal, t) * in.world_tangent.w;
+    return normalize(mat3x3<f32>(t, b, normal) * mapped);
+}
+
+// calculates the final color of a fragment using the nodes material and the lights
+fn shade(in: VertexOutput) -> vec4<f32> {
+    // sample all textures up front so they are sampled in uniform control flow
+    let base_color = diffuse_color * textureSample(diffuse_texture, diffuse_sampler, in.tex_coords);
+    let metallic_roughness = textureSample(roughness_texture, roughness_sampler, in.tex_coords);
+    let occlusion = textureSample(occlusion_texture, occlusion_sampler, in.tex_coords).r;
+    let emissive = emissive_color.rgb * textureSample(emissive_texture, emissive_sampler, in.tex_coords).rgb;
+    let mapped_normal = textureSample(normal_texture, normal_sampler, in.tex_coords).xyz * 2.0 - 1.0;
+    let normal = surface_normal(in, mapped_normal);
+
+    // alpha modes, 1 = opaque, 2 = mask, 3 = blend
+    var alpha = 1.0;
+    if (metadata.z == 2.0 && base_color.a < metadata.w) { discard; }
+    if (metadata.z == 3.0) { alpha = base_color.a; }
+
+    // metallic is stored in the blue channel and roughness in the green channel, roughness is clamped to avoid a singular highlight
+    let metallic = clamp(metadata.x * metallic_roughness.b, 0.0, 1.0);
+    let roughness = clamp(metadata.y * metallic_roughness.g, 0.04, 1.0);
+
+    let color = Lights::calculate_pbr_lights(camera.view_pos.xyz, in.world_position, normal, base_color.rgb, metallic, roughness, occlusion, shadow_flags.x > 0.0);
+    return vec4<f32>(color + emissive, alpha);
+}
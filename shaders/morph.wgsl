@@ -0,0 +1,40 @@
+// the position and normal offsets of one vertex for one morph target
+struct MorphDelta {
+    position: vec4<f32>,
+    normal: vec4<f32>,
+}
+// FORMAT: [vertex][target], offset to the start of the current mesh
+@group(3) @binding(2)
+var<storage, read> deltas: array<MorphDelta>;
+
+// FORMAT: the default weights of the node, followed by the weights of each instance that overrides them
+struct MorphWeights {
+    num_targets: u32,
+    num_instances: u32,
+    weights: array<f32>,
+}
+@group(3) @binding(3)
+var<storage, read> morph_weights: MorphWeights;
+
+struct MorphedVertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+}
+
+// applies the weighted deltas of all morph targets to the given vertex
+fn morph(vertex_index: u32, instance_index: u32, position: vec3<f32>, normal: vec3<f32>) -> MorphedVertex {
+    let num_targets = morph_weights.num_targets;
+    let row = select(0u, instance_index + 1u, instance_index < morph_weights.num_instances);
+
+    var out: MorphedVertex;
+    out.position = position;
+    out.normal = normal;
+    for (var i = 0u; i < num_targets; i += 1u) {
+        let weight = morph_weights.weights[row * num_targets + i];
+        if (weight == 0.0) { continue; }
+        let delta = deltas[vertex_index * num_targets + i];
+        out.position += weight * delta.position.xyz;
+        out.normal += weight * delta.normal.xyz;
+    }
+    return out;
+}
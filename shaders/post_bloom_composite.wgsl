@@ -0,0 +1,27 @@
+#import ./post_process.wgsl as PostProcess
+
+struct Bloom {
+    settings: vec4<f32>, // FORMAT: threshold, knee, intensity, radius
+    prefilter: vec4<f32> // FORMAT: 1 if this is the first downsample which applies the threshold, unused, unused, unused
+}
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var input_sampler: sampler;
+@group(1) @binding(0)
+var<uniform> bloom: Bloom;
+@group(2) @binding(0)
+var bloom_texture: texture_2d<f32>;
+@group(2) @binding(1)
+var bloom_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> PostProcess::FullscreenOutput { return PostProcess::vs_main(index); }
+
+// adds the blurred bright parts of the image back onto it
+@fragment
+fn fs_main(in: PostProcess::FullscreenOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(input_texture, input_sampler, in.uv, 0.0).rgb;
+    let glow = textureSampleLevel(bloom_texture, bloom_sampler, in.uv, 0.0).rgb;
+    return vec4<f32>(color + glow * bloom.settings.z, 1.0);
+}
@@ -0,0 +1,71 @@
+#import ./post_process.wgsl as PostProcess
+
+struct Bloom {
+    settings: vec4<f32>, // FORMAT: threshold, knee, intensity, radius
+    prefilter: vec4<f32> // FORMAT: 1 if this is the first downsample which applies the threshold, unused, unused, unused
+}
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+@group(1) @binding(0)
+var<uniform> bloom: Bloom;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> PostProcess::FullscreenOutput { return PostProcess::vs_main(index); }
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// removes everything darker than the threshold, with a soft knee so the cutoff is not visible
+fn threshold(color: vec3<f32>) -> vec3<f32> {
+    let brightness = max(color.r, max(color.g, color.b));
+    let knee = bloom.settings.x * bloom.settings.y;
+    var soft = clamp(brightness - bloom.settings.x + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.00001);
+    return color * max(soft, brightness - bloom.settings.x) / max(brightness, 0.00001);
+}
+
+// the average of four samples weighted by karis's inverse luminance, which stops single bright pixels from flickering
+fn karis_average(a: vec3<f32>, b: vec3<f32>, c: vec3<f32>, d: vec3<f32>) -> vec4<f32> {
+    let average = (a + b + c + d) * 0.25;
+    let weight = 1.0 / (1.0 + luminance(average));
+    return vec4<f32>(average * weight, weight);
+}
+
+fn sample_source(uv: vec2<f32>) -> vec3<f32> {
+    return max(textureSampleLevel(source_texture, source_sampler, uv, 0.0).rgb, vec3<f32>(0.0));
+}
+
+// the 13 tap downsample from call of duty: advanced warfare
+@fragment
+fn fs_main(in: PostProcess::FullscreenOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+    let a = sample_source(in.uv + texel * vec2<f32>(-2.0, -2.0));
+    let b = sample_source(in.uv + texel * vec2<f32>(0.0, -2.0));
+    let c = sample_source(in.uv + texel * vec2<f32>(2.0, -2.0));
+    let d = sample_source(in.uv + texel * vec2<f32>(-2.0, 0.0));
+    let e = sample_source(in.uv);
+    let f = sample_source(in.uv + texel * vec2<f32>(2.0, 0.0));
+    let g = sample_source(in.uv + texel * vec2<f32>(-2.0, 2.0));
+    let h = sample_source(in.uv + texel * vec2<f32>(0.0, 2.0));
+    let i = sample_source(in.uv + texel * vec2<f32>(2.0, 2.0));
+    let j = sample_source(in.uv + texel * vec2<f32>(-1.0, -1.0));
+    let k = sample_source(in.uv + texel * vec2<f32>(1.0, -1.0));
+    let l = sample_source(in.uv + texel * vec2<f32>(-1.0, 1.0));
+    let m = sample_source(in.uv + texel * vec2<f32>(1.0, 1.0));
+
+    // the first downsample weights each group of samples with karis's average, then applies the threshold
+    if (bloom.prefilter.x > 0.0) {
+        let sum = karis_average(j, k, l, m) * 0.5
+            + karis_average(a, b, d, e) * 0.125
+            + karis_average(b, c, e, f) * 0.125
+            + karis_average(d, e, g, h) * 0.125
+            + karis_average(e, f, h, i) * 0.125;
+        return vec4<f32>(threshold(sum.rgb / sum.a), 1.0);
+    }
+
+    let color = e * 0.125 + (a + c + g + i) * 0.03125 + (b + d + f + h) * 0.0625 + (j + k + l + m) * 0.125;
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,39 @@
+#import ./post_process.wgsl as PostProcess
+
+struct Bloom {
+    settings: vec4<f32>, // FORMAT: threshold, knee, intensity, radius
+    prefilter: vec4<f32> // FORMAT: 1 if this is the first downsample which applies the threshold, unused, unused, unused
+}
+@group(0) @binding(0)
+var lower_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var lower_sampler: sampler;
+@group(1) @binding(0)
+var<uniform> bloom: Bloom;
+@group(2) @binding(0)
+var current_texture: texture_2d<f32>;
+@group(2) @binding(1)
+var current_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> PostProcess::FullscreenOutput { return PostProcess::vs_main(index); }
+
+fn sample_lower(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(lower_texture, lower_sampler, uv, 0.0).rgb;
+}
+
+// blurs the lower mip up with a 9 tap tent filter and adds it to the downsampled mip of this size
+@fragment
+fn fs_main(in: PostProcess::FullscreenOutput) -> @location(0) vec4<f32> {
+    let texel = bloom.settings.w / vec2<f32>(textureDimensions(lower_texture));
+    var color = sample_lower(in.uv) * 4.0;
+    color += (
+        sample_lower(in.uv + texel * vec2<f32>(0.0, -1.0)) + sample_lower(in.uv + texel * vec2<f32>(-1.0, 0.0))
+        + sample_lower(in.uv + texel * vec2<f32>(1.0, 0.0)) + sample_lower(in.uv + texel * vec2<f32>(0.0, 1.0))
+    ) * 2.0;
+    color += sample_lower(in.uv + texel * vec2<f32>(-1.0, -1.0)) + sample_lower(in.uv + texel * vec2<f32>(1.0, -1.0))
+        + sample_lower(in.uv + texel * vec2<f32>(-1.0, 1.0)) + sample_lower(in.uv + texel * vec2<f32>(1.0, 1.0));
+
+    let current = textureSampleLevel(current_texture, current_sampler, in.uv, 0.0).rgb;
+    return vec4<f32>(current + color / 16.0, 1.0);
+}
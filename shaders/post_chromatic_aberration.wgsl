@@ -0,0 +1,24 @@
+#import ./post_process.wgsl as PostProcess
+
+struct ChromaticAberration {
+    settings: vec4<f32> // FORMAT: intensity, unused, unused, unused
+}
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var input_sampler: sampler;
+@group(1) @binding(0)
+var<uniform> aberration: ChromaticAberration;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> PostProcess::FullscreenOutput { return PostProcess::vs_main(index); }
+
+// splits the red and blue channels away from each other towards the edges of the image
+@fragment
+fn fs_main(in: PostProcess::FullscreenOutput) -> @location(0) vec4<f32> {
+    let offset = (in.uv - 0.5) * aberration.settings.x;
+    let red = textureSampleLevel(input_texture, input_sampler, in.uv - offset, 0.0).r;
+    let green = textureSampleLevel(input_texture, input_sampler, in.uv, 0.0).g;
+    let blue = textureSampleLevel(input_texture, input_sampler, in.uv + offset, 0.0).b;
+    return vec4<f32>(red, green, blue, 1.0);
+}
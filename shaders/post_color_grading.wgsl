@@ -0,0 +1,31 @@
+#import ./post_process.wgsl as PostProcess
+
+struct ColorGrading {
+    settings: vec4<f32> // FORMAT: intensity, unused, unused, unused
+}
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var input_sampler: sampler;
+@group(1) @binding(0)
+var<uniform> grading: ColorGrading;
+@group(2) @binding(0)
+var lut_texture: texture_3d<f32>;
+@group(2) @binding(1)
+var lut_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> PostProcess::FullscreenOutput { return PostProcess::vs_main(index); }
+
+// looks up each color in the lut, the lut is indexed by gamma encoded colors and stores srgb colors so it returns linear ones
+@fragment
+fn fs_main(in: PostProcess::FullscreenOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(input_texture, input_sampler, in.uv, 0.0).rgb;
+
+    // the lut only covers 0 to 1, anything brighter in hdr images is added back afterwards
+    let clamped = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+    let size = f32(textureDimensions(lut_texture).x);
+    let coords = pow(clamped, vec3<f32>(1.0 / 2.2)) * ((size - 1.0) / size) + 0.5 / size;
+    let graded = textureSampleLevel(lut_texture, lut_sampler, coords, 0.0).rgb + color - clamped;
+    return vec4<f32>(mix(color, graded, grading.settings.x), 1.0);
+}
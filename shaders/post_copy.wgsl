@@ -0,0 +1,15 @@
+#import ./post_process.wgsl as PostProcess
+
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var input_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> PostProcess::FullscreenOutput { return PostProcess::vs_main(index); }
+
+// copies the input unchanged, used when no effects are enabled
+@fragment
+fn fs_main(in: PostProcess::FullscreenOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(textureSampleLevel(input_texture, input_sampler, in.uv, 0.0).rgb, 1.0);
+}
@@ -0,0 +1,51 @@
+#import ./post_process.wgsl as PostProcess
+
+struct Fxaa {
+    settings: vec4<f32> // FORMAT: max span in pixels, reduce multiplier, min reduce, unused
+}
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var input_sampler: sampler;
+@group(1) @binding(0)
+var<uniform> fxaa: Fxaa;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> PostProcess::FullscreenOutput { return PostProcess::vs_main(index); }
+
+fn sample_input(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(input_texture, input_sampler, uv, 0.0).rgb;
+}
+
+// the luma used to find edges, compressed so that edges in hdr images are found like those in ldr images
+fn luma(color: vec3<f32>) -> f32 {
+    let luma = dot(color, vec3<f32>(0.299, 0.587, 0.114));
+    return luma / (1.0 + luma);
+}
+
+// timothy lottes's fast approximate anti-aliasing, blurring along the edge found from the luma of the corners
+@fragment
+fn fs_main(in: PostProcess::FullscreenOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(input_texture));
+    let color = sample_input(in.uv);
+    let luma_nw = luma(sample_input(in.uv + texel * vec2<f32>(-1.0, -1.0)));
+    let luma_ne = luma(sample_input(in.uv + texel * vec2<f32>(1.0, -1.0)));
+    let luma_sw = luma(sample_input(in.uv + texel * vec2<f32>(-1.0, 1.0)));
+    let luma_se = luma(sample_input(in.uv + texel * vec2<f32>(1.0, 1.0)));
+    let luma_m = luma(color);
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // the direction along the edge
+    var direction = vec2<f32>(-((luma_nw + luma_ne) - (luma_sw + luma_se)), (luma_nw + luma_sw) - (luma_ne + luma_se));
+    let reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * fxaa.settings.y, fxaa.settings.z);
+    let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2<f32>(-fxaa.settings.x), vec2<f32>(fxaa.settings.x)) * texel;
+
+    // blur along the edge, using the wider blur unless it picks up colors from outside the neighbourhood
+    let near = 0.5 * (sample_input(in.uv + direction * (1.0 / 3.0 - 0.5)) + sample_input(in.uv + direction * (2.0 / 3.0 - 0.5)));
+    let far = near * 0.5 + 0.25 * (sample_input(in.uv - direction * 0.5) + sample_input(in.uv + direction * 0.5));
+    let luma_far = luma(far);
+    if (luma_far < luma_min || luma_far > luma_max) { return vec4<f32>(near, 1.0); }
+    return vec4<f32>(far, 1.0);
+}
@@ -0,0 +1,24 @@
+#import ./post_process.wgsl as PostProcess
+
+struct Vignette {
+    settings: vec4<f32>, // FORMAT: intensity, radius, smoothness, unused
+    color: vec4<f32>
+}
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var input_sampler: sampler;
+@group(1) @binding(0)
+var<uniform> vignette: Vignette;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> PostProcess::FullscreenOutput { return PostProcess::vs_main(index); }
+
+// fades the edges of the image towards the vignette color, the distance is 0 at the center and 1 in the corners
+@fragment
+fn fs_main(in: PostProcess::FullscreenOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(input_texture, input_sampler, in.uv, 0.0).rgb;
+    let distance = length(in.uv - 0.5) * 1.4142135;
+    let amount = smoothstep(vignette.settings.y, vignette.settings.y + vignette.settings.z, distance) * vignette.settings.x;
+    return vec4<f32>(mix(color, vignette.color.rgb, amount), 1.0);
+}
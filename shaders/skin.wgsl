@@ -0,0 +1,12 @@
+// the joint matrices of the nodes skin, must match MAX_JOINTS in skin.rs
+@group(3) @binding(1)
+var<uniform> joints: array<mat4x4<f32>, 128u>;
+
+// blend the joint matrices by the vertices weights, the skinned nodes own transform is ignored as the joints are already in model space
+fn skin_matrix(vertex_joints: vec4<u32>, weights: vec4<f32>) -> mat4x4<f32> {
+    return
+        weights.x * joints[vertex_joints.x] +
+        weights.y * joints[vertex_joints.y] +
+        weights.z * joints[vertex_joints.z] +
+        weights.w * joints[vertex_joints.w];
+}
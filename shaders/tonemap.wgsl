@@ -0,0 +1,88 @@
+struct Tonemapping {
+    settings: vec4<f32> // FORMAT: operator (0 = aces, 1 = reinhard, 2 = agx), exposure multiplier, 1 if auto exposure is used, 1 if the output must be gamma encoded
+}
+@group(0) @binding(0)
+var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> tonemapping: Tonemapping;
+@group(0) @binding(2)
+var<uniform> auto_exposure: vec4<f32>; // FORMAT: exposure multiplier, exposure in stops, average luminance, unused
+
+// a triangle covering the whole screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// stephen hill's fit of the aces reference rendering and output transforms
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let input_matrix = mat3x3<f32>(
+        vec3<f32>(0.59719, 0.07600, 0.02840),
+        vec3<f32>(0.35458, 0.90834, 0.13383),
+        vec3<f32>(0.04823, 0.01566, 0.83777)
+    );
+    let output_matrix = mat3x3<f32>(
+        vec3<f32>(1.60475, -0.10208, -0.00327),
+        vec3<f32>(-0.53108, 1.10813, -0.07276),
+        vec3<f32>(-0.07367, -0.00605, 1.07602)
+    );
+    let v = input_matrix * color;
+    let a = v * (v + 0.0245786) - 0.000090537;
+    let b = v * (0.983729 * v + 0.4329510) + 0.238081;
+    return clamp(output_matrix * (a / b), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// reinhard's operator applied to the luminance so that hues are kept
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return clamp(color / (1.0 + luminance), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// troy sobotka's agx with the default look, using a polynomial fit of its contrast curve
+fn agx(color: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3<f32>(
+        vec3<f32>(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+        vec3<f32>(0.0784335999999992, 0.878468636469772, 0.0784336),
+        vec3<f32>(0.0792237451477643, 0.0791661274605434, 0.879142973793104)
+    );
+    let outset = mat3x3<f32>(
+        vec3<f32>(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+        vec3<f32>(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+        vec3<f32>(-0.0990297440797205, -0.0989611768448433, 1.15107367264116)
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    // compress into the log encoded agx space
+    var v = inset * max(color, vec3<f32>(1e-10));
+    v = (clamp(log2(v), vec3<f32>(min_ev), vec3<f32>(max_ev)) - min_ev) / (max_ev - min_ev);
+
+    // contrast curve
+    let x2 = v * v;
+    let x4 = x2 * x2;
+    v = 15.5 * x4 * x2 - 40.14 * x4 * v + 31.96 * x4 - 6.868 * x2 * v + 0.4298 * x2 + 0.1191 * v - 0.00232;
+
+    // the curve outputs display encoded values, convert back to linear
+    v = outset * v;
+    return pow(clamp(v, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(2.2));
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let settings = tonemapping.settings;
+    var exposure = settings.y;
+    if (settings.z > 0.0) { exposure *= auto_exposure.x; }
+    let color = textureLoad(hdr_texture, vec2<i32>(position.xy), 0).rgb * exposure;
+
+    var mapped: vec3<f32>;
+    switch (u32(settings.x)) {
+        case 1u: { mapped = reinhard(color); }
+        case 2u: { mapped = agx(color); }
+        default: { mapped = aces(color); }
+    }
+
+    // surfaces without an srgb format do not encode their output themselves
+    if (settings.w > 0.0) { mapped = pow(mapped, vec3<f32>(1.0 / 2.2)); }
+    return vec4<f32>(mapped, 1.0);
+}
@@ -13,15 +13,26 @@ pub trait EngineComponent<T> {
     /// Called when the engine updates.
     fn update(&mut self, other: T);
 
+    /// Called every frame before any pass of the render graph runs, so components can dispatch compute work whose results are used while rendering, like particles, culling or skinning.  Every component records onto the same compute pass.
+    fn compute<'pass>(&'pass mut self, _render_engine: &'pass RenderEngine, _pass: &mut wgpu::ComputePass<'pass>) {}
+
     /// Called when this component is called to render during its render pass defined in the created `App`.
     fn render<'rpass>(&'rpass mut self, render_engine: &'rpass RenderEngine, pass: &mut wgpu::RenderPass<'rpass>);
 
+    /// Called when a pass of the render graph runs that was added by a component instead of being listed in the created `App`.  Components that add their own render passes to `RenderEngine::render_graph` render them here and ignore every other pass.
+    fn render_graph_pass<'rpass>(&'rpass mut self, _name: &str, _render_engine: &'rpass RenderEngine, _pass: &mut wgpu::RenderPass<'rpass>) {}
+
+    /// Called when an encoder pass of the render graph runs.  Components that add their own encoder passes to `RenderEngine::render_graph` record them onto the frame's command encoder here and ignore every other pass.
+    fn encode_graph_pass(&mut self, _name: &str, _render_engine: &RenderEngine, _encoder: &mut wgpu::CommandEncoder) {}
+
     /// Called when the engine exits.
     fn exit(&mut self, other: T);
 }
 
 /// This macro creates a `App` objects using a given set of components and some render pass descriptions.
 /// 
+/// Each pass is added to the render engine's `RenderGraph` under its name, along with the textures listed in the optional `TEXTURES` block.  Passes list the textures they render too (`COLOR` and the optional `DEPTH`) and optionally the textures they sample (`READS`) and their order among passes writing the same textures (`ORDER`).  The graph runs every pass in an order that respects what they read and write, this includes the passes components like `UIEngine` and `EguiEngine` add themselves.
+/// 
 /// Example:
 /// ```rust
 /// create_app! {
@@ -34,30 +45,16 @@ pub trait EngineComponent<T> {
 ///     },
 /// 
 ///     PASSES {
-///         0: {
+///         "main": {
+///             COLOR: ["forte.surface"],
+///             DEPTH: "forte.depth",
 ///             PARTS: [
 ///                 {
 ///                     PIPELINE: "forte.test",
 ///                     PREPARE: [],
 ///                     RENDER: test,
 ///                 }
-///             ],
-///             DEPTH: true
-///         },
-///         1: {
-///             PARTS: [
-///                 {
-///                     PIPELINE: "forte.ui",
-///                     PREPARE: [],
-///                     RENDER: ui_engine,
-///                 },
-///                 {
-///                     PIPELINE: "forte.ui",
-///                     PREPARE: [],
-///                     RENDER: egui,
-///                 }
-///             ],
-///             DEPTH: false
+///             ]
 ///         }
 ///     }
 /// }
@@ -69,16 +66,22 @@ macro_rules! create_app {
         APP {$(
             $component:ident: $type:ty[$($param:ident),*]
         ),*},
+        $(TEXTURES {$(
+            $texture:literal: $texture_descriptor:expr
+        ),*},)?
         PASSES {$(
-            $pass_idx:literal: {
+            $pass_name:literal: {
+                COLOR: [$($color_target:expr),*],
+                $(DEPTH: $depth_target:expr,)?
+                $(READS: [$($read:expr),*],)?
+                $(ORDER: $order:expr,)?
                 PARTS: [$(
                     {
                         PIPELINE: $pipeline:expr,
                         PREPARE: [$($prepare:ident),*],
                         RENDER: $to_render:ident,
                     }
-                ),*],
-                DEPTH: $depth:literal
+                ),*]
             }
         ),*}
     } => {
@@ -91,9 +94,23 @@ macro_rules! create_app {
         }
 
         impl EngineApp for App {
-            // Takes in a render engine and creates each component individually in the order listed, then saves them into a new instance of App.
+            // Takes in a render engine, adds the given textures and passes to its render graph, then creates each component individually in the order listed, then saves them into a new instance of App.
             fn create(mut render_engine: RenderEngine) -> Self {
                 let inputs = Inputs::new();
+
+                // setup render graph
+                render_engine.render_graph.set_clear_color($color);
+                $($(render_engine.render_graph.add_texture($texture, $texture_descriptor);)*)?
+                $(
+                    #[allow(unused_mut)]
+                    let mut pass = forte_engine::render::graph::GraphPass::new($pass_name);
+                    $(pass = pass.with_color($color_target);)*
+                    $(pass = pass.with_depth($depth_target);)?
+                    $($(pass = pass.with_read($read);)*)?
+                    $(pass = pass.with_order($order);)?
+                    render_engine.render_graph.add_pass(pass);
+                )*
+
                 $(let $component = <$type>::create(&mut render_engine);)*
                 Self {
                     render_engine,
@@ -109,68 +126,63 @@ macro_rules! create_app {
                 )*
             }
 
-            // Updates App components, then performs the render steps in the order given.
+            // Updates App components, then runs the passes of the render graph in order.
             fn update(&mut self) {
                 // Run update
                 $(
                     <$type>::update(&mut self.$component, ($(&mut self.$param),*));
                 )*
 
+                // swap in shaders that changed on disk if hot reloading is enabled
+                self.render_engine.reload_changed_shaders();
+
+                // make sure the render graph is sorted and its textures are allocated, an invalid graph renders nothing until it is changed
+                if let Err(error) = self.render_engine.render_graph.compile(&self.render_engine.device, &self.render_engine.config) {
+                    $crate::log!("Could not compile the render graph: {:#}", error);
+                }
+
                 // start the render
                 let resources = render_utils::prepare_render(&self.render_engine);
                 let mut resources = if resources.is_ok() { resources.unwrap() } else { return };
 
-                // run each render pass in the order given
-                $(
-                    {
-                        // create color attachment for this pass
-                        let pass_id = $pass_idx;
-                        let color_attachment = wgpu::RenderPassColorAttachment {
-                            view: &resources.view,
-                            resolve_target: None,
-                            ops: if pass_id == 0 {
-                                wgpu::Operations {
-                                    load: wgpu::LoadOp::Clear($color),
-                                    store: wgpu::StoreOp::Store,
-                                }
-                            } else {
-                                wgpu::Operations {
-                                    load: wgpu::LoadOp::Load,
-                                    store: wgpu::StoreOp::Store,
-                                }
-                            },
-                        };
-
-                        // create the render pass
-                        let mut pass = resources.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                            label: Some("Render Pass"),
-                            color_attachments: &[Some(color_attachment)],
-                            depth_stencil_attachment: 
-                                if !$depth { None } 
-                                else { 
-                                    Some(wgpu::RenderPassDepthStencilAttachment {
-                                        view: &self.render_engine.depth_texture.view,
-                                        depth_ops: Some(wgpu::Operations {
-                                            load: wgpu::LoadOp::Clear(1.0),
-                                            store: wgpu::StoreOp::Store
-                                        }),
-                                        stencil_ops: None
-                                    })
-                                },
-                            occlusion_query_set: None,
-                            timestamp_writes: None,
-                        });
+                // record the compute work of the components before any render pass
+                {
+                    let mut pass = resources.encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("forte.compute"), timestamp_writes: None });
+                    $(self.$component.compute(&self.render_engine, &mut pass);)*
+                }
+
+                // run each pass of the render graph in order
+                for index in 0 .. self.render_engine.render_graph.num_passes() {
+                    let graph = &self.render_engine.render_graph;
+                    let name = graph.pass(index).name.as_str();
+
+                    // encoder passes record themselves onto the encoder
+                    if graph.pass(index).encoder {
+                        $(self.$component.encode_graph_pass(name, &self.render_engine, &mut resources.encoder);)*
+                        continue;
+                    }
 
+                    // create the render pass
+                    let mut pass = graph.begin_pass(index, &self.render_engine, &mut resources.encoder, &resources.view);
+
+                    match name {
                         $(
-                            // call all members of this pass' render functions
-                            self.render_engine.pipeline_path($pipeline).unwrap().bind(&mut pass);
-                            $(
-                                self.$prepare.render(&self.render_engine, &mut pass);
-                            )*
-                            self.$to_render.render(&self.render_engine, &mut pass);
+                            $pass_name => {
+                                $(
+                                    // call all members of this pass' render functions
+                                    self.render_engine.pipeline_path($pipeline).unwrap().bind(&mut pass);
+                                    $(
+                                        self.$prepare.render(&self.render_engine, &mut pass);
+                                    )*
+                                    self.$to_render.render(&self.render_engine, &mut pass);
+                                )*
+                            },
                         )*
+
+                        // otherwise, let the components render the passes they added
+                        _ => { $(self.$component.render_graph_pass(name, &self.render_engine, &mut pass);)* }
                     }
-                )*
+                }
 
                 // end the render
                 render_utils::finalize_render(&mut self.render_engine, resources);
@@ -181,7 +193,6 @@ macro_rules! create_app {
                 // reset inputs
                 self.inputs.reset();
             }
-
             // takes all input from the event loop, will be processed later
             fn input(&mut self, input: EngineInput) {
                 self.inputs.handle_input(input);
@@ -197,5 +208,10 @@ macro_rules! create_app {
                 )*
             }
         }
+
+        // gives tools like `utils::golden` access to the render engine
+        impl forte_engine::RenderEngineApp for App {
+            fn render_engine(&self) -> &RenderEngine { &self.render_engine }
+        }
     };
 }
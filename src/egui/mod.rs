@@ -3,7 +3,7 @@ use egui::{pos2, vec2, ClippedPrimitive, FontDefinitions, TexturesDelta};
 use egui_wgpu::ScreenDescriptor;
 use winit::keyboard::KeyCode;
 
-use crate::{component_app::EngineComponent, inputs::Inputs, render::render_engine::RenderEngine};
+use crate::{component_app::EngineComponent, inputs::Inputs, render::{graph::{GraphPass, SURFACE_TEXTURE, UI_PASS_ORDER}, render_engine::RenderEngine}};
 
 pub mod helpers;
 
@@ -18,15 +18,12 @@ pub mod helpers;
 ///         egui: EguiEngine[render_engine, inputs]
 ///     },
 ///
-///     PASSES {
-///         0: {
-///             COMPONENTS: [egui],
-///             DEPTH: false
-///         }
-///     }
+///     PASSES {}
 /// );
 /// ```
 /// 
+/// The engine draws the UI in its own render graph pass over everything else drawn to the surface.
+/// 
 /// Example for drawing:
 /// ```rust
 /// egui::Window::new("Test")
@@ -38,9 +35,12 @@ pub mod helpers;
 ///         }
 ///     });
 /// ```
+/// 
+/// The renderer follows the render engine's sample count.  When it changes, the renderer is recreated and the font atlas uploaded again, any textures the user loaded into egui must be loaded again too.
 pub struct EguiEngine {
     context: egui::Context,
     renderer: egui_wgpu::Renderer,
+    sample_count: u32,
     raw_input: egui::RawInput,
     info: EguiRenderInfo
 }
@@ -53,18 +53,33 @@ struct EguiRenderInfo {
 }
 
 impl EguiEngine {
+    /// The name of the render graph pass this engine draws in.
+    pub const PASS: &'static str = "forte.egui";
+
     /// Returns a immutable reference to an `egui::Context` for rendering.
     pub fn context(&self) -> &egui::Context { &self.context }
 
     /// Returns a mutable referecne to an `egui::Context` for rendering.
     pub fn context_mut(&mut self) -> &mut egui::Context { &mut self.context }
+
+    /// Recreates the renderer if the render engine's sample count changed, as it must match the pass it draws in.  The new renderer is given the font atlas again.
+    fn follow_sample_count(&mut self, engine: &RenderEngine) {
+        if self.sample_count == engine.sample_count() { return }
+        self.sample_count = engine.sample_count();
+        self.renderer = egui_wgpu::Renderer::new(&engine.device, engine.config.format, None, self.sample_count);
+        let font_image = egui::epaint::ImageDelta::full(self.context.fonts(|fonts| fonts.image()), egui::TextureOptions::LINEAR);
+        self.renderer.update_texture(&engine.device, &engine.queue, egui::TextureId::default(), &font_image);
+    }
 }
 
 impl EngineComponent<(&mut RenderEngine, &mut Inputs)> for EguiEngine {
     /// Creates a new instance of `EguiEngine` from the given mutable reference to a `RenderEngine`.
     fn create(engine: &mut RenderEngine) -> Self {
         // setup egui renderer
-        let renderer = egui_wgpu::Renderer::new(&engine.device, wgpu::TextureFormat::Bgra8UnormSrgb, None, 1);
+        let sample_count = engine.sample_count();
+        let renderer = egui_wgpu::Renderer::new(&engine.device, engine.config.format, None, sample_count);
+
+        engine.render_graph.add_pass(GraphPass::new(Self::PASS).with_color(SURFACE_TEXTURE).with_order(UI_PASS_ORDER));
 
         // setup egui context
         let context = egui::Context::default();
@@ -93,7 +108,7 @@ impl EngineComponent<(&mut RenderEngine, &mut Inputs)> for EguiEngine {
             paint_jobs: Vec::new()
         };
 
-        Self { renderer, context, raw_input, info }
+        Self { renderer, sample_count, context, raw_input, info }
     }
 
     /// Starts this `EguiEngine` using mutable references to `RenderEngine` and `Inputs` using the standard `EngineComponent` methods.
@@ -107,14 +122,16 @@ impl EngineComponent<(&mut RenderEngine, &mut Inputs)> for EguiEngine {
         let output = self.context.end_frame();
 
         // update cursor
-        if let Some(cursor_icon) = helpers::egui_to_winit_cursor_icon(output.platform_output.cursor_icon)
-        {
-            engine.window.set_cursor_visible(true);
-            if inputs.mouse_position().is_some() {
-                engine.window.set_cursor_icon(cursor_icon);
+        if let Some(window) = engine.window() {
+            if let Some(cursor_icon) = helpers::egui_to_winit_cursor_icon(output.platform_output.cursor_icon)
+            {
+                window.set_cursor_visible(true);
+                if inputs.mouse_position().is_some() {
+                    window.set_cursor_icon(cursor_icon);
+                }
+            } else {
+                window.set_cursor_visible(false);
             }
-        } else {
-            engine.window.set_cursor_visible(false);
         }
 
         // update mouse position
@@ -209,6 +226,8 @@ impl EngineComponent<(&mut RenderEngine, &mut Inputs)> for EguiEngine {
 
     /// Renders this `EguiEngine` using mutable references to self, a `wgpu::RenderPass` and a immutable to a `RenderEngine` to render the Egui UI specified during the last update cycle.
     fn render<'rpass>(&'rpass mut self, engine: &'rpass RenderEngine, pass: &mut wgpu::RenderPass<'rpass>) {
+        self.follow_sample_count(engine);
+
         // create a command encode for drawing
         let mut encoder = engine.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("encoder"),
@@ -227,6 +246,11 @@ impl EngineComponent<(&mut RenderEngine, &mut Inputs)> for EguiEngine {
         engine.queue.submit(std::iter::once(encoder.finish()));
     }
 
+    /// Renders the Egui UI when the render graph runs this engine's pass.
+    fn render_graph_pass<'rpass>(&'rpass mut self, name: &str, engine: &'rpass RenderEngine, pass: &mut wgpu::RenderPass<'rpass>) {
+        if name == Self::PASS { self.render(engine, pass); }
+    }
+
     /// Calls necessary exit functions using mutable references to `RenderEngine` and `Inputs` using the standard `EngineComponent` methods.
     fn exit(&mut self, _: (&mut RenderEngine, &mut Inputs)) {}
 }
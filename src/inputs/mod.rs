@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use cgmath::Point2;
+use cgmath::{Point2, Vector2, Zero};
 use winit::{event::MouseButton, keyboard::KeyCode};
 
 use crate::inputs::winit_input::EngineInput;
@@ -13,6 +13,7 @@ pub struct Inputs {
 
     mouse_position: Option<Point2<f32>>,
     mouse_scroll_delta: Option<Point2<f32>>,
+    mouse_delta: Option<Vector2<f32>>,
 
     key_codes: HashMap<KeyCode, bool>,
     keys_just_pressed: Vec<KeyCode>,
@@ -40,6 +41,9 @@ impl Inputs {
             // update mouse position
             EngineInput::MouseMove(position) => { self.mouse_position = Some(position); },
 
+            // add up raw mouse motion
+            EngineInput::MouseMotion(delta) => { self.mouse_delta = Some(self.mouse_delta() + delta); },
+
             // handle mouse buttons
             EngineInput::MouseButton(button, state) => {
                 match state {
@@ -86,6 +90,7 @@ impl Inputs {
     /// This is meant to be called by the engine core directly.
     pub fn reset(&mut self) {
         self.mouse_scroll_delta = None;
+        self.mouse_delta = None;
         self.raw_inputs.clear();
         self.mouse_buttons_just_pressed.clear();
         self.mouse_buttons_just_released.clear();
@@ -117,6 +122,9 @@ impl Inputs {
     /// Returns the current mouse position if we have one.
     pub fn mouse_position(&self) -> Option<&Point2<f32>> { self.mouse_position.as_ref() }
     
+    /// Returns how far the mouse moved this frame, from raw mouse motion, so it keeps changing while the cursor is grabbed.
+    pub fn mouse_delta(&self) -> Vector2<f32> { self.mouse_delta.unwrap_or(Vector2::zero()) }
+
     /// Returns the last mouse scroll delta.
     pub fn mouse_scroll_delta(&self) -> &Point2<f32> { self.mouse_scroll_delta.as_ref().unwrap_or(&Point2 { x: 0.0, y: 0.0 }) }
 
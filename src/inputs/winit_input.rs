@@ -5,12 +5,17 @@ use winit::{event::*, keyboard::PhysicalKey};
 /// 
 /// Options:
 /// * MouseMove(position: Point2<f32>) - Represents new mouse position.
+/// * MouseMotion(delta: Vector2<f32>) - Represents how far the mouse moved, even while the cursor is grabbed and cannot move.  `run_app` only forwards it while the window is focused.
 /// * MouseButton(button: MouseButton, state: ElementState) - Represents a state change (pressed or released) of a mouse button.
 /// * MouseWheel(delta: MouseScrollDelta) - Represents how much the mouse wheel was turned.
 /// * KeyInput(key: VirtualKeyCode, state: ElementState) - Represents a state change (pressed or released) of a keyboard input.
+///
+/// More inputs may be added in the future, so matches on this enum need a wildcard arm.
 #[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
 pub enum EngineInput {
     MouseMove(Point2<f32>),
+    MouseMotion(Vector2<f32>),
     MouseButton(winit::event::MouseButton, winit::event::ElementState),
     MouseWheel(winit::event::MouseScrollDelta),
     KeyInput(winit::keyboard::KeyCode, winit::event::ElementState)
@@ -51,4 +56,18 @@ impl EngineInput {
             _ => None
         }
     }
+
+    /// A function that quickly converts a winit DeviceEvent into a `EngineInput`.
+    /// 
+    /// Arguments:
+    /// * event: &DeviceEvent - The winit `DeviceEvent` that occured.
+    pub fn from_winit_device_input(event: &DeviceEvent) -> Option<Self> {
+        match event {
+            // handle raw mouse motion, used for mouse look
+            DeviceEvent::MouseMotion { delta } => Some(Self::MouseMotion(Vector2 { x: delta.0 as f32, y: delta.1 as f32 })),
+
+            // all other inputs, return nothing
+            _ => None
+        }
+    }
 }
@@ -37,6 +37,12 @@ pub trait EngineApp {
     fn exit(&mut self);
 }
 
+/// A trait implemented by apps that own a render engine, so tools like `utils::golden` can read back their rendered frames.  Apps created with `create_app!` implement this automatically.
+pub trait RenderEngineApp {
+    /// Returns a reference to the render engine used by this app.
+    fn render_engine(&self) -> &RenderEngine;
+}
+
 /// The run_app function effectively creates an runs the app given as a generic argument.
 /// 
 /// This function will automatically call the create function on startup just after the window is created and render engine initialized.
@@ -86,6 +92,9 @@ pub async fn run_app<T: EngineApp + 'static>() {
     }
 
 
+    // raw mouse motion is reported even while other windows are focused, so track focus to ignore it then
+    let mut focused = window.has_focus();
+
     // setup engine
     log!("Creating RenderEngine...");
     let engine = RenderEngine::new(window).await;
@@ -108,6 +117,9 @@ pub async fn run_app<T: EngineApp + 'static>() {
                     // handle resizes
                     WindowEvent::Resized(size) => app.resize(*size),
 
+                    // remember if the window is focused
+                    WindowEvent::Focused(state) => focused = *state,
+
                     // handle updates
                     WindowEvent::RedrawRequested => {
                         app.update();
@@ -124,7 +136,10 @@ pub async fn run_app<T: EngineApp + 'static>() {
                 }
             },
             Event::NewEvents(_) => {},
-            Event::DeviceEvent { device_id: _, event: _ } => {},
+            Event::DeviceEvent { ref event, .. } => {
+                // convert the winit device event to a `EngineInput`, like raw mouse motion, while the window is focused
+                if let Some(input) = EngineInput::from_winit_device_input(event).filter(|_| focused) { app.input(input); }
+            },
             Event::UserEvent(_) => {},
             Event::Suspended => {},
             Event::Resumed => {},
@@ -0,0 +1,242 @@
+use cgmath::{Matrix4, SquareMatrix};
+use wgpu::util::DeviceExt;
+
+use crate::{create_compute_pipeline, primitives::cameras::Camera, render::render_engine::RenderEngine};
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/light_clusters.wgsl")]
+mod light_clusters_shader {}
+
+/// The number of clusters the camera's view is split into along x, y and z.
+pub const CLUSTER_GRID: [u32; 3] = [16, 9, 24];
+
+/// The distance clusters reach for projections without a far plane, fragments further away are lit by every light.
+pub const MAX_CLUSTER_DISTANCE: f32 = 1000.0;
+
+/// The maximum number of lights that can reach a single cluster, any more lights are ignored in that cluster.
+pub const MAX_LIGHTS_PER_CLUSTER: u32 = 128;
+
+/// The maximum number of lights used on devices without storage buffers, this must match the size of the lights array in `light.wgsl`.
+pub const MAX_UNIFORM_LIGHTS: usize = 256;
+
+/// The number of clusters along each axis handled by a single compute workgroup, this must match the workgroup size in `light_clusters.wgsl`.
+const WORKGROUP_SIZE: u32 = 4;
+
+/// The rust representation of the cluster information passed to the shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ClustersUniform {
+    view: [[f32; 4]; 4],
+    projection: [[f32; 4]; 4],
+    inverse_projection: [[f32; 4]; 4],
+
+    // FORMAT: clusters along x, y and z (all 0 until the clusters are built), max lights per cluster
+    grid: [u32; 4],
+
+    // FORMAT: near, far, log(far / near), unused
+    depth: [f32; 4]
+}
+
+/// Splits the camera's view into a grid of clusters and lists the lights that reach each of them with a compute shader, so that each fragment only has to light itself with the lights of its own cluster.
+///
+/// Only lights with a range can be culled, directional lights and lights without a range reach every cluster.  The clusters are fitted to a single camera, fragments outside its view, like those only seen by other cameras, are lit by every light instead.
+#[derive(Debug)]
+pub struct LightClusters {
+    uniform_buffer: wgpu::Buffer,
+    counts_buffer: wgpu::Buffer,
+    indices_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pending: Option<ClustersUniform>
+}
+
+impl LightClusters {
+    /// The name of the compute pipeline that builds the clusters.
+    pub const PIPELINE: &'static str = "forte.light_clusters";
+
+    /// The bind group layout of the compute shader that builds the clusters.
+    pub const COMPUTE_BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("light_clusters_bind_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }
+        ]
+    };
+
+    /// Returns true if the given engine supports clustered lighting, it needs storage buffers in fragment shaders and compute shaders which WebGL does not have.  Without it, lights are stored in a uniform buffer and at most `MAX_UNIFORM_LIGHTS` are used.
+    pub fn is_supported(engine: &RenderEngine) -> bool {
+        let limits = engine.device.limits();
+        limits.max_storage_buffers_per_shader_stage >= 3 && limits.max_compute_invocations_per_workgroup >= WORKGROUP_SIZE.pow(3)
+    }
+
+    /// Creates new light clusters.  Until they are built, every fragment is lit by every light.
+    ///
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine to create the clusters with.
+    /// * light_buffer: &wgpu::Buffer - The storage buffer containing the lights.
+    /// * light_count_buffer: &wgpu::Buffer - The uniform buffer containing the number of lights.
+    pub fn new(engine: &mut RenderEngine, light_buffer: &wgpu::Buffer, light_count_buffer: &wgpu::Buffer) -> Self {
+        let num_clusters = CLUSTER_GRID.iter().product::<u32>() as u64;
+
+        // create buffers, the grid stays zeroed until the clusters are built
+        let uniform_buffer = engine.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("light_clusters_buffer"),
+            contents: bytemuck::cast_slice(&[<ClustersUniform as bytemuck::Zeroable>::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+        });
+        let counts_buffer = engine.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("light_cluster_counts_buffer"),
+            size: num_clusters * 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false
+        });
+        let indices_buffer = engine.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("light_cluster_indices_buffer"),
+            size: num_clusters * MAX_LIGHTS_PER_CLUSTER as u64 * 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false
+        });
+
+        // make sure compute pipeline exists
+        create_compute_pipeline! {
+            NAME => Self::PIPELINE,
+            ENGINE => engine,
+            SHADER => light_clusters_shader::SOURCE,
+            BIND_GROUPS => [Self::COMPUTE_BIND_LAYOUT],
+            ENTRY_POINT => "cs_main"
+        }
+
+        let bind_group = Self::create_bind_group(engine, &uniform_buffer, light_buffer, light_count_buffer, &counts_buffer, &indices_buffer);
+        Self { uniform_buffer, counts_buffer, indices_buffer, bind_group, pending: None }
+    }
+
+    /// Sets the buffers the lights are read from when the clusters are built.  This should be called whenever the light buffer is recreated.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine the buffers belong too.
+    /// * light_buffer: &wgpu::Buffer - The storage buffer containing the lights.
+    /// * light_count_buffer: &wgpu::Buffer - The uniform buffer containing the number of lights.
+    pub fn set_light_buffers(&mut self, engine: &RenderEngine, light_buffer: &wgpu::Buffer, light_count_buffer: &wgpu::Buffer) {
+        self.bind_group = Self::create_bind_group(engine, &self.uniform_buffer, light_buffer, light_count_buffer, &self.counts_buffer, &self.indices_buffer);
+    }
+
+    /// Fits the clusters to the given camera's view, the lights that reach each of them are listed next time the clusters are encoded.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine the clusters will be built with.
+    /// * camera: &Camera - The camera the scene will be viewed from.
+    pub fn fit(&mut self, engine: &RenderEngine, camera: &Camera) {
+        // calculate the view and projection of the camera
+        let (near, far) = camera.projection.depth_range();
+        let near = near.max(0.0001);
+        let far = if far.is_finite() { far } else { MAX_CLUSTER_DISTANCE }.max(near * 2.0);
+        let view = camera.view_matrix();
+        let projection = camera.projection_matrix(engine);
+        let inverse_projection = projection.invert().unwrap_or(Matrix4::identity());
+
+        self.pending = Some(ClustersUniform {
+            view: view.into(),
+            projection: projection.into(),
+            inverse_projection: inverse_projection.into(),
+            grid: [CLUSTER_GRID[0], CLUSTER_GRID[1], CLUSTER_GRID[2], MAX_LIGHTS_PER_CLUSTER],
+            depth: [near, far, (far / near).ln(), 0.0]
+        });
+    }
+
+    /// Lists the lights that reach each cluster with the compute shader, if the clusters were fit to a camera since they were last encoded.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to build the clusters with.
+    /// * encoder: &mut wgpu::CommandEncoder - The command encoder to record the compute pass onto.
+    pub fn encode(&mut self, engine: &RenderEngine, encoder: &mut wgpu::CommandEncoder) {
+        let Some(pipeline) = engine.compute_pipeline_path(Self::PIPELINE) else { return };
+        let Some(uniform) = self.pending.take() else { return };
+        engine.queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        // run one invocation per cluster
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("light_clusters_pass"), timestamp_writes: None });
+        pipeline.bind(&mut pass);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        let [x, y, z] = CLUSTER_GRID.map(|size| size.div_ceil(WORKGROUP_SIZE));
+        pass.dispatch_workgroups(x, y, z);
+    }
+
+    /// The bind group entries of the clusters, bound alongside the lights.
+    pub(crate) fn bind_group_entries(&self) -> [wgpu::BindGroupEntry<'_>; 3] {
+        [
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: self.uniform_buffer.as_entire_binding()
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: self.counts_buffer.as_entire_binding()
+            },
+            wgpu::BindGroupEntry {
+                binding: 8,
+                resource: self.indices_buffer.as_entire_binding()
+            }
+        ]
+    }
+
+    /// Creates the bind group of the compute shader that builds the clusters.
+    fn create_bind_group(engine: &RenderEngine, uniform_buffer: &wgpu::Buffer, light_buffer: &wgpu::Buffer, light_count_buffer: &wgpu::Buffer, counts_buffer: &wgpu::Buffer, indices_buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+        engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("light_clusters_bind_group"),
+            layout: &engine.device.create_bind_group_layout(&Self::COMPUTE_BIND_LAYOUT),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: uniform_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: light_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: light_count_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: counts_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: indices_buffer.as_entire_binding() }
+            ]
+        })
+    }
+}
@@ -1,65 +1,256 @@
-/// The rust representation of all needed light information needed for shaders.
+use crate::render::render_engine::RenderEngine;
+
+use super::{clusters::LightClusters, shadows::ShadowKind};
+
+/// A light in the scene.  Brightness uses physical units: directional lights are measured in lux, the light falling on a surface facing them, and point and spot lights in candela, which light a surface one unit away with that many lux.  Until an exposure is applied, the light reflected by a surface is drawn as is, so a white surface facing a light needs about 3 (π) lux to be drawn at full brightness.
+///
+/// Point and spot lights fall off with the inverse square of their distance, smoothly reaching zero at their range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Light {
+    /// A light infinitely far away, like the sun, that shines along its direction.
+    Directional {
+        direction: [f32; 3],
+        color: [f32; 3],
+        illuminance: f32,
+        cast_shadows: bool
+    },
+
+    /// A light that shines in every direction from its position.
+    Point {
+        position: [f32; 3],
+        color: [f32; 3],
+        intensity: f32,
+        range: f32,
+        cast_shadows: bool
+    },
+
+    /// A light that shines in a cone along its direction.  The light is at full intensity inside the inner angle and fades out towards the outer angle, both angles are in radians from the direction to the edge of the cone.
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        color: [f32; 3],
+        intensity: f32,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+        cast_shadows: bool
+    }
+}
+
+impl Light {
+    /// Creates a new directional light without shadows.
+    ///
+    /// Arguments:
+    /// * direction: [f32; 3] - The direction the light shines in.
+    /// * color: [f32; 3] - The color of the light.
+    /// * illuminance: f32 - The brightness of the light in lux.
+    pub fn directional(direction: [f32; 3], color: [f32; 3], illuminance: f32) -> Self {
+        Self::Directional { direction, color, illuminance, cast_shadows: false }
+    }
+
+    /// Creates a new point light without a range or shadows.
+    ///
+    /// Arguments:
+    /// * position: [f32; 3] - The position of the light.
+    /// * color: [f32; 3] - The color of the light.
+    /// * intensity: f32 - The brightness of the light in candela.
+    pub fn point(position: [f32; 3], color: [f32; 3], intensity: f32) -> Self {
+        Self::Point { position, color, intensity, range: f32::INFINITY, cast_shadows: false }
+    }
+
+    /// Creates a new spot light without a range or shadows.
+    ///
+    /// Arguments:
+    /// * position: [f32; 3] - The position of the light.
+    /// * direction: [f32; 3] - The direction the light shines in.
+    /// * color: [f32; 3] - The color of the light.
+    /// * intensity: f32 - The brightness of the light in candela.
+    /// * inner_angle: f32 - The angle in radians from the direction where the light starts to fade out.
+    /// * outer_angle: f32 - The angle in radians from the direction where the light has faded out completely.
+    pub fn spot(position: [f32; 3], direction: [f32; 3], color: [f32; 3], intensity: f32, inner_angle: f32, outer_angle: f32) -> Self {
+        Self::Spot { position, direction, color, intensity, range: f32::INFINITY, inner_angle, outer_angle, cast_shadows: false }
+    }
+
+    /// Converts the arguments of the old `LightUniform::new` into a light.  Lights with a cutoff below 1 become spot lights with a hard edge, all others become point lights.
+    ///
+    /// Lights now always fall off with the inverse square of their distance, like an exponent of 2 did, so the exponent is ignored and the light is given an intensity of 1.  Lights that used a smaller exponent need a larger intensity to look the same.
+    ///
+    /// Arguments:
+    /// * position: [f32; 3] - The position of the light.
+    /// * color: [f32; 3] - The color of the light.
+    /// * direction: [f32; 3] - The direction of the light, only used if cutoff is below 1.
+    /// * range: f32 - The range of the light in units.
+    /// * exponent: f32 - Ignored, see above.
+    /// * cutoff: f32 - The dot product with the direction at which the light cuts off.
+    pub fn from_legacy(position: [f32; 3], color: [f32; 3], direction: [f32; 3], range: f32, _exponent: f32, cutoff: f32) -> Self {
+        if cutoff < 1.0 {
+            let angle = cutoff.max(-1.0).acos();
+            Self::Spot { position, direction, color, intensity: 1.0, range, inner_angle: angle, outer_angle: angle, cast_shadows: false }
+        } else {
+            Self::Point { position, color, intensity: 1.0, range, cast_shadows: false }
+        }
+    }
+
+    /// Returns this light with the given range, after which it no longer lights anything.  Directional lights have no range, so they are returned unchanged.
+    pub fn with_range(mut self, new_range: f32) -> Self {
+        match &mut self {
+            Self::Directional { .. } => {},
+            Self::Point { range, .. } | Self::Spot { range, .. } => *range = new_range
+        }
+        self
+    }
+
+    /// Returns this light casting shadows onto meshes that receive them.
+    pub fn with_shadows(mut self) -> Self {
+        match &mut self {
+            Self::Directional { cast_shadows, .. } | Self::Point { cast_shadows, .. } | Self::Spot { cast_shadows, .. } => *cast_shadows = true
+        }
+        self
+    }
+
+    /// Returns true if this light casts shadows.
+    pub fn casts_shadows(&self) -> bool {
+        match self {
+            Self::Directional { cast_shadows, .. } | Self::Point { cast_shadows, .. } | Self::Spot { cast_shadows, .. } => *cast_shadows
+        }
+    }
+
+    /// Returns an option containing how this light projects its shadows, or none if it does not cast shadows.
+    pub fn shadow_kind(&self) -> Option<ShadowKind> {
+        if !self.casts_shadows() { return None }
+        Some(match self {
+            Self::Directional { .. } => ShadowKind::Directional,
+            Self::Point { .. } => ShadowKind::Point,
+            Self::Spot { .. } => ShadowKind::Spot
+        })
+    }
+}
+
+/// The rust representation of a light that is passed to the shaders, create these from a `Light`.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LightUniform {
     position: [f32; 3],
-    range: f32,
+    inverse_range_squared: f32,
     color: [f32; 3],
-    exponent: f32,
+    kind: u32,
     direction: [f32; 3],
-    cutoff: f32
+    pub(crate) shadow_layer: i32,
+    spot_scale: f32,
+    spot_offset: f32,
+    padding: [u32; 2]
 }
 
 impl LightUniform {
-    /// The bind group layout to be used for LightUniform.  This is here to promote consistency across implementations.
+    /// The bind group layout to be used for LightUniform when clustered lighting is supported.  This is here to promote consistency across implementations.
     pub const BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
         entries: &[
-            wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 1,
-                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                ty: wgpu::BindingType::Buffer { 
-                    ty: wgpu::BufferBindingType::Uniform, 
-                    has_dynamic_offset: false, 
-                    min_binding_size: None
-                },
-                count: None
-            },
-            wgpu::BindGroupLayoutEntry {
-                binding: 2,
-                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                ty: wgpu::BindingType::Buffer { 
-                    ty: wgpu::BufferBindingType::Uniform, 
-                    has_dynamic_offset: false, 
-                    min_binding_size: None
-                },
-                count: None
-            }
+            buffer_entry(0, wgpu::ShaderStages::FRAGMENT, wgpu::BufferBindingType::Storage { read_only: true }),
+            buffer_entry(1, wgpu::ShaderStages::VERTEX_FRAGMENT, wgpu::BufferBindingType::Uniform),
+            buffer_entry(2, wgpu::ShaderStages::VERTEX_FRAGMENT, wgpu::BufferBindingType::Uniform),
+            SHADOW_MAPS_ENTRY,
+            SHADOW_SAMPLER_ENTRY,
+            buffer_entry(5, wgpu::ShaderStages::FRAGMENT, wgpu::BufferBindingType::Uniform),
+            buffer_entry(6, wgpu::ShaderStages::FRAGMENT, wgpu::BufferBindingType::Uniform),
+            buffer_entry(7, wgpu::ShaderStages::FRAGMENT, wgpu::BufferBindingType::Storage { read_only: true }),
+            buffer_entry(8, wgpu::ShaderStages::FRAGMENT, wgpu::BufferBindingType::Storage { read_only: true })
         ],
         label: Some("light_uniform_layout"),
     };
 
-    /// Create a new light uniform.
-    /// 
+    /// The bind group layout to be used for LightUniform on devices without clustered lighting, like WebGL, where the lights are stored in a uniform buffer.
+    pub const UNIFORM_BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            buffer_entry(0, wgpu::ShaderStages::VERTEX_FRAGMENT, wgpu::BufferBindingType::Uniform),
+            buffer_entry(1, wgpu::ShaderStages::VERTEX_FRAGMENT, wgpu::BufferBindingType::Uniform),
+            buffer_entry(2, wgpu::ShaderStages::VERTEX_FRAGMENT, wgpu::BufferBindingType::Uniform),
+            SHADOW_MAPS_ENTRY,
+            SHADOW_SAMPLER_ENTRY,
+            buffer_entry(5, wgpu::ShaderStages::FRAGMENT, wgpu::BufferBindingType::Uniform)
+        ],
+        label: Some("light_uniform_fallback_layout"),
+    };
+
+    /// The bind group layout the light engine uses with the given render engine, see `LightClusters::is_supported`.
+    ///
     /// Arguments:
-    /// * position: [f32; 3] - The position of the light.
-    /// * color: [f32; 3] - The color of the light.
-    /// * direction: [f32; 3] - The direction of the light.  Only needed if cutoff is set, otherwise, leave as anything.
-    /// * range: f32 - The range of the light in units.  If not needed, set to infinity.
-    /// * exponent: f32 - The exponent of the light used to adjust how the light "bleeds off" over distance.  If not needed, leave as 0.
-    /// * cutoff: f32 - The dot product at which a light will "cutoff".  This is usual for spot lights.  If no cut off needed, set this to higher than 100.
-    /// 
-    /// Returns a new light uniform.
-    pub fn new(position: [f32; 3], color: [f32; 3], direction: [f32; 3], range: f32, exponent: f32, cutoff: f32) -> Self {
-        Self { position, color, range, exponent, direction, cutoff }
+    /// * engine: &RenderEngine - The render engine the lights are used with.
+    ///
+    /// Returns either `BIND_LAYOUT` or `UNIFORM_BIND_LAYOUT`.
+    pub fn bind_layout(engine: &RenderEngine) -> wgpu::BindGroupLayoutDescriptor<'static> {
+        if LightClusters::is_supported(engine) { Self::BIND_LAYOUT } else { Self::UNIFORM_BIND_LAYOUT }
+    }
+}
+
+/// The layout entry of the shadow map array.
+const SHADOW_MAPS_ENTRY: wgpu::BindGroupLayoutEntry = wgpu::BindGroupLayoutEntry {
+    binding: 3,
+    visibility: wgpu::ShaderStages::FRAGMENT,
+    ty: wgpu::BindingType::Texture {
+        sample_type: wgpu::TextureSampleType::Depth,
+        view_dimension: wgpu::TextureViewDimension::D2Array,
+        multisampled: false
+    },
+    count: None
+};
+
+/// The layout entry of the shadow map comparison sampler.
+const SHADOW_SAMPLER_ENTRY: wgpu::BindGroupLayoutEntry = wgpu::BindGroupLayoutEntry {
+    binding: 4,
+    visibility: wgpu::ShaderStages::FRAGMENT,
+    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+    count: None
+};
+
+/// Creates the layout entry of a buffer in the light bind group.
+const fn buffer_entry(binding: u32, visibility: wgpu::ShaderStages, ty: wgpu::BufferBindingType) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None
+        },
+        count: None
+    }
+}
+
+impl From<&Light> for LightUniform {
+    fn from(light: &Light) -> Self {
+        let mut uniform = Self { position: [0.0; 3], inverse_range_squared: 0.0, color: [0.0; 3], kind: 0, direction: [0.0, -1.0, 0.0], shadow_layer: -1, spot_scale: 0.0, spot_offset: 1.0, padding: [0; 2] };
+        let scale = |color: [f32; 3], brightness: f32| color.map(|channel| channel * brightness);
+        let inverse_range_squared = |range: f32| if range.is_finite() && range > 0.0 { 1.0 / (range * range) } else { 0.0 };
+        match *light {
+            Light::Directional { direction, color, illuminance, .. } => {
+                uniform.direction = normalize(direction);
+                uniform.color = scale(color, illuminance);
+            },
+            Light::Point { position, color, intensity, range, .. } => {
+                uniform.kind = 1;
+                uniform.position = position;
+                uniform.color = scale(color, intensity);
+                uniform.inverse_range_squared = inverse_range_squared(range);
+            },
+            Light::Spot { position, direction, color, intensity, range, inner_angle, outer_angle, .. } => {
+                // the cone fades linearly between the cosines of the angles, precalculated as a scale and offset
+                let cos_outer = outer_angle.cos();
+                let cos_inner = inner_angle.min(outer_angle).cos();
+                uniform.kind = 2;
+                uniform.position = position;
+                uniform.direction = normalize(direction);
+                uniform.color = scale(color, intensity);
+                uniform.inverse_range_squared = inverse_range_squared(range);
+                uniform.spot_scale = 1.0 / (cos_inner - cos_outer).max(0.0001);
+                uniform.spot_offset = -cos_outer * uniform.spot_scale;
+            }
+        }
+        uniform
     }
 }
+
+/// Normalizes the given direction, returning straight down if it has no length.
+fn normalize(direction: [f32; 3]) -> [f32; 3] {
+    let length = direction.iter().map(|axis| axis * axis).sum::<f32>().sqrt();
+    if length > 0.0 { direction.map(|axis| axis / length) } else { [0.0, -1.0, 0.0] }
+}
@@ -1,12 +1,18 @@
-use std::collections::HashMap;
-
-use lights::LightUniform;
-use crate::{component_app::EngineComponent, render::render_engine::RenderEngine};
+use clusters::{LightClusters, MAX_UNIFORM_LIGHTS};
+use lights::{Light, LightUniform};
+use shadows::{ShadowCaster, ShadowMaps, ShadowSettings};
+use slots::{LightHandle, LightSlots};
+use crate::{component_app::EngineComponent, primitives::cameras::Camera, render::{graph::{GraphPass, PREPARE_PASS_ORDER}, render_engine::RenderEngine}};
 use wgpu::util::DeviceExt;
 
+pub mod clusters;
 pub mod lights;
+pub mod shadows;
+pub mod slots;
 
 /// A engine used to drive all lights in the scene.
+///
+/// Lights are stored in a storage buffer and culled into clusters when the device supports it (see `LightClusters::is_supported`), otherwise up to `MAX_UNIFORM_LIGHTS` lights are stored in a uniform buffer.
 #[derive(Debug)]
 pub struct LightEngine {
     // wgpu
@@ -14,12 +20,15 @@ pub struct LightEngine {
     light_count_buffer: wgpu::Buffer,
     light_ambient_buffer: wgpu::Buffer,
     light_bind_group: wgpu::BindGroup,
+    shadow_maps: ShadowMaps,
+    clusters: Option<LightClusters>,
 
     // internal
-    lights: HashMap<u32, LightUniform>,
+    lights: LightSlots,
+    uploaded: Vec<LightUniform>,
+    light_capacity: usize,
     ambient_color: [f32; 3],
-    last_count: usize,
-    dirty: bool
+    ambient_dirty: bool
 }
 
 impl EngineComponent<&mut RenderEngine> for LightEngine {
@@ -30,15 +39,17 @@ impl EngineComponent<&mut RenderEngine> for LightEngine {
     /// * engine: &RenderEngine - The render engine this light engine will be used with.
     /// * ambient_light: [f32; 3] - The ambient light color.
     fn create(engine: &mut RenderEngine) -> Self {
-        // setup a default light
-        let default_light = lights::LightUniform::new([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], 0.0, 0.0, 1000.0);
+        // setup a default light, the uniform buffer always holds the maximum number of lights
+        let default_light = LightUniform::from(&Light::point([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0));
+        let clustered = LightClusters::is_supported(engine);
+        let default_lights = if clustered { vec![default_light] } else { vec![default_light; MAX_UNIFORM_LIGHTS] };
 
         // create a light buffer
         let light_buffer = engine.device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Light Buffer"),
-                contents: bytemuck::cast_slice(&[default_light]),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+                contents: bytemuck::cast_slice(&default_lights),
+                usage: Self::light_buffer_usage(clustered)
             }
         );
 
@@ -61,30 +72,17 @@ impl EngineComponent<&mut RenderEngine> for LightEngine {
         );
 
         // create light bind group
-        let light_bind_group = engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &engine.device.create_bind_group_layout(&lights::LightUniform::BIND_LAYOUT),
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: light_buffer.as_entire_binding()
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: light_count_buffer.as_entire_binding()
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: light_ambient_buffer.as_entire_binding()
-                }
-            ],
-            label: Some("light_bind_group")
-        });
+        let shadow_maps = ShadowMaps::new(engine, ShadowSettings::default());
+        let clusters = if clustered { Some(LightClusters::new(engine, &light_buffer, &light_count_buffer)) } else { None };
+        if clustered { engine.render_graph.add_pass(GraphPass::encoder(Self::CLUSTER_PASS).with_order(PREPARE_PASS_ORDER)); }
+        let light_bind_group = Self::create_bind_group(engine, &light_buffer, &light_count_buffer, &light_ambient_buffer, &shadow_maps, clusters.as_ref());
 
         Self { 
             light_buffer, light_count_buffer, 
-            light_bind_group, light_ambient_buffer,
+            light_bind_group, light_ambient_buffer, shadow_maps, clusters,
             ambient_color: [0.0, 0.0, 0.0],
-            lights: HashMap::new(), last_count: 0, dirty: false 
+            lights: LightSlots::default(), uploaded: Vec::new(),
+            light_capacity: default_lights.len(), ambient_dirty: false
         }
     }
 
@@ -93,73 +91,76 @@ impl EngineComponent<&mut RenderEngine> for LightEngine {
     /// Arguments:
     /// * engine: &RenderEngine - The render engine this light engine is being used with.
     fn update(&mut self, engine: &mut RenderEngine) {
-        // if not marked dirty, stop here
-        if !self.dirty { return }
+        // update ambient light buffer
+        if self.ambient_dirty {
+            self.ambient_dirty = false;
+            engine.queue.write_buffer(&self.light_ambient_buffer, 0, bytemuck::cast_slice(&[self.ambient_color[0], self.ambient_color[1], self.ambient_color[2], 0.0]));
+        }
 
-        // update count buffer
-        engine.queue.write_buffer(
-            &self.light_count_buffer, 
-            0, 
-            bytemuck::cast_slice(&[self.lights.len() as u32, 0, 0, 0])
-        );
+        // if no lights are marked dirty, stop here
+        let Some(dirty) = self.lights.take_dirty() else { return };
 
-        // update ambient light buffer
-        engine.queue.write_buffer(&self.light_ambient_buffer, 0, bytemuck::cast_slice(&[self.ambient_color[0], self.ambient_color[1], self.ambient_color[2], 0.0]));
+        // create array of lights, giving lights that cast shadows their shadow maps
+        let lights = self.lights.lights();
+        let lights = if self.clusters.is_none() { &lights[..lights.len().min(MAX_UNIFORM_LIGHTS)] } else { lights };
+        let mut uniforms: Vec<LightUniform> = lights.iter().map(LightUniform::from).collect();
+        self.shadow_maps.assign_layers(lights, &mut uniforms);
 
-        // if the lights count is not the same size as the current count, create a new buffer and bind count
-        if self.last_count != self.lights.len() {
-            self.last_count = self.lights.len();
+        // update count buffer
+        if uniforms.len() != self.uploaded.len() {
+            engine.queue.write_buffer(
+                &self.light_count_buffer, 
+                0, 
+                bytemuck::cast_slice(&[uniforms.len() as u32, 0, 0, 0])
+            );
+        }
 
-            // create array of lights
-            let mut lights: Vec<LightUniform> = self.lights.values().cloned().collect();
-            if lights.len() < 1 {
-                lights.push(LightUniform::new([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], 0.0, 0.0, 1000.0));
-            }
+        // a light that starts or stops casting shadows changes the shadow layers of the lights after it, so they are uploaded too
+        let mut dirty = dirty.start.min(uniforms.len())..dirty.end.min(uniforms.len());
+        let moved_layers = uniforms.iter().zip(self.uploaded.iter()).enumerate()
+            .filter(|(_, (new, old))| new.shadow_layer != old.shadow_layer)
+            .map(|(index, _)| index);
+        moved_layers.for_each(|index| dirty = dirty.start.min(index)..dirty.end.max(index + 1));
+
+        // if the storage buffer is too small, create a new buffer with room to grow and bind it
+        if self.clusters.is_some() && uniforms.len() > self.light_capacity {
+            self.light_capacity = uniforms.len().next_power_of_two();
+            let mut contents = uniforms.clone();
+            contents.resize(self.light_capacity, LightUniform::from(&Light::point([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0)));
 
             // update light buffer
             self.light_buffer = engine.device.create_buffer_init(
                 &wgpu::util::BufferInitDescriptor {
                     label: Some("Light Buffer"),
-                    contents: bytemuck::cast_slice(&lights),
-                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+                    contents: bytemuck::cast_slice(&contents),
+                    usage: Self::light_buffer_usage(true)
                 }
             );
         
-            // update bind group
-            self.light_bind_group = engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &engine.device.create_bind_group_layout(&lights::LightUniform::BIND_LAYOUT),
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: self.light_buffer.as_entire_binding()
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: self.light_count_buffer.as_entire_binding()
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: self.light_ambient_buffer.as_entire_binding()
-                    }
-                ],
-                label: Some("light_bind_group")
-            });
+            // update bind groups
+            if let Some(clusters) = &mut self.clusters { clusters.set_light_buffers(engine, &self.light_buffer, &self.light_count_buffer); }
+            self.light_bind_group = Self::create_bind_group(engine, &self.light_buffer, &self.light_count_buffer, &self.light_ambient_buffer, &self.shadow_maps, self.clusters.as_ref());
         }
-        // otherwise, update light buffer
-        else {
-            // create array of lights
-            let mut lights: Vec<LightUniform> = self.lights.values().cloned().collect();
-            if lights.len() < 1 {
-                lights.push(LightUniform::new([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], 0.0, 0.0, 1000.0));
-            }
-
-            // update light buffer
+        // otherwise, only update the dirty lights
+        else if !dirty.is_empty() {
             engine.queue.write_buffer(
                 &self.light_buffer, 
-                0, 
-                bytemuck::cast_slice(&lights)
+                (dirty.start * std::mem::size_of::<LightUniform>()) as wgpu::BufferAddress, 
+                bytemuck::cast_slice(&uniforms[dirty])
             );
         }
+        self.uploaded = uniforms;
+    }
+
+    /// On the render graph's `CLUSTER_PASS`, this engine lists the lights that reach each cluster.
+    ///
+    /// Arguments:
+    /// * name: &str - The name of the pass being run.
+    /// * engine: &RenderEngine - The render engine being rendered with.
+    /// * encoder: &mut wgpu::CommandEncoder - The frame's command encoder.
+    fn encode_graph_pass(&mut self, name: &str, engine: &RenderEngine, encoder: &mut wgpu::CommandEncoder) {
+        if name != Self::CLUSTER_PASS { return }
+        if let Some(clusters) = &mut self.clusters { clusters.encode(engine, encoder); }
     }
 
     /// On render, this engine binds its lights to the current render pass.
@@ -172,33 +173,125 @@ impl EngineComponent<&mut RenderEngine> for LightEngine {
 }
 
 impl LightEngine {
+    /// The name of the render graph pass that lists the lights reaching each cluster.
+    pub const CLUSTER_PASS: &'static str = "forte.light_clusters";
+
     /// The bind group currently being used by this engine.
     pub fn bind_group(&self) -> &wgpu::BindGroup { &self.light_bind_group }
 
-    /// Marks the light engine "dirty" so that its buffers will be updated next time this engine is updated.
-    pub fn mark_dirty(&mut self) { self.dirty = true; }
+    /// Marks every light "dirty" so that the whole light buffer will be updated next time this engine is updated.
+    pub fn mark_dirty(&mut self) { self.lights.mark_all_dirty(); self.ambient_dirty = true; }
 
-    /// Adds a light to the engine.
+    /// Adds a light to the engine.  Lights are uploaded in the order they are added, removing a light moves the lights added after it back by one, so they keep their order and are uploaded again.
     /// 
     /// Arguments:
-    /// * id: 32 - The id of the light.
-    /// * light: LightUniform - The lights information.
-    pub fn add_light(&mut self, id: u32, light: LightUniform) { self.lights.insert(id, light); self.mark_dirty(); }
+    /// * light: Light - The lights information.
+    ///
+    /// Returns the handle used to edit or remove the light.
+    pub fn add_light(&mut self, light: Light) -> LightHandle { self.lights.insert(light) }
 
     /// Removes a light from the engine.
     /// 
     /// Arguments:
-    /// * idx: u32 - The index of the light to be removed.
-    pub fn remove_light(&mut self, idx: u32) { self.lights.remove(&idx); self.mark_dirty(); }
+    /// * handle: LightHandle - The handle of the light to be removed.
+    ///
+    /// Returns an option containing the removed light, or none if it was already removed.
+    pub fn remove_light(&mut self, handle: LightHandle) -> Option<Light> { self.lights.remove(handle) }
 
     /// Clears all lights from the engine.
-    pub fn clear_lights(&mut self) { self.lights.clear(); self.mark_dirty(); }
+    pub fn clear_lights(&mut self) { self.lights.clear(); }
+
+    /// Gets a light in the engine.
+    ///
+    /// Arguments:
+    /// * handle: LightHandle - The handle of the light.
+    ///
+    /// Returns an option containing the light, or none if it was removed.
+    pub fn light(&self, handle: LightHandle) -> Option<&Light> { self.lights.get(handle) }
+
+    /// Gets a light in the engine to edit it in place.  Only this light is uploaded again next time this engine is updated.
+    ///
+    /// Arguments:
+    /// * handle: LightHandle - The handle of the light.
+    ///
+    /// Returns an option containing the light, or none if it was removed.
+    pub fn light_mut(&mut self, handle: LightHandle) -> Option<&mut Light> { self.lights.get_mut(handle) }
+
+    /// The number of lights in the engine.
+    pub fn num_lights(&self) -> usize { self.lights.lights().len() }
 
     /// Sets the current ambient light color.
     /// 
     /// Arguments:
     /// * ambient: [f32; 3] - The new ambient color.
-    pub fn set_ambient_color(&mut self, ambient: [f32; 3]) { self.ambient_color = ambient; }
+    pub fn set_ambient_color(&mut self, ambient: [f32; 3]) { self.ambient_color = ambient; self.ambient_dirty = true; }
+
+    /// The settings of the shadow maps drawn for lights that cast shadows.
+    pub fn shadow_settings(&self) -> &ShadowSettings { self.shadow_maps.settings() }
+
+    /// Recreates the shadow maps with the given settings.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to create the shadow maps with.
+    /// * settings: ShadowSettings - The new shadow map settings.
+    pub fn set_shadow_settings(&mut self, engine: &RenderEngine, settings: ShadowSettings) {
+        self.shadow_maps = ShadowMaps::new(engine, settings);
+        self.light_bind_group = Self::create_bind_group(engine, &self.light_buffer, &self.light_count_buffer, &self.light_ambient_buffer, &self.shadow_maps, self.clusters.as_ref());
+        self.lights.mark_all_dirty();
+    }
+
+    /// Draws the shadow maps of all lights that cast shadows.  This should be called once per frame after this engine is updated and before anything lit is rendered.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to draw with.
+    /// * camera: &Camera - The camera the scene will be viewed from, directional light shadows are fit to its view.  When several cameras render the scene, pass the main one, other cameras only see directional light shadows where their view overlaps its view.
+    /// * casters: &[&dyn ShadowCaster] - Everything that casts shadows.
+    pub fn render_shadows(&self, engine: &RenderEngine, camera: &Camera, casters: &[&dyn ShadowCaster]) {
+        self.shadow_maps.render(engine, camera, casters);
+    }
+
+    /// Fits the light clusters to the given camera's view, so that fragments are only lit by lights that can reach them.  The lights reaching each cluster are listed by this engine's `CLUSTER_PASS` in the render graph, which runs before every other pass.  This should be called once per frame.  Until it is called, every fragment is lit by every light.  Does nothing on devices without clustered lighting.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to build the clusters with.
+    /// * camera: &Camera - The camera the scene will be viewed from.  When several cameras render the scene, pass the main one, fragments outside its view are lit by every light.
+    pub fn build_clusters(&mut self, engine: &RenderEngine, camera: &Camera) {
+        if let Some(clusters) = &mut self.clusters { clusters.fit(engine, camera); }
+    }
+
+    /// The usage of the light buffer, a storage buffer when the lights are clustered and a uniform buffer otherwise.
+    fn light_buffer_usage(clustered: bool) -> wgpu::BufferUsages {
+        if clustered { wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST } else { wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST }
+    }
+
+    /// Creates the bind group of the lights, their shadow maps and their clusters if they have them.
+    fn create_bind_group(engine: &RenderEngine, light_buffer: &wgpu::Buffer, light_count_buffer: &wgpu::Buffer, light_ambient_buffer: &wgpu::Buffer, shadow_maps: &ShadowMaps, clusters: Option<&LightClusters>) -> wgpu::BindGroup {
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_buffer.as_entire_binding()
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: light_count_buffer.as_entire_binding()
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: light_ambient_buffer.as_entire_binding()
+            }
+        ];
+        entries.extend(shadow_maps.bind_group_entries());
+        let layout = match clusters {
+            Some(clusters) => { entries.extend(clusters.bind_group_entries()); LightUniform::BIND_LAYOUT },
+            None => LightUniform::UNIFORM_BIND_LAYOUT
+        };
+
+        engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &engine.device.create_bind_group_layout(&layout),
+            entries: &entries,
+            label: Some("light_bind_group")
+        })
+    }
 
     /// Gets teh current ambient light color.
     /// 
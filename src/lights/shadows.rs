@@ -0,0 +1,374 @@
+use cgmath::{Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Vector3, Vector4};
+use wgpu::util::DeviceExt;
+
+use crate::{primitives::{cameras::{Camera, CameraUniform}, textures::depth_textures::DepthTexture}, render::{graph::DepthConvention, render_engine::RenderEngine, OPENGL_TO_WGPU_MATRIX}};
+
+use super::lights::{Light, LightUniform};
+
+/// The maximum number of layers the shadow map array can have, this must match the size of the matrices array in `light.wgsl`.
+pub const MAX_SHADOW_LAYERS: usize = 32;
+
+/// The maximum number of cascades a directional light can split the camera's view into.
+pub const MAX_CASCADES: u32 = 4;
+
+/// The distance from a spot or point light at which its shadow maps start.
+const SHADOW_NEAR: f32 = 0.05;
+
+/// How a light projects its shadows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowKind {
+    /// A light far away, like the sun, that shines along its direction.  The camera's view is split into cascades that each get their own shadow map, so shadows close to the camera are sharper.
+    Directional,
+
+    /// A light that shines along its direction, covering its outer cone with a single perspective shadow map.
+    Spot,
+
+    /// A light that shines in every direction from its position, using a cube of six shadow maps.
+    Point
+}
+
+impl ShadowKind {
+    /// The number of shadow map layers a light with this kind of shadow needs.
+    pub fn num_layers(&self, settings: &ShadowSettings) -> usize {
+        match self {
+            Self::Directional => settings.cascades.clamp(1, MAX_CASCADES) as usize,
+            Self::Spot => 1,
+            Self::Point => 6
+        }
+    }
+}
+
+/// The settings shared by the shadow maps of all lights.
+///
+/// Arguments:
+/// * resolution: u32 - The width and height of each shadow map in pixels.
+/// * layers: u32 - The number of shadow maps that can be drawn, a directional light uses one per cascade, a spot light uses one and a point light uses six.  At most `MAX_SHADOW_LAYERS`.
+/// * cascades: u32 - The number of cascades directional lights split the camera's view into.  At most `MAX_CASCADES`.
+/// * max_distance: f32 - How far from the camera directional shadows are drawn, also used as the range of spot and point light shadows when the light's range is larger.
+/// * normal_bias: f32 - How far, in shadow map texels, surfaces are pushed along their normal before being tested against the shadow maps.  This prevents surfaces shadowing themselves.
+/// * pcf_radius: u32 - The number of texels around each sample that are filtered to soften the edges of shadows.
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowSettings {
+    pub resolution: u32,
+    pub layers: u32,
+    pub cascades: u32,
+    pub max_distance: f32,
+    pub normal_bias: f32,
+    pub pcf_radius: u32
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self { resolution: 1024, layers: 16, cascades: 4, max_distance: 50.0, normal_bias: 1.5, pcf_radius: 1 }
+    }
+}
+
+/// Anything that can draw itself into shadow maps.  When called, the view of the shadow map is bound to bind group 0, and bind groups 1 and 2 are bound to empty placeholders.
+pub trait ShadowCaster {
+    /// Draws the depth of this caster into the current shadow map.
+    ///
+    /// Arguments:
+    /// * pass: &mut wgpu::RenderPass - The depth only render pass of the shadow map.
+    /// * engine: &RenderEngine - The render engine used to draw.
+    fn draw_shadow<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>, engine: &'rpass RenderEngine);
+}
+
+/// The rust representation of the shadow information passed to the shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShadowUniform {
+    matrices: [[[f32; 4]; 4]; MAX_SHADOW_LAYERS],
+
+    // FORMAT: the size of a texel in world units, or at a distance of 1 for perspective maps, and 1 if the map is perspective
+    layers: [[f32; 4]; MAX_SHADOW_LAYERS],
+
+    // FORMAT: normal_bias, pcf_radius, cascades, resolution
+    settings: [f32; 4]
+}
+
+/// A light that has been given shadow map layers.
+#[derive(Debug, Clone, Copy)]
+struct ShadowedLight {
+    light: Light,
+    kind: ShadowKind
+}
+
+/// The shadow maps of all lights that cast shadows, stored as layers of one depth texture array.
+#[derive(Debug)]
+pub struct ShadowMaps {
+    settings: ShadowSettings,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    layer_views: Vec<wgpu::TextureView>,
+    sampler: wgpu::Sampler,
+    buffer: wgpu::Buffer,
+    layer_cameras: Vec<(wgpu::Buffer, wgpu::BindGroup)>,
+    empty_bind_group: wgpu::BindGroup,
+    lights: Vec<ShadowedLight>
+}
+
+impl ShadowMaps {
+    /// The layout of the placeholder bind groups used by shadow pipelines for the bind groups they do not use.
+    pub const EMPTY_BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("empty_bind_layout"),
+        entries: &[]
+    };
+
+    /// The depth bias used by shadow pipelines to prevent surfaces shadowing themselves.
+    pub const DEPTH_BIAS: wgpu::DepthBiasState = wgpu::DepthBiasState {
+        constant: 2,
+        slope_scale: 2.0,
+        clamp: 0.0
+    };
+
+    /// How depth is stored in the shadow maps.  They are drawn with the lights' own projections, so they keep the standard convention whatever the render engine's depth convention is, and shadow pipelines must compare depth with it.
+    pub const DEPTH_CONVENTION: DepthConvention = DepthConvention::Standard;
+
+    /// Creates new shadow maps.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to create the shadow maps with.
+    /// * settings: ShadowSettings - The settings of the shadow maps.
+    pub fn new(engine: &RenderEngine, settings: ShadowSettings) -> Self {
+        // always create at least two layers, otherwise some backends treat the texture as a single 2d texture instead of an array
+        let num_layers = (settings.layers as usize).clamp(2, MAX_SHADOW_LAYERS);
+        let texture = engine.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shadow_maps"),
+            size: wgpu::Extent3d { width: settings.resolution.max(1), height: settings.resolution.max(1), depth_or_array_layers: num_layers as u32 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DepthTexture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[]
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let layer_views = (0 .. num_layers as u32).map(|layer| texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_array_layer: layer,
+            array_layer_count: Some(1),
+            ..Default::default()
+        })).collect();
+        let sampler = engine.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        // create the buffer passed to the lit shaders and a camera for every layer for drawing the shadow maps
+        let buffer = engine.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("shadow_buffer"),
+            contents: bytemuck::cast_slice(&[ShadowUniform::new(&settings)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+        });
+        let camera_layout = engine.device.create_bind_group_layout(&Camera::BIND_LAYOUT);
+        let layer_cameras = (0 .. num_layers).map(|_| {
+            let buffer = engine.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("shadow_camera_buffer"),
+                contents: bytemuck::cast_slice(&[CameraUniform::new()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            });
+            let bind_group = engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("shadow_camera_bind_group"),
+                layout: &camera_layout,
+                entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }]
+            });
+            (buffer, bind_group)
+        }).collect();
+        let empty_bind_group = engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("empty_bind_group"),
+            layout: &engine.device.create_bind_group_layout(&Self::EMPTY_BIND_LAYOUT),
+            entries: &[]
+        });
+
+        Self { settings, texture, view, layer_views, sampler, buffer, layer_cameras, empty_bind_group, lights: Vec::new() }
+    }
+
+    /// The settings these shadow maps were created with.
+    pub fn settings(&self) -> &ShadowSettings { &self.settings }
+
+    /// The depth texture array containing every shadow map.
+    pub fn texture(&self) -> &wgpu::Texture { &self.texture }
+
+    /// Gives each light that casts shadows its shadow map layers, lights are given layers in order until there are no layers left.  Lights without layers do not cast shadows.
+    ///
+    /// Arguments:
+    /// * lights: &[Light] - The lights to assign layers too.
+    /// * uniforms: &mut [LightUniform] - The uniforms of the above lights, their shadow layers are updated.
+    pub fn assign_layers(&mut self, lights: &[Light], uniforms: &mut [LightUniform]) {
+        self.lights.clear();
+        let mut next_layer = 0;
+        lights.iter().zip(uniforms.iter_mut()).for_each(|(light, uniform)| {
+            uniform.shadow_layer = -1;
+            let Some(kind) = light.shadow_kind() else { return };
+            let num_layers = kind.num_layers(&self.settings);
+            if next_layer + num_layers > self.layer_views.len() { return }
+
+            uniform.shadow_layer = next_layer as i32;
+            next_layer += num_layers;
+            self.lights.push(ShadowedLight { light: *light, kind });
+        });
+    }
+
+    /// Draws the shadow maps of every light that was given layers by the last `assign_layers` call.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to draw with.
+    /// * camera: &Camera - The camera the scene will be viewed from, directional light shadows are fit to its view.  Positions outside every cascade, like those only seen by other cameras, are not shadowed by directional lights.
+    /// * casters: &[&dyn ShadowCaster] - Everything that casts shadows.
+    pub fn render(&self, engine: &RenderEngine, camera: &Camera, casters: &[&dyn ShadowCaster]) {
+        // calculate the matrices of every layer
+        let mut uniform = ShadowUniform::new(&self.settings);
+        let mut layer = 0;
+        self.lights.iter().for_each(|shadowed| {
+            self.layer_matrices(engine, camera, shadowed).into_iter().for_each(|(matrix, params)| {
+                uniform.matrices[layer] = matrix.into();
+                uniform.layers[layer] = params;
+                let view_position = match shadowed.light {
+                    Light::Point { position, .. } | Light::Spot { position, .. } => Vector4::new(position[0], position[1], position[2], 0.0),
+                    Light::Directional { .. } => Vector4::new(0.0, 0.0, 0.0, 0.0)
+                };
+                engine.queue.write_buffer(&self.layer_cameras[layer].0, 0, bytemuck::cast_slice(&[CameraUniform { view_position: view_position.into(), view_proj: matrix.into() }]));
+                layer += 1;
+            });
+        });
+        engine.queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        // draw every caster into each used layer
+        let mut encoder = engine.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("shadow_encoder") });
+        self.layer_views.iter().zip(self.layer_cameras.iter()).take(layer).for_each(|(view, (_, camera_bind_group))| {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("shadow_pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(Self::DEPTH_CONVENTION.clear_depth()),
+                        store: wgpu::StoreOp::Store
+                    }),
+                    stencil_ops: None
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None
+            });
+            pass.set_bind_group(0, camera_bind_group, &[]);
+            pass.set_bind_group(1, &self.empty_bind_group, &[]);
+            pass.set_bind_group(2, &self.empty_bind_group, &[]);
+            casters.iter().for_each(|caster| caster.draw_shadow(&mut pass, engine));
+        });
+        engine.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// The bind group entries of the shadow maps, bound alongside the lights.
+    pub(crate) fn bind_group_entries(&self) -> [wgpu::BindGroupEntry<'_>; 3] {
+        [
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&self.view)
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Sampler(&self.sampler)
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: self.buffer.as_entire_binding()
+            }
+        ]
+    }
+
+    /// Calculates the view projection matrix of each layer of the given light, with the layer parameters passed to the shaders.
+    fn layer_matrices(&self, engine: &RenderEngine, camera: &Camera, shadowed: &ShadowedLight) -> Vec<(Matrix4<f32>, [f32; 4])> {
+        let resolution = self.settings.resolution.max(1) as f32;
+        let far = |range: f32| range.min(self.settings.max_distance).max(SHADOW_NEAR * 2.0);
+
+        match (shadowed.kind, shadowed.light) {
+            (ShadowKind::Spot, Light::Spot { position, direction, range, outer_angle, .. }) => {
+                let position = Point3::from(position);
+                let direction = safe_normalize(Vector3::from(direction));
+                let angle = Rad((2.0 * outer_angle).clamp(Rad::from(Deg(1.0)).0, Rad::from(Deg(170.0)).0));
+                let view = Matrix4::look_at_rh(position, position + direction, up_vector(direction));
+                let projection = OPENGL_TO_WGPU_MATRIX * cgmath::perspective(angle, 1.0, SHADOW_NEAR, far(range));
+                vec![(projection * view, [2.0 * (angle.0 / 2.0).tan() / resolution, 1.0, 0.0, 0.0])]
+            },
+            (ShadowKind::Point, Light::Point { position, range, .. }) => {
+                // the faces are ordered +x, -x, +y, -y, +z, -z, matching the face picked in `light.wgsl`
+                let position = Point3::from(position);
+                let projection = OPENGL_TO_WGPU_MATRIX * cgmath::perspective(Deg(90.0), 1.0, SHADOW_NEAR, far(range));
+                [Vector3::unit_x(), -Vector3::unit_x(), Vector3::unit_y(), -Vector3::unit_y(), Vector3::unit_z(), -Vector3::unit_z()].into_iter().map(|face| {
+                    let view = Matrix4::look_at_rh(position, position + face, up_vector(face));
+                    (projection * view, [2.0 / resolution, 1.0, 0.0, 0.0])
+                }).collect()
+            },
+            (ShadowKind::Directional, Light::Directional { direction, .. }) => self.cascade_matrices(engine, camera, safe_normalize(Vector3::from(direction))),
+            _ => Vec::new()
+        }
+    }
+
+    /// Splits the view of the camera into cascades and fits an orthographic shadow map around each of them.
+    fn cascade_matrices(&self, engine: &RenderEngine, camera: &Camera, direction: Vector3<f32>) -> Vec<(Matrix4<f32>, [f32; 4])> {
+        let num_cascades = self.settings.cascades.clamp(1, MAX_CASCADES);
+        let (near, far) = camera.projection.depth_range();
+        let near = near.max(0.0001);
+        let far = far.min(self.settings.max_distance).max(near * 2.0);
+        let resolution = self.settings.resolution.max(1) as f32;
+
+        // blend between logarithmic and linear splits, logarithmic splits give closer cascades more detail
+        let split = |index: u32| {
+            let ratio = index as f32 / num_cascades as f32;
+            let logarithmic = near * (far / near).powf(ratio);
+            let linear = near + (far - near) * ratio;
+            0.75 * logarithmic + 0.25 * linear
+        };
+
+        let light_view = Matrix4::look_at_rh(Point3::origin(), Point3::from_vec(direction), up_vector(direction));
+        (0 .. num_cascades).map(|index| {
+            // find the corners of this cascade's slice of the camera's view in world space
+            let corners = camera.frustum_corners(engine, split(index), split(index + 1));
+
+            // fit a sphere around the slice so the shadow map does not change size as the camera rotates
+            let center = corners.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, corner| sum + corner) / corners.len() as f32;
+            let radius = corners.iter().map(|corner| (corner - center).magnitude()).fold(0.0, f32::max);
+            let radius = (radius * 16.0).ceil() / 16.0;
+            let texel = 2.0 * radius / resolution;
+
+            // snap the center to texels so shadow edges do not shimmer as the camera moves
+            let light_center = light_view * center.extend(1.0);
+            let x = (light_center.x / texel).floor() * texel;
+            let y = (light_center.y / texel).floor() * texel;
+
+            // everything between the light and the cascade can cast shadows onto it, so pull the near plane back
+            let projection = OPENGL_TO_WGPU_MATRIX * cgmath::ortho(x - radius, x + radius, y - radius, y + radius, -light_center.z - radius - self.settings.max_distance, -light_center.z + radius);
+            (projection * light_view, [texel, 0.0, 0.0, 0.0])
+        }).collect()
+    }
+}
+
+impl ShadowUniform {
+    fn new(settings: &ShadowSettings) -> Self {
+        let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+        Self {
+            matrices: [identity; MAX_SHADOW_LAYERS],
+            layers: [[0.0; 4]; MAX_SHADOW_LAYERS],
+            settings: [settings.normal_bias, settings.pcf_radius as f32, settings.cascades.clamp(1, MAX_CASCADES) as f32, settings.resolution.max(1) as f32]
+        }
+    }
+}
+
+/// Normalizes the given vector, returning straight down if it has no length.
+fn safe_normalize(vector: Vector3<f32>) -> Vector3<f32> {
+    if vector.magnitude2() > 0.0 { vector.normalize() } else { -Vector3::unit_y() }
+}
+
+/// Picks an up vector for looking in the given direction that is not parallel to it.
+fn up_vector(direction: Vector3<f32>) -> Vector3<f32> {
+    if direction.normalize().y.abs() > 0.99 { Vector3::unit_z() } else { Vector3::unit_y() }
+}
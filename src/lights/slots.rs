@@ -0,0 +1,205 @@
+use std::ops::Range;
+
+use super::lights::Light;
+
+/// A handle to a light added to a `LightEngine`.  Handles stay valid until their light is removed, after which they no longer refer to any light, even if a new light reuses their slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LightHandle {
+    index: u32,
+    generation: u32
+}
+
+/// A slot that a handle refers to, pointing to its light in the dense list of lights.
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    generation: u32,
+    dense_index: Option<u32>
+}
+
+/// A slot map of lights.  Lights are stored densely in the order they were added so that they can be uploaded as is, removing a light moves the lights after it back by one, keeping their order.
+#[derive(Debug, Default)]
+pub(crate) struct LightSlots {
+    slots: Vec<Slot>,
+    free_slots: Vec<u32>,
+    lights: Vec<Light>,
+    owners: Vec<u32>,
+    dirty: Option<Range<usize>>
+}
+
+impl LightSlots {
+    /// Adds a light, reusing a free slot if there is one.
+    ///
+    /// Returns the handle of the new light.
+    pub fn insert(&mut self, light: Light) -> LightHandle {
+        let dense_index = self.lights.len();
+        let index = match self.free_slots.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot { generation: 0, dense_index: None });
+                self.slots.len() as u32 - 1
+            }
+        };
+
+        let slot = &mut self.slots[index as usize];
+        slot.dense_index = Some(dense_index as u32);
+        let handle = LightHandle { index, generation: slot.generation };
+        self.lights.push(light);
+        self.owners.push(index);
+        self.mark_dirty(dense_index..dense_index + 1);
+        handle
+    }
+
+    /// Removes the light of the given handle, freeing its slot for reuse.
+    ///
+    /// Returns an option containing the removed light, or none if the handle no longer refers to a light.
+    pub fn remove(&mut self, handle: LightHandle) -> Option<Light> {
+        let dense_index = self.dense_index(handle)?;
+        let slot = &mut self.slots[handle.index as usize];
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.dense_index = None;
+        self.free_slots.push(handle.index);
+
+        // move the lights after the removed light back by one, so they keep their order
+        let light = self.lights.remove(dense_index);
+        self.owners.remove(dense_index);
+        self.owners[dense_index..].iter().enumerate().for_each(|(offset, &moved)| {
+            self.slots[moved as usize].dense_index = Some((dense_index + offset) as u32);
+        });
+        self.mark_dirty(dense_index..self.lights.len());
+        Some(light)
+    }
+
+    /// Removes every light, every existing handle no longer refers to a light.
+    pub fn clear(&mut self) {
+        self.owners.drain(..).for_each(|index| {
+            let slot = &mut self.slots[index as usize];
+            slot.generation = slot.generation.wrapping_add(1);
+            slot.dense_index = None;
+            self.free_slots.push(index);
+        });
+        self.lights.clear();
+        self.mark_all_dirty();
+    }
+
+    /// Returns an option containing the light of the given handle, or none if it was removed.
+    pub fn get(&self, handle: LightHandle) -> Option<&Light> {
+        self.dense_index(handle).map(|index| &self.lights[index])
+    }
+
+    /// Returns an option containing a mutable reference to the light of the given handle, or none if it was removed.  The light is marked dirty.
+    pub fn get_mut(&mut self, handle: LightHandle) -> Option<&mut Light> {
+        let index = self.dense_index(handle)?;
+        self.mark_dirty(index..index + 1);
+        Some(&mut self.lights[index])
+    }
+
+    /// The lights in the order they are uploaded.
+    pub fn lights(&self) -> &[Light] { &self.lights }
+
+    /// Marks the given range of the dense lights dirty, merging it with the range already marked.
+    pub fn mark_dirty(&mut self, range: Range<usize>) {
+        self.dirty = Some(match self.dirty.take() {
+            Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+            None => range
+        });
+    }
+
+    /// Marks every light dirty.
+    pub fn mark_all_dirty(&mut self) { self.mark_dirty(0..self.lights.len()); }
+
+    /// Returns an option containing the range of dense lights marked dirty since the last call, or none if nothing changed.
+    pub fn take_dirty(&mut self) -> Option<Range<usize>> { self.dirty.take() }
+
+    /// The index of the light of the given handle in the dense list of lights.
+    fn dense_index(&self, handle: LightHandle) -> Option<usize> {
+        let slot = self.slots.get(handle.index as usize)?;
+        if slot.generation != handle.generation { return None }
+        slot.dense_index.map(|index| index as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light(intensity: f32) -> Light { Light::point([0.0, 0.0, 0.0], [1.0, 1.0, 1.0], intensity) }
+
+    fn intensities(slots: &LightSlots) -> Vec<f32> {
+        slots.lights().iter().map(|light| match light { Light::Point { intensity, .. } => *intensity, _ => unreachable!() }).collect()
+    }
+
+    #[test]
+    fn remove_keeps_order_of_remaining_lights() {
+        let mut slots = LightSlots::default();
+        let handles: Vec<LightHandle> = (0 .. 4).map(|index| slots.insert(light(index as f32))).collect();
+        slots.take_dirty();
+
+        assert!(slots.remove(handles[1]).is_some());
+        assert_eq!(intensities(&slots), vec![0.0, 2.0, 3.0]);
+        assert_eq!(slots.take_dirty(), Some(1 .. 3));
+
+        // handles of the moved lights still find them
+        assert_eq!(slots.get(handles[3]), Some(&light(3.0)));
+        assert_eq!(slots.get(handles[2]), Some(&light(2.0)));
+    }
+
+    #[test]
+    fn stale_handles_are_rejected_after_slot_reuse() {
+        let mut slots = LightSlots::default();
+        let first = slots.insert(light(1.0));
+        assert_eq!(slots.remove(first), Some(light(1.0)));
+        assert_eq!(slots.remove(first), None);
+
+        let second = slots.insert(light(2.0));
+        assert_eq!(second.index, first.index);
+        assert_ne!(second, first);
+        assert!(slots.get(first).is_none());
+        assert!(slots.get_mut(first).is_none());
+        assert_eq!(slots.get(second), Some(&light(2.0)));
+    }
+
+    #[test]
+    fn get_mut_marks_only_its_light_dirty() {
+        let mut slots = LightSlots::default();
+        let handles: Vec<LightHandle> = (0 .. 4).map(|index| slots.insert(light(index as f32))).collect();
+        assert_eq!(slots.take_dirty(), Some(0 .. 4));
+        assert_eq!(slots.take_dirty(), None);
+
+        *slots.get_mut(handles[2]).unwrap() = light(5.0);
+        assert_eq!(slots.take_dirty(), Some(2 .. 3));
+        assert_eq!(intensities(&slots), vec![0.0, 1.0, 5.0, 3.0]);
+    }
+
+    #[test]
+    fn dirty_ranges_merge() {
+        let mut slots = LightSlots::default();
+        let handles: Vec<LightHandle> = (0 .. 6).map(|index| slots.insert(light(index as f32))).collect();
+        slots.take_dirty();
+
+        slots.get_mut(handles[4]);
+        slots.get_mut(handles[1]);
+        assert_eq!(slots.take_dirty(), Some(1 .. 5));
+
+        slots.mark_dirty(2 .. 3);
+        slots.mark_dirty(0 .. 1);
+        assert_eq!(slots.take_dirty(), Some(0 .. 3));
+    }
+
+    #[test]
+    fn clear_invalidates_every_handle() {
+        let mut slots = LightSlots::default();
+        let handles: Vec<LightHandle> = (0 .. 3).map(|index| slots.insert(light(index as f32))).collect();
+        slots.take_dirty();
+
+        slots.clear();
+        assert!(slots.lights().is_empty());
+        assert!(handles.iter().all(|handle| slots.get(*handle).is_none()));
+        assert_eq!(slots.take_dirty(), Some(0 .. 0));
+
+        // cleared slots are reused with new generations
+        let handle = slots.insert(light(7.0));
+        assert!(!handles.contains(&handle));
+        assert_eq!(slots.take_dirty(), Some(0 .. 1));
+        assert_eq!(intensities(&slots), vec![7.0]);
+    }
+}
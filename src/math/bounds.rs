@@ -0,0 +1,203 @@
+use cgmath::*;
+
+/// An axis aligned box bounding a set of points, like the vertices of a mesh.
+///
+/// Arguments:
+/// * min: Vector3<f32> - The corner of the box with the smallest coordinates.
+/// * max: Vector3<f32> - The corner of the box with the largest coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self { Self { min: Vector3::zero(), max: Vector3::zero() } }
+}
+
+impl BoundingBox {
+    /// Creates the smallest box containing all the given points.  If there are no points, the box is a single point at the origin.
+    ///
+    /// Arguments:
+    /// * points: impl IntoIterator<Item = Vector3<f32>> - The points to bound.
+    pub fn from_points(points: impl IntoIterator<Item = Vector3<f32>>) -> Self {
+        let mut points = points.into_iter();
+        let Some(first) = points.next() else { return Self::default() };
+        points.fold(Self { min: first, max: first }, |bounds, point| Self {
+            min: Vector3::new(bounds.min.x.min(point.x), bounds.min.y.min(point.y), bounds.min.z.min(point.z)),
+            max: Vector3::new(bounds.max.x.max(point.x), bounds.max.y.max(point.y), bounds.max.z.max(point.z))
+        })
+    }
+
+    /// The center of this box.
+    pub fn center(&self) -> Vector3<f32> { (self.min + self.max) * 0.5 }
+
+    /// Half the size of this box along each axis.
+    pub fn extents(&self) -> Vector3<f32> { (self.max - self.min) * 0.5 }
+
+    /// Returns the smallest axis aligned box containing this box after it is transformed by the given matrix.
+    ///
+    /// Arguments:
+    /// * matrix: &Matrix4<f32> - The matrix to transform this box with, like the model matrix of an instance.
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        let center = (matrix * self.center().extend(1.0)).truncate();
+        let extents = self.extents();
+        let axis = |column: Vector4<f32>| Vector3::new(column.x.abs(), column.y.abs(), column.z.abs());
+        let extents = axis(matrix.x) * extents.x + axis(matrix.y) * extents.y + axis(matrix.z) * extents.z;
+        Self { min: center - extents, max: center + extents }
+    }
+}
+
+/// The 6 planes enclosing everything a camera can see.  Each plane is stored as a normal pointing into the frustum and a distance, so a point `p` is on the inside of a plane when `dot(normal, p) + distance >= 0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vector4<f32>; 6]
+}
+
+impl Frustum {
+    /// Extracts the frustum planes from a WGPU view projection matrix, which maps depth to the range 0 to 1.
+    ///
+    /// Arguments:
+    /// * view_projection: &Matrix4<f32> - The view projection matrix of the camera, like `Camera::build_view_projection_matrix`.
+    pub fn from_matrix(view_projection: &Matrix4<f32>) -> Self {
+        let row = |index: usize| Vector4::new(view_projection.x[index], view_projection.y[index], view_projection.z[index], view_projection.w[index]);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let planes = [w + x, w - x, w + y, w - y, z, w - z].map(|plane| plane / plane.truncate().magnitude().max(f32::EPSILON));
+        Self { planes }
+    }
+
+    /// Returns true if any part of the given box may be inside this frustum.  Boxes close to the corners of the frustum may be reported as inside even when they are not.
+    ///
+    /// Arguments:
+    /// * bounds: &BoundingBox - The box to test, in the same space as the frustum.
+    pub fn intersects_box(&self, bounds: &BoundingBox) -> bool {
+        let center = bounds.center();
+        let extents = bounds.extents();
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            let radius = normal.x.abs() * extents.x + normal.y.abs() * extents.y + normal.z.abs() * extents.z;
+            normal.dot(center) + plane.w + radius >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::cameras::Projection;
+
+    fn unit_box() -> BoundingBox { BoundingBox { min: Vector3::new(-1.0, -1.0, -1.0), max: Vector3::new(1.0, 1.0, 1.0) } }
+
+    fn assert_close(actual: Vector3<f32>, expected: Vector3<f32>) {
+        assert!((actual - expected).magnitude() < 1e-5, "expected {:?}, got {:?}", expected, actual);
+    }
+
+    /// The view projection of a camera at the origin looking down -Z with each depth convention, the views are 4 wide and tall 2 units in front of the camera.
+    fn view_projections() -> [Matrix4<f32>; 4] {
+        // maps depth from 0 to 1 at the near and far planes to 1 to 0
+        #[rustfmt::skip]
+        let reverse = Matrix4::new(
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, -1.0, 0.0,
+            0.0, 0.0, 1.0, 1.0
+        );
+        let perspective = Projection::Perspective { fovy: 90.0, znear: 1.0, zfar: 50.0 }.matrix(1.0);
+        [
+            perspective,
+            Projection::Orthographic { size: 4.0, znear: 1.0, zfar: 50.0 }.matrix(1.0),
+            reverse * perspective,
+            Projection::InfinitePerspective { fovy: 90.0, znear: 1.0 }.matrix(1.0)
+        ]
+    }
+
+    /// A small box centered on the given point.
+    fn box_at(x: f32, y: f32, z: f32) -> BoundingBox {
+        let center = Vector3::new(x, y, z);
+        BoundingBox { min: center - Vector3::new(0.1, 0.1, 0.1), max: center + Vector3::new(0.1, 0.1, 0.1) }
+    }
+
+    #[test]
+    fn from_points_bounds_every_point() {
+        let bounds = BoundingBox::from_points([Vector3::new(1.0, -2.0, 3.0), Vector3::new(-4.0, 5.0, 0.5), Vector3::new(0.0, 0.0, -6.0)]);
+        assert_eq!(bounds.min, Vector3::new(-4.0, -2.0, -6.0));
+        assert_eq!(bounds.max, Vector3::new(1.0, 5.0, 3.0));
+        assert_eq!(BoundingBox::from_points([]), BoundingBox::default());
+    }
+
+    #[test]
+    fn transformed_moves_and_scales_box() {
+        let bounds = BoundingBox { min: Vector3::new(0.0, 0.0, 0.0), max: Vector3::new(2.0, 4.0, 6.0) };
+        let matrix = Matrix4::from_translation(Vector3::new(10.0, -1.0, 0.0)) * Matrix4::from_nonuniform_scale(2.0, 0.5, -1.0);
+        let transformed = bounds.transformed(&matrix);
+        assert_close(transformed.min, Vector3::new(10.0, -1.0, -6.0));
+        assert_close(transformed.max, Vector3::new(14.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn transformed_contains_rotated_corners() {
+        // rotating the unit box 45 degrees about Y makes it sqrt(2) wide along X and Z
+        let rotation = Matrix4::from_angle_y(Deg(45.0));
+        let transformed = unit_box().transformed(&rotation);
+        let half = 2.0f32.sqrt();
+        assert_close(transformed.min, Vector3::new(-half, -1.0, -half));
+        assert_close(transformed.max, Vector3::new(half, 1.0, half));
+
+        // an arbitrary transform never leaves a corner outside the box
+        let matrix = Matrix4::from_translation(Vector3::new(3.0, 1.0, -2.0)) * Matrix4::from_axis_angle(Vector3::new(1.0, 2.0, 3.0).normalize(), Deg(37.0)) * Matrix4::from_scale(1.5);
+        let transformed = unit_box().transformed(&matrix);
+        for corner in 0 .. 8 {
+            let corner = Vector3::new(if corner & 1 == 0 { -1.0 } else { 1.0 }, if corner & 2 == 0 { -1.0 } else { 1.0 }, if corner & 4 == 0 { -1.0 } else { 1.0 });
+            let point = (matrix * corner.extend(1.0)).truncate();
+            assert!((0 .. 3).all(|axis| point[axis] >= transformed.min[axis] - 1e-5 && point[axis] <= transformed.max[axis] + 1e-5), "{:?} outside {:?}", point, transformed);
+        }
+    }
+
+    #[test]
+    fn frustum_planes_are_normalized_and_face_inwards() {
+        for view_projection in view_projections() {
+            let frustum = Frustum::from_matrix(&view_projection);
+            for plane in frustum.planes {
+                let magnitude = plane.truncate().magnitude();
+                if magnitude > 0.0 { assert!((magnitude - 1.0).abs() < 1e-5, "{:?} is not normalized", plane); }
+                assert!(plane.truncate().dot(Vector3::new(0.0, 0.0, -2.0)) + plane.w >= 0.0, "{:?} does not contain the view", plane);
+            }
+        }
+    }
+
+    #[test]
+    fn frustum_intersects_boxes_inside_view() {
+        for view_projection in view_projections() {
+            let frustum = Frustum::from_matrix(&view_projection);
+            assert!(frustum.intersects_box(&box_at(0.0, 0.0, -2.0)), "{:?}", view_projection);
+            assert!(frustum.intersects_box(&box_at(1.5, -1.5, -10.0)), "{:?}", view_projection);
+
+            // boxes overlapping the near plane or a side plane are kept
+            assert!(frustum.intersects_box(&box_at(0.0, 0.0, -0.95)), "{:?}", view_projection);
+            assert!(frustum.intersects_box(&box_at(2.05, 0.0, -2.0)), "{:?}", view_projection);
+        }
+    }
+
+    #[test]
+    fn frustum_rejects_boxes_outside_view() {
+        for view_projection in view_projections() {
+            let frustum = Frustum::from_matrix(&view_projection);
+            assert!(!frustum.intersects_box(&box_at(0.0, 0.0, 2.0)), "{:?}", view_projection);
+            assert!(!frustum.intersects_box(&box_at(0.0, 0.0, -0.5)), "{:?}", view_projection);
+            assert!(!frustum.intersects_box(&box_at(2.5, 0.0, -2.0)), "{:?}", view_projection);
+            assert!(!frustum.intersects_box(&box_at(0.0, -2.5, -2.0)), "{:?}", view_projection);
+        }
+    }
+
+    #[test]
+    fn frustum_far_plane_depends_on_projection() {
+        let [perspective, orthographic, reverse, infinite] = view_projections().map(|view_projection| Frustum::from_matrix(&view_projection));
+        let far_box = box_at(0.0, 0.0, -60.0);
+        assert!(!perspective.intersects_box(&far_box));
+        assert!(!orthographic.intersects_box(&far_box));
+        assert!(!reverse.intersects_box(&far_box));
+        assert!(reverse.intersects_box(&box_at(0.0, 0.0, -49.95)));
+        assert!(infinite.intersects_box(&far_box));
+        assert!(infinite.intersects_box(&box_at(0.0, 0.0, -1.0e5)));
+    }
+}
@@ -1,5 +1,7 @@
+pub mod bounds;
 pub mod euler;
 pub mod quaternion;
+pub mod ray;
 pub mod transforms;
 pub mod vec;
 pub mod vector;
\ No newline at end of file
@@ -0,0 +1,65 @@
+use cgmath::*;
+
+use super::bounds::BoundingBox;
+
+/// A half line starting at a point and going on forever in one direction, for example, the line under the cursor from `Camera::screen_to_ray`.
+///
+/// Arguments:
+/// * origin: Vector3<f32> - The point the ray starts at.
+/// * direction: Vector3<f32> - The normalized direction the ray goes in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3<f32>,
+    pub direction: Vector3<f32>
+}
+
+impl Ray {
+    /// Creates a new ray, normalizing its direction.
+    ///
+    /// Arguments:
+    /// * origin: Vector3<f32> - The point the ray starts at.
+    /// * direction: Vector3<f32> - The direction the ray goes in, this does not need to be normalized.
+    pub fn new(origin: Vector3<f32>, direction: Vector3<f32>) -> Self { Self { origin, direction: direction.normalize() } }
+
+    /// The point at the given distance along this ray.
+    pub fn at(&self, distance: f32) -> Vector3<f32> { self.origin + self.direction * distance }
+
+    /// Finds where this ray first enters the given box.
+    ///
+    /// Arguments:
+    /// * bounds: &BoundingBox - The box to test, in the same space as the ray.
+    ///
+    /// Returns an option containing the distance along the ray to the box, 0 if the ray starts inside it, or none if the ray misses it.
+    pub fn intersect_box(&self, bounds: &BoundingBox) -> Option<f32> {
+        // clip the ray against the slab between each pair of faces
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+        for axis in 0 .. 3 {
+            let (origin, direction) = (self.origin[axis], self.direction[axis]);
+            if direction.abs() <= f32::EPSILON {
+                if origin < bounds.min[axis] || origin > bounds.max[axis] { return None }
+                continue;
+            }
+            let first = (bounds.min[axis] - origin) / direction;
+            let second = (bounds.max[axis] - origin) / direction;
+            near = near.max(first.min(second));
+            far = far.min(first.max(second));
+            if near > far { return None }
+        }
+        Some(near)
+    }
+
+    /// Finds where this ray crosses the given plane.
+    ///
+    /// Arguments:
+    /// * point: Vector3<f32> - Any point on the plane.
+    /// * normal: Vector3<f32> - The normal of the plane, the ray hits the plane from either side.
+    ///
+    /// Returns an option containing the distance along the ray to the plane, or none if the ray is parallel to the plane or points away from it.
+    pub fn intersect_plane(&self, point: Vector3<f32>, normal: Vector3<f32>) -> Option<f32> {
+        let facing = self.direction.dot(normal);
+        if facing.abs() <= f32::EPSILON { return None }
+        let distance = (point - self.origin).dot(normal) / facing;
+        (distance >= 0.0).then_some(distance)
+    }
+}
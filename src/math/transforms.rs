@@ -31,4 +31,17 @@ impl Transform {
         let translation = cgmath::Matrix4::from_translation(self.position);
         return translation * rotation * scale;
     }
+
+    /// Blends this transform with another, positions and scales are linearly interpolated and rotations are spherically interpolated.
+    /// 
+    /// Arguments:
+    /// * other: &Transform - The transform to blend towards.
+    /// * amount: f32 - How far to blend towards the other transform, 0 being this transform and 1 being the other transform.
+    pub fn lerp(&self, other: &Transform, amount: f32) -> Transform {
+        Transform {
+            position: self.position.lerp(other.position, amount),
+            rotation: self.rotation.slerp(other.rotation, amount),
+            scale: self.scale.lerp(other.scale, amount)
+        }
+    }
 }
@@ -0,0 +1,578 @@
+use std::{collections::HashMap, ops::{Add, Mul}};
+
+use cgmath::{InnerSpace, Quaternion, Vector3, VectorSpace};
+
+use crate::{math::transforms::Transform, render::render_engine::RenderEngine};
+
+use super::Model;
+
+/// The local transforms and morph target weights of a model's nodes, keyed by node index.
+#[derive(Debug, Clone, Default)]
+pub struct Pose {
+    pub transforms: HashMap<usize, Transform>,
+    pub weights: HashMap<usize, Vec<f32>>
+}
+
+impl Pose {
+    /// Blends this pose with another, only nodes in this pose are kept.
+    ///
+    /// Arguments:
+    /// * other: &Pose - The pose to blend towards.
+    /// * amount: f32 - How far to blend towards the other pose, 0 being this pose and 1 being the other pose.
+    pub fn lerp(&self, other: &Pose, amount: f32) -> Pose {
+        let transforms = self.transforms.iter().map(|(index, from)| {
+            (*index, other.transforms.get(index).map(|to| from.lerp(to, amount)).unwrap_or(*from))
+        }).collect();
+        let weights = self.weights.iter().map(|(index, from)| {
+            let blended = match other.weights.get(index) {
+                Some(to) => from.iter().zip(to.iter()).map(|(from, to)| from + (to - from) * amount).collect(),
+                None => from.clone()
+            };
+            (*index, blended)
+        }).collect();
+        Pose { transforms, weights }
+    }
+}
+
+/// How the values between two keyframes of a channel are calculated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Values are linearly interpolated, rotations are spherically interpolated.
+    Linear,
+    /// The value of the previous keyframe is used until the next keyframe.
+    Step,
+    /// Values are interpolated with a cubic hermite spline, each keyframe stores an in tangent, a value and an out tangent.
+    CubicSpline
+}
+
+/// The keyframe values of a channel and the property of the node they animate.
+#[derive(Debug, Clone)]
+pub enum ChannelValues {
+    Translation(Vec<Vector3<f32>>),
+    Rotation(Vec<Quaternion<f32>>),
+    Scale(Vec<Vector3<f32>>),
+    /// The morph target weights of each keyframe, formatted as `[keyframe][target]`.
+    Weights(Vec<f32>)
+}
+
+/// Animates one property of one node.
+///
+/// Arguments:
+/// * node: usize - The index of the node this channel animates.
+/// * interpolation: Interpolation - How values between keyframes are calculated.
+/// * times: Vec<f32> - The time of each keyframe in seconds.
+/// * values: ChannelValues - The value of each keyframe, 3 values per keyframe for cubic spline interpolation.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub node: usize,
+    pub interpolation: Interpolation,
+    pub times: Vec<f32>,
+    pub values: ChannelValues
+}
+
+impl Channel {
+    /// Samples this channel at the given time and writes the result into the given pose, if the pose contains the node this channel animates.
+    ///
+    /// Arguments:
+    /// * time: f32 - The time in seconds to sample at.
+    /// * pose: &mut Pose - The pose to write into.
+    pub fn sample(&self, time: f32, pose: &mut Pose) {
+        // morph target weights are sampled one target at a time
+        if let ChannelValues::Weights(values) = &self.values {
+            if let Some(weights) = pose.weights.get_mut(&self.node) {
+                let stride = if self.interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+                let num_targets = values.len().checked_div(self.times.len() * stride).unwrap_or(0);
+                for (target, weight) in weights.iter_mut().enumerate().take(num_targets) {
+                    let target_values: Vec<f32> = values.iter().skip(target).step_by(num_targets).copied().collect();
+                    if let Some(value) = sample_keyframes(&self.times, &target_values, self.interpolation, time, |a, b, t| a + (b - a) * t) { *weight = value; }
+                }
+            }
+            return;
+        }
+
+        let transform = match pose.transforms.get_mut(&self.node) { Some(transform) => transform, None => return };
+        match &self.values {
+            ChannelValues::Translation(values) => {
+                if let Some(value) = sample_keyframes(&self.times, values, self.interpolation, time, |a, b, t| a.lerp(b, t)) { transform.position = value; }
+            },
+            ChannelValues::Rotation(values) => {
+                if let Some(value) = sample_keyframes(&self.times, values, self.interpolation, time, |a, b, t| a.slerp(b, t)) { transform.rotation = value.normalize(); }
+            },
+            ChannelValues::Scale(values) => {
+                if let Some(value) = sample_keyframes(&self.times, values, self.interpolation, time, |a, b, t| a.lerp(b, t)) { transform.scale = value; }
+            },
+            ChannelValues::Weights(_) => {}
+        }
+    }
+}
+
+/// A named set of channels that animate a model's nodes together.
+///
+/// Arguments:
+/// * name: Option<String> - The name of the clip given in the file it was loaded from.
+/// * duration: f32 - The length of the clip in seconds, the time of its last keyframe.
+/// * channels: Vec<Channel> - The channels of this clip.
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+    pub name: Option<String>,
+    pub duration: f32,
+    pub channels: Vec<Channel>
+}
+
+impl AnimationClip {
+    /// Samples all channels of this clip at the given time, writing the results into the given pose.  Nodes that are not in the pose are ignored.
+    ///
+    /// Arguments:
+    /// * time: f32 - The time in seconds to sample at.
+    /// * pose: &mut Pose - The pose to write into, nodes not animated by this clip keep their transform and weights.
+    pub fn sample(&self, time: f32, pose: &mut Pose) {
+        self.channels.iter().for_each(|channel| channel.sample(time, pose));
+    }
+}
+
+/// A clip that is being played by an `AnimationPlayer`.
+///
+/// Arguments:
+/// * clip: usize - The index of the clip in the model's animations.
+/// * time: f32 - The current time in the clip in seconds.
+/// * looping: bool - If true, the clip restarts when it reaches its end, otherwise it holds its last frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayingClip {
+    pub clip: usize,
+    pub time: f32,
+    pub looping: bool
+}
+
+/// Plays the animation clips of a model, with support for looping and cross fading from one clip to another.
+///
+/// Example:
+/// ```rust
+/// // in create
+/// let mut player = AnimationPlayer::new(&model);
+/// player.play(model.animation("Idle").unwrap(), true);
+///
+/// // when the character starts moving
+/// player.cross_fade(model.animation("Walk").unwrap(), true, 0.25);
+///
+/// // in update
+/// player.update(engine, &mut self.model);
+/// ```
+///
+/// Arguments:
+/// * speed: f32 - How fast clips are played, 1 is normal speed.
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer {
+    pub speed: f32,
+
+    rest_pose: Pose,
+    current: Option<PlayingClip>,
+    previous: Option<PlayingClip>,
+    fade_time: f32,
+    fade_duration: f32
+}
+
+impl AnimationPlayer {
+    /// Creates a new animation player for the given model.  The current pose of the model is used for any property a clip does not animate.
+    ///
+    /// Arguments:
+    /// * model: &Model - The model that will be animated.
+    pub fn new(model: &Model) -> Self {
+        Self {
+            speed: 1.0,
+            rest_pose: model.pose(),
+            current: None,
+            previous: None,
+            fade_time: 0.0,
+            fade_duration: 0.0
+        }
+    }
+
+    /// Immediately starts playing the given clip from its start, stopping any other clips.
+    ///
+    /// Arguments:
+    /// * clip: usize - The index of the clip in the model's animations.
+    /// * looping: bool - If true, the clip restarts when it reaches its end.
+    pub fn play(&mut self, clip: usize, looping: bool) {
+        self.current = Some(PlayingClip { clip, time: 0.0, looping });
+        self.previous = None;
+    }
+
+    /// Starts playing the given clip from its start, blending from the currently playing clip over the given duration.
+    ///
+    /// Arguments:
+    /// * clip: usize - The index of the clip in the model's animations.
+    /// * looping: bool - If true, the clip restarts when it reaches its end.
+    /// * duration: f32 - The number of seconds to blend between the clips over.
+    pub fn cross_fade(&mut self, clip: usize, looping: bool, duration: f32) {
+        if self.current.is_none() || duration <= 0.0 { return self.play(clip, looping) }
+        self.previous = self.current.take();
+        self.current = Some(PlayingClip { clip, time: 0.0, looping });
+        self.fade_time = 0.0;
+        self.fade_duration = duration;
+    }
+
+    /// Stops all clips, the model will be returned to its rest pose on the next update.
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.previous = None;
+    }
+
+    /// Returns the clip that is currently playing, if any.
+    pub fn current(&self) -> Option<&PlayingClip> { self.current.as_ref() }
+
+    /// Returns true while the player is blending between two clips.
+    pub fn is_fading(&self) -> bool { self.previous.is_some() }
+
+    /// Returns true if no clip is playing, or the current clip is not looping and has reached its end.
+    ///
+    /// Arguments:
+    /// * model: &Model - The model the clips belong too.
+    pub fn is_finished(&self, model: &Model) -> bool {
+        match &self.current {
+            Some(current) => !current.looping && model.animations.get(current.clip).map(|clip| current.time >= clip.duration).unwrap_or(true),
+            None => true
+        }
+    }
+
+    /// Moves all playing clips forward by the given number of seconds, scaled by the players speed.
+    ///
+    /// Arguments:
+    /// * model: &Model - The model the clips belong too.
+    /// * delta_time: f32 - The number of seconds to advance by.
+    pub fn advance(&mut self, model: &Model, delta_time: f32) {
+        let delta_time = delta_time * self.speed;
+        if let Some(current) = &mut self.current { advance_clip(model, current, delta_time); }
+        if let Some(previous) = &mut self.previous { advance_clip(model, previous, delta_time); }
+
+        // finish cross fades
+        if self.previous.is_some() {
+            self.fade_time += delta_time;
+            if self.fade_time >= self.fade_duration { self.previous = None; }
+        }
+    }
+
+    /// Samples the playing clips into a pose, blending between them if the player is cross fading.
+    ///
+    /// Arguments:
+    /// * model: &Model - The model the clips belong too.
+    ///
+    /// Returns the sampled pose.
+    pub fn sample(&self, model: &Model) -> Pose {
+        let current = self.sample_clip(model, self.current.as_ref());
+        match &self.previous {
+            Some(previous) => {
+                let amount = (self.fade_time / self.fade_duration).clamp(0.0, 1.0);
+                self.sample_clip(model, Some(previous)).lerp(&current, amount)
+            },
+            None => current
+        }
+    }
+
+    /// Advances the playing clips by the engine's delta time, then applies the sampled pose to the given model and updates its transforms and morph target weights.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine, used for its delta time and to update the model's buffers.
+    /// * model: &mut Model - The model to animate.
+    pub fn update(&mut self, engine: &RenderEngine, model: &mut Model) {
+        self.advance(model, engine.delta_time);
+        let pose = self.sample(model);
+        model.apply_pose(&pose);
+        model.update_transforms(engine);
+        model.update_morph_weights(engine);
+    }
+
+    fn sample_clip(&self, model: &Model, playing: Option<&PlayingClip>) -> Pose {
+        let mut pose = self.rest_pose.clone();
+        if let Some(playing) = playing {
+            if let Some(clip) = model.animations.get(playing.clip) { clip.sample(playing.time, &mut pose); }
+        }
+        pose
+    }
+}
+
+/// Moves the given playing clip forward, wrapping looping clips and holding the last frame of other clips.
+fn advance_clip(model: &Model, playing: &mut PlayingClip, delta_time: f32) {
+    let duration = model.animations.get(playing.clip).map(|clip| clip.duration).unwrap_or(0.0);
+    playing.time += delta_time;
+    if duration <= 0.0 { playing.time = 0.0 }
+    else if playing.looping { playing.time = playing.time.rem_euclid(duration) }
+    else { playing.time = playing.time.clamp(0.0, duration) }
+}
+
+/// Samples a list of keyframes at the given time.  Times before the first keyframe or after the last keyframe use the first or last keyframe respectively.
+///
+/// Arguments:
+/// * times: &[f32] - The time of each keyframe.
+/// * values: &[T] - The values of each keyframe, with cubic spline interpolation these are stored as in tangent, value, out tangent.
+/// * interpolation: Interpolation - How to interpolate between keyframes.
+/// * time: f32 - The time to sample at.
+/// * lerp: impl Fn(T, T, f32) -> T - The function used to interpolate linearly between two values.
+///
+/// Returns the sampled value, or none if there are no keyframes or not enough values.
+fn sample_keyframes<T>(times: &[f32], values: &[T], interpolation: Interpolation, time: f32, lerp: impl Fn(T, T, f32) -> T) -> Option<T>
+    where T: Copy + Add<Output = T> + Mul<f32, Output = T>
+{
+    // cubic spline keyframes store an in tangent and out tangent around each value
+    let stride = if interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+    let value = |index: usize| values.get(index * stride + stride / 2).copied();
+
+    // find the keyframes either side of time
+    let last = times.len().checked_sub(1)?;
+    if time <= times[0] { return value(0) }
+    if time >= times[last] { return value(last) }
+    let next = times.partition_point(|keyframe| *keyframe <= time);
+    let previous = next - 1;
+    let delta = times[next] - times[previous];
+    let amount = if delta > 0.0 { (time - times[previous]) / delta } else { 0.0 };
+
+    match interpolation {
+        Interpolation::Step => value(previous),
+        Interpolation::Linear => Some(lerp(value(previous)?, value(next)?, amount)),
+        Interpolation::CubicSpline => {
+            // hermite spline using the out tangent of the previous keyframe and the in tangent of the next keyframe
+            let out_tangent = *values.get(previous * 3 + 2)?;
+            let in_tangent = *values.get(next * 3)?;
+            let t2 = amount * amount;
+            let t3 = t2 * amount;
+            Some(
+                value(previous)? * (2.0 * t3 - 3.0 * t2 + 1.0) +
+                out_tangent * ((t3 - 2.0 * t2 + amount) * delta) +
+                value(next)? * (-2.0 * t3 + 3.0 * t2) +
+                in_tangent * ((t3 - t2) * delta)
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, Rotation3};
+
+    use super::*;
+
+    fn lerp(a: f32, b: f32, t: f32) -> f32 { a + (b - a) * t }
+
+    fn assert_close(actual: f32, expected: f32) { assert!((actual - expected).abs() < 1e-5, "expected {}, got {}", expected, actual); }
+
+    /// A pose with a default transform and the given number of morph target weights for node 0.
+    fn pose(num_targets: usize) -> Pose {
+        Pose { transforms: HashMap::from([(0, Transform::default())]), weights: HashMap::from([(0, vec![0.0; num_targets])]) }
+    }
+
+    /// A clip moving node 0 along X from the first to the second value over 1 second.
+    fn translation_clip(from: f32, to: f32) -> AnimationClip {
+        let values = ChannelValues::Translation(vec![Vector3::new(from, 0.0, 0.0), Vector3::new(to, 0.0, 0.0)]);
+        AnimationClip { name: None, duration: 1.0, channels: vec![Channel { node: 0, interpolation: Interpolation::Linear, times: vec![0.0, 1.0], values }] }
+    }
+
+    /// A model without nodes playing the given clips, the player's rest pose is set by the tests.
+    fn model(animations: Vec<AnimationClip>) -> Model { Model { nodes: Vec::new(), skins: Vec::new(), animations } }
+
+    #[test]
+    fn step_and_linear_keyframes() {
+        let times = [0.0, 1.0, 3.0];
+        let values = [0.0, 10.0, 30.0];
+
+        // time, step, linear
+        let table = [
+            (-1.0, 0.0, 0.0),
+            (0.0, 0.0, 0.0),
+            (0.25, 0.0, 2.5),
+            (1.0, 10.0, 10.0),
+            (2.0, 10.0, 20.0),
+            (2.9, 10.0, 29.0),
+            (3.0, 30.0, 30.0),
+            (5.0, 30.0, 30.0)
+        ];
+        for (time, step, linear) in table {
+            assert_close(sample_keyframes(&times, &values, Interpolation::Step, time, lerp).unwrap(), step);
+            assert_close(sample_keyframes(&times, &values, Interpolation::Linear, time, lerp).unwrap(), linear);
+        }
+    }
+
+    #[test]
+    fn cubic_spline_keyframes() {
+        // FORMAT: in tangent, value, out tangent of each keyframe
+        let times = [0.0, 2.0];
+        let values = [100.0, 0.0, 1.0, 3.0, 4.0, 100.0];
+
+        // the in tangent of the first and out tangent of the last keyframe are never used, so the ends use the values
+        let table = [
+            (-1.0, 0.0),
+            (0.0, 0.0),
+            // t = 0.25: 0 * 0.84375 + 1 * 0.140625 * 2 + 4 * 0.15625 + 3 * -0.046875 * 2
+            (0.5, 0.625),
+            // t = 0.5: 0 * 0.5 + 1 * 0.125 * 2 + 4 * 0.5 + 3 * -0.125 * 2
+            (1.0, 1.5),
+            // t = 0.75: 0 * 0.15625 + 1 * 0.046875 * 2 + 4 * 0.84375 + 3 * -0.140625 * 2
+            (1.5, 2.625),
+            (2.0, 4.0),
+            (3.0, 4.0)
+        ];
+        for (time, expected) in table {
+            assert_close(sample_keyframes(&times, &values, Interpolation::CubicSpline, time, lerp).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn missing_keyframes_sample_nothing() {
+        assert_eq!(sample_keyframes::<f32>(&[], &[], Interpolation::Linear, 0.5, lerp), None);
+        assert_eq!(sample_keyframes(&[0.0, 1.0], &[1.0], Interpolation::Linear, 0.5, lerp), None);
+        assert_eq!(sample_keyframes(&[0.0, 1.0], &[1.0, 2.0, 3.0], Interpolation::CubicSpline, 0.5, lerp), None);
+    }
+
+    #[test]
+    fn rotation_channel_slerps() {
+        let channel = Channel {
+            node: 0,
+            interpolation: Interpolation::Linear,
+            times: vec![0.0, 1.0],
+            values: ChannelValues::Rotation(vec![Quaternion::from_angle_y(Deg(0.0)), Quaternion::from_angle_y(Deg(90.0))])
+        };
+        for (time, angle) in [(0.0, 0.0), (0.5, 45.0), (0.25, 22.5), (2.0, 90.0)] {
+            let mut pose = pose(0);
+            channel.sample(time, &mut pose);
+            let rotation = pose.transforms[&0].rotation;
+            let expected = Quaternion::from_angle_y(Deg(angle));
+            assert_close(rotation.dot(expected).abs(), 1.0);
+            assert_close(rotation.magnitude(), 1.0);
+        }
+    }
+
+    #[test]
+    fn translation_and_scale_channels() {
+        let channels = [
+            Channel { node: 0, interpolation: Interpolation::Linear, times: vec![0.0, 2.0], values: ChannelValues::Translation(vec![Vector3::new(0.0, 2.0, 0.0), Vector3::new(4.0, 2.0, -8.0)]) },
+            Channel { node: 0, interpolation: Interpolation::Step, times: vec![0.0, 1.0], values: ChannelValues::Scale(vec![Vector3::new(1.0, 1.0, 1.0), Vector3::new(3.0, 3.0, 3.0)]) }
+        ];
+        let mut pose = pose(0);
+        channels.iter().for_each(|channel| channel.sample(1.5, &mut pose));
+        assert_eq!(pose.transforms[&0].position, Vector3::new(3.0, 2.0, -6.0));
+        assert_eq!(pose.transforms[&0].scale, Vector3::new(3.0, 3.0, 3.0));
+    }
+
+    #[test]
+    fn weight_channels_sample_each_target() {
+        // FORMAT: [keyframe][target]
+        let linear = Channel { node: 0, interpolation: Interpolation::Linear, times: vec![0.0, 1.0, 2.0], values: ChannelValues::Weights(vec![0.0, 1.0, 1.0, 0.0, 0.5, 0.5]) };
+        for (time, expected) in [(0.5, [0.5, 0.5]), (1.5, [0.75, 0.25]), (3.0, [0.5, 0.5])] {
+            let mut pose = pose(2);
+            linear.sample(time, &mut pose);
+            assert_close(pose.weights[&0][0], expected[0]);
+            assert_close(pose.weights[&0][1], expected[1]);
+        }
+
+        // FORMAT: [keyframe][in tangents of all targets, values of all targets, out tangents of all targets]
+        let cubic = Channel {
+            node: 0,
+            interpolation: Interpolation::CubicSpline,
+            times: vec![0.0, 2.0],
+            values: ChannelValues::Weights(vec![100.0, 100.0, 0.0, 10.0, 1.0, 0.0, 3.0, 0.0, 4.0, 10.0, 100.0, 100.0])
+        };
+        let mut pose = pose(2);
+        cubic.sample(1.0, &mut pose);
+        assert_close(pose.weights[&0][0], 1.5);
+        assert_close(pose.weights[&0][1], 10.0);
+    }
+
+    #[test]
+    fn channels_ignore_nodes_outside_pose() {
+        let channel = Channel { node: 3, interpolation: Interpolation::Linear, times: vec![0.0, 1.0], values: ChannelValues::Weights(vec![1.0, 1.0]) };
+        let mut pose = pose(1);
+        channel.sample(0.5, &mut pose);
+        assert_eq!(pose.weights[&0], vec![0.0]);
+        assert_eq!(pose.weights.len(), 1);
+
+        let mut empty = Pose::default();
+        translation_clip(0.0, 1.0).sample(0.5, &mut empty);
+        assert!(empty.transforms.is_empty());
+    }
+
+    #[test]
+    fn clips_loop_or_hold_at_their_ends() {
+        let model = model(vec![AnimationClip { duration: 2.0, ..translation_clip(0.0, 1.0) }, AnimationClip { duration: 0.0, ..translation_clip(0.0, 1.0) }]);
+
+        // clip, looping, delta time, expected time
+        let table = [
+            (0, true, 0.5, 0.5),
+            (0, true, 2.5, 0.5),
+            (0, true, 4.0, 0.0),
+            (0, true, -0.5, 1.5),
+            (0, false, 2.5, 2.0),
+            (0, false, -0.5, 0.0),
+            (1, true, 1.0, 0.0),
+            (1, false, 1.0, 0.0)
+        ];
+        for (clip, looping, delta_time, expected) in table {
+            let mut playing = PlayingClip { clip, time: 0.0, looping };
+            advance_clip(&model, &mut playing, delta_time);
+            assert_close(playing.time, expected);
+        }
+
+        let mut player = AnimationPlayer::new(&model);
+        assert!(player.is_finished(&model));
+        player.play(0, false);
+        player.advance(&model, 1.0);
+        assert!(!player.is_finished(&model));
+        player.advance(&model, 5.0);
+        assert!(player.is_finished(&model));
+        assert_close(player.current().unwrap().time, 2.0);
+
+        player.play(0, true);
+        player.advance(&model, 5.0);
+        assert!(!player.is_finished(&model));
+        assert_close(player.current().unwrap().time, 1.0);
+    }
+
+    #[test]
+    fn cross_fade_blends_between_clips() {
+        let model = model(vec![translation_clip(0.0, 0.0), translation_clip(10.0, 10.0)]);
+        let mut player = AnimationPlayer::new(&model);
+        player.rest_pose = pose(0);
+        let position = |player: &AnimationPlayer| player.sample(&model).transforms[&0].position.x;
+
+        // cross fading without a playing clip plays it right away
+        player.cross_fade(0, true, 1.0);
+        assert!(!player.is_fading());
+        assert_close(position(&player), 0.0);
+
+        player.cross_fade(1, true, 2.0);
+        assert!(player.is_fading());
+        assert_close(position(&player), 0.0);
+
+        // speed scales the fade too
+        player.speed = 0.5;
+        player.advance(&model, 1.0);
+        assert_close(position(&player), 2.5);
+        player.speed = 1.0;
+        player.advance(&model, 1.0);
+        assert_close(position(&player), 7.5);
+        player.advance(&model, 0.5);
+        assert!(!player.is_fading());
+        assert_close(position(&player), 10.0);
+
+        // stopped players return to the rest pose
+        player.stop();
+        assert_close(position(&player), 0.0);
+        assert!(player.current().is_none());
+    }
+
+    #[test]
+    fn pose_lerp_blends_transforms_and_weights() {
+        let mut from = pose(2);
+        from.weights.insert(0, vec![0.0, 1.0]);
+        from.transforms.insert(1, Transform::default());
+        let mut to = pose(2);
+        to.weights.insert(0, vec![1.0, 0.0]);
+        to.transforms.get_mut(&0).unwrap().position = Vector3::new(4.0, 0.0, 0.0);
+        to.transforms.insert(2, Transform::default());
+
+        let blended = from.lerp(&to, 0.25);
+        assert_eq!(blended.transforms[&0].position, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(blended.weights[&0], vec![0.25, 0.75]);
+
+        // nodes only in this pose are kept, nodes only in the other pose are dropped
+        assert!(blended.transforms.contains_key(&1));
+        assert!(!blended.transforms.contains_key(&2));
+    }
+}
@@ -1,78 +1,262 @@
+use std::path::Path;
+
+use anyhow::*;
+use base64::prelude::*;
+use cgmath::{Matrix4, SquareMatrix};
 use gltf::Gltf;
+use percent_encoding::percent_decode_str;
 
-use crate::{create_pipeline, lights::lights::LightUniform, primitives::{cameras::Camera, mesh::Mesh, textures::Texture, transforms::TransformRaw, vertices::Vertex}, render::{pipelines::Pipeline, render_engine::RenderEngine}, ui::style::Color};
+use crate::{create_pipeline, log, lights::{clusters::LightClusters, lights::LightUniform, shadows::ShadowMaps}, math::transforms::Transform, primitives::{cameras::Camera, mesh::Mesh, textures::Texture, transforms::TransformRaw, vertices::{ModelVertex, SkinnedVertex}}, render::{pipelines::{engine_shader_path, PipelineBuilder}, render_engine::RenderEngine}, ui::style::Color, utils::files::{AssetReader, DirectoryReader}};
 use crate::models::{Model, Node};
 
-use super::material::{Material, MaterialBuilder};
+use super::{animation::{AnimationClip, Channel, ChannelValues, Interpolation}, material::{Material, MaterialBuilder, MaterialTexture}, morph::{MorphDelta, MorphTargets}, skin::{Skin, MAX_JOINTS}};
 
 #[include_wgsl_oil::include_wgsl_oil("../../shaders/gltf.wgsl")]
 mod gltf_shader {}
 
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/gltf_skinned.wgsl")]
+mod gltf_skinned_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/gltf_uniform_lights.wgsl")]
+mod gltf_uniform_lights_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/gltf_skinned_uniform_lights.wgsl")]
+mod gltf_skinned_uniform_lights_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/gltf_morph.wgsl")]
+mod gltf_morph_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/gltf_skinned_morph.wgsl")]
+mod gltf_skinned_morph_shader {}
+
 pub struct GLTFLoader;
 
+/// The primitives of a mesh with their materials, and the morph target deltas of each primitive.
+type UnpackedMesh = (Vec<(Mesh, Material)>, Vec<Vec<MorphDelta>>);
+
 impl GLTFLoader {
-    pub fn unpack_static_gltf(engine: &mut RenderEngine, gltf: Gltf) -> Model {
-        let buffers = Self::unpack_buffers(&gltf);
+    /// Loads a glTF model from the given path.  Both binary `.glb` and `.gltf` files are supported, any files referenced by the model are loaded relative to the models directory.  Skins with more than `MAX_JOINTS` joints are not supported, the meshes using them are loaded without skinning.
+    /// 
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine to load the model with.
+    /// * path: impl AsRef<Path> - The path to the `.glb` or `.gltf` file.
+    /// 
+    /// Returns a result:
+    /// * Ok - Model - The loaded model.
+    /// * Error - A description of why the model could not be loaded.
+    pub fn load_path(engine: &mut RenderEngine, path: impl AsRef<Path>) -> Result<Model> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("Could not read glTF file {:?}", path))?;
+        let reader = DirectoryReader { directory: path.parent().map(Path::to_path_buf).unwrap_or_default() };
+        Self::load_slice(engine, &bytes, &reader).with_context(|| format!("Could not load glTF file {:?}", path))
+    }
+
+    /// Loads a glTF model from the given bytes of a `.glb` or `.gltf` file, using the given reader to load any external files referenced by the model.
+    /// 
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine to load the model with.
+    /// * bytes: &[u8] - The bytes of the `.glb` or `.gltf` file.
+    /// * reader: &impl AssetReader - The reader used to load external buffers and images.
+    /// 
+    /// Returns a result:
+    /// * Ok - Model - The loaded model.
+    /// * Error - A description of why the model could not be loaded.
+    pub fn load_slice(engine: &mut RenderEngine, bytes: &[u8], reader: &impl AssetReader) -> Result<Model> {
+        let gltf = Gltf::from_slice(bytes).context("Could not parse glTF")?;
+        Self::unpack_gltf(engine, gltf, reader)
+    }
+
+    /// Unpacks a self-contained glTF model, any external files referenced by the model will cause an error.  Use `unpack_gltf` or `load_path` for models with external files.
+    /// 
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine to load the model with.
+    /// * gltf: Gltf - The glTF file to unpack.
+    /// 
+    /// Returns a result:
+    /// * Ok - Model - The loaded model.
+    /// * Error - A description of why the model could not be loaded.
+    pub fn unpack_static_gltf(engine: &mut RenderEngine, gltf: Gltf) -> Result<Model> {
+        Self::unpack_gltf(engine, gltf, &|uri: &str| -> Result<Vec<u8>> { bail!("No asset reader given to load external file {:?}", uri) })
+    }
+
+    /// Unpacks a glTF model using the given reader to load any external files referenced by the model.
+    /// 
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine to load the model with.
+    /// * gltf: Gltf - The glTF file to unpack.
+    /// * reader: &impl AssetReader - The reader used to load external buffers and images.
+    /// 
+    /// Returns a result:
+    /// * Ok - Model - The loaded model.
+    /// * Error - A description of why the model could not be loaded.
+    pub fn unpack_gltf(engine: &mut RenderEngine, gltf: Gltf, reader: &impl AssetReader) -> Result<Model> {
+        let buffers = Self::unpack_buffers(&gltf, reader)?;
 
         let mut root_nodes: Vec<Node> = Vec::new();
 
-        // make sure render pipeline exists
+        // make sure render pipeline exists, devices without clustered lighting read lights from a uniform buffer instead, morph targets are not supported on them
+        let light_layout = LightUniform::bind_layout(engine);
+        let (static_shader, skinned_shader, static_path, skinned_path) = if LightClusters::is_supported(engine) {
+            (gltf_shader::SOURCE, gltf_skinned_shader::SOURCE, "shaders/gltf.wgsl", "shaders/gltf_skinned.wgsl")
+        } else {
+            (gltf_uniform_lights_shader::SOURCE, gltf_skinned_uniform_lights_shader::SOURCE, "shaders/gltf_uniform_lights.wgsl", "shaders/gltf_skinned_uniform_lights.wgsl")
+        };
         create_pipeline! {
             NAME => "forte.gltf",
             ENGINE => engine,
-            SHADER => gltf_shader::SOURCE,
-            BUFFER_LAYOUTS => [Vertex::desc(), TransformRaw::desc()],
-            BIND_GROUPS => [Camera::BIND_LAYOUT, Material::BIND_LAYOUT, LightUniform::BIND_LAYOUT],
-            HAS_DEPTH => true
+            SHADER => static_shader,
+            BUFFER_LAYOUTS => [ModelVertex::desc(), TransformRaw::desc()],
+            BIND_GROUPS => [Camera::BIND_LAYOUT, Material::BIND_LAYOUT, light_layout, Node::BIND_LAYOUT],
+            HAS_DEPTH => true,
+            OPTIONS => [with_source_path(engine_shader_path(static_path))]
+        }
+        create_shadow_pipeline(engine, "forte.gltf.shadow", static_shader, static_path, ModelVertex::desc(), Node::BIND_LAYOUT);
+        if gltf.skins().len() > 0 {
+            create_pipeline! {
+                NAME => "forte.gltf.skinned",
+                ENGINE => engine,
+                SHADER => skinned_shader,
+                BUFFER_LAYOUTS => [SkinnedVertex::desc(), TransformRaw::desc()],
+                BIND_GROUPS => [Camera::BIND_LAYOUT, Material::BIND_LAYOUT, light_layout, Node::SKINNED_BIND_LAYOUT],
+                HAS_DEPTH => true,
+                OPTIONS => [with_source_path(engine_shader_path(skinned_path))]
+            }
+            create_shadow_pipeline(engine, "forte.gltf.skinned.shadow", skinned_shader, skinned_path, SkinnedVertex::desc(), Node::SKINNED_BIND_LAYOUT);
+        }
+        if MorphTargets::is_supported(engine) && gltf.meshes().any(|mesh| mesh.primitives().any(|primitive| primitive.morph_targets().len() > 0)) {
+            create_pipeline! {
+                NAME => "forte.gltf.morph",
+                ENGINE => engine,
+                SHADER => gltf_morph_shader::SOURCE,
+                BUFFER_LAYOUTS => [ModelVertex::desc(), TransformRaw::desc()],
+                BIND_GROUPS => [Camera::BIND_LAYOUT, Material::BIND_LAYOUT, light_layout, Node::MORPH_BIND_LAYOUT],
+                HAS_DEPTH => true,
+                OPTIONS => [with_source_path(engine_shader_path("shaders/gltf_morph.wgsl"))]
+            }
+            create_shadow_pipeline(engine, "forte.gltf.morph.shadow", gltf_morph_shader::SOURCE, "shaders/gltf_morph.wgsl", ModelVertex::desc(), Node::MORPH_BIND_LAYOUT);
+            if gltf.skins().len() > 0 {
+                create_pipeline! {
+                    NAME => "forte.gltf.skinned.morph",
+                    ENGINE => engine,
+                    SHADER => gltf_skinned_morph_shader::SOURCE,
+                    BUFFER_LAYOUTS => [SkinnedVertex::desc(), TransformRaw::desc()],
+                    BIND_GROUPS => [Camera::BIND_LAYOUT, Material::BIND_LAYOUT, light_layout, Node::SKINNED_MORPH_BIND_LAYOUT],
+                    HAS_DEPTH => true,
+                    OPTIONS => [with_source_path(engine_shader_path("shaders/gltf_skinned_morph.wgsl"))]
+                }
+                create_shadow_pipeline(engine, "forte.gltf.skinned.morph.shadow", gltf_skinned_morph_shader::SOURCE, "shaders/gltf_skinned_morph.wgsl", SkinnedVertex::desc(), Node::SKINNED_MORPH_BIND_LAYOUT);
+            }
         }
 
         // load nodes
         for scene in gltf.scenes() {
             for node in scene.nodes() {
                 // load mesh
-                let node = Self::unpack_node(engine, &buffers, &node);
+                let node = Self::unpack_node(engine, &buffers, reader, &node)?;
                 root_nodes.push(node);
             }
         }
 
-        return Model { nodes: root_nodes };
+        // load skins and animations
+        let skins = gltf.skins().map(|skin| Self::unpack_skin(&buffers, &skin).with_context(|| format!("Could not load skin {}", skin.index()))).collect::<Result<Vec<Skin>>>()?;
+        let animations = gltf.animations().map(|animation| Self::unpack_animation(&buffers, &animation).with_context(|| format!("Could not load animation {}", animation.index()))).collect::<Result<Vec<AnimationClip>>>()?;
+
+        // calculate world transforms of all nodes
+        let mut model = Model { nodes: root_nodes, skins, animations };
+        model.update_transforms(engine);
+        Ok(model)
     }
 
-    fn unpack_node<'a>(engine: &RenderEngine, buffers: &Vec<Vec<u8>>, node: &gltf::Node<'a>) -> Node {
-        Node { 
-            meshes: if node.mesh().is_some() { Some(Self::unpack_mesh(engine, buffers, &node.mesh().unwrap())) } else { None }, 
-            children: node.children().into_iter().map(|child| Self::unpack_node(engine, buffers, &child)).collect()
-        }
+    fn unpack_node(engine: &RenderEngine, buffers: &[Vec<u8>], reader: &impl AssetReader, node: &gltf::Node) -> Result<Node> {
+        let (position, rotation, scale) = node.transform().decomposed();
+        // meshes whose skin has too many joints are drawn without skinning
+        let skin = node.skin().filter(|skin| skin.joints().len() <= MAX_JOINTS).map(|skin| skin.index());
+        let (meshes, morph_targets) = match node.mesh() {
+            Some(mesh) => {
+                let (meshes, deltas) = Self::unpack_mesh(engine, buffers, reader, &mesh, skin.is_some()).with_context(|| format!("Could not load mesh {} of node {}", mesh.index(), node.index()))?;
+                (Some(meshes), Self::unpack_morph_targets(engine, node, &mesh, &deltas))
+            },
+            None => (None, None)
+        };
+        let children = node.children().map(|child| Self::unpack_node(engine, buffers, reader, &child)).collect::<Result<Vec<Node>>>()?;
+        Ok(Node::new(
+            engine,
+            node.index(),
+            Transform { position: position.into(), rotation: rotation.into(), scale: scale.into() },
+            meshes,
+            children,
+            skin,
+            morph_targets
+        ))
     }
 
-    fn unpack_mesh<'mesh>(engine: &RenderEngine, buffers: &Vec<Vec<u8>>, mesh: &gltf::Mesh<'mesh>) -> Vec<(Mesh, Material)> {
-        mesh.primitives().into_iter().map(|primitive| {
+    /// Unpacks the primitives of a mesh, returning each primitive with its material and its morph target deltas formatted as `[vertex][target]`.
+    fn unpack_mesh(engine: &RenderEngine, buffers: &[Vec<u8>], asset_reader: &impl AssetReader, mesh: &gltf::Mesh, skinned: bool) -> Result<UnpackedMesh> {
+        let primitives = mesh.primitives().map(|primitive| {
             // read everything from the primitive
-            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
-            let mut positions = reader.read_positions().expect("Expecting positions in gltf.");
-            let mut normals = reader.read_normals().expect("Expecting normals in gltf.");
-            let mut tex_coords = reader.read_tex_coords(0).expect("Expecting TexCoords(0) in gltf.").into_f32();
-            let indices: Vec<u16> = reader.read_indices().expect("Expecting indices in gltf.").into_u32().map(|a| a as u16).collect();
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+            let mut positions = reader.read_positions().context("Primitive has no positions")?;
+            let mut normals = reader.read_normals().context("Primitive has no normals")?;
+            let mut tex_coords = reader.read_tex_coords(0).context("Primitive has no TexCoords(0)")?.into_f32();
+            let indices: Vec<u32> = reader.read_indices().context("Primitive has no indices")?.into_u32().collect();
 
             // convert positions, normals and tex coords into a vertices array
-            let mut vertices: Vec<Vertex> = Vec::with_capacity(positions.len());
-            while let Some(position) = positions.next() {
-                let normal = normals.next().unwrap();
-                let tex_coord = tex_coords.next().unwrap();
-                vertices.push(Vertex { position: position.into(), tex_coords: tex_coord.into(), normal: normal.into() })
+            let mut vertices: Vec<ModelVertex> = Vec::with_capacity(positions.len());
+            for position in positions.by_ref() {
+                let normal = normals.next().context("Primitive has less normals than positions")?;
+                let tex_coord = tex_coords.next().context("Primitive has less TexCoords(0) than positions")?;
+                vertices.push(ModelVertex { position, tex_coords: tex_coord, normal, tangent: [0.0; 4] })
+            }
+
+            // use the tangents of the primitive if given, otherwise generate them
+            match reader.read_tangents() {
+                Some(tangents) => {
+                    if tangents.len() < vertices.len() { bail!("Primitive has less tangents than positions"); }
+                    vertices.iter_mut().zip(tangents).for_each(|(vertex, tangent)| vertex.tangent = tangent);
+                },
+                None => ModelVertex::generate_tangents(&mut vertices, &indices)
+            }
+
+            // skinned meshes also need the joints and weights of each vertex
+            let mesh = if skinned {
+                let mut joints = reader.read_joints(0).context("Skinned primitive has no Joints(0)")?.into_u16();
+                let mut weights = reader.read_weights(0).context("Skinned primitive has no Weights(0)")?.into_f32();
+                let vertices = vertices.iter().map(|vertex| {
+                    let joints = joints.next().context("Primitive has less Joints(0) than positions")?;
+                    let weights = weights.next().context("Primitive has less Weights(0) than positions")?;
+                    Ok(SkinnedVertex { position: vertex.position, tex_coords: vertex.tex_coords, normal: vertex.normal, tangent: vertex.tangent, joints: joints.map(u32::from), weights })
+                }).collect::<Result<Vec<SkinnedVertex>>>()?;
+                Mesh::from_vertices_u32(&engine.device, &vertices, &indices)
+            } else { Mesh::from_vertices_u32(&engine.device, &vertices, &indices) };
+
+            // read the position and normal deltas of each morph target
+            let targets = reader.read_morph_targets().map(|(positions, normals, _)| {
+                let positions: Vec<[f32; 3]> = positions.map(Iterator::collect).unwrap_or_default();
+                let normals: Vec<[f32; 3]> = normals.map(Iterator::collect).unwrap_or_default();
+                (positions, normals)
+            }).collect::<Vec<_>>();
+            let mut deltas = Vec::with_capacity(vertices.len() * targets.len());
+            for vertex in 0 .. vertices.len() {
+                for (positions, normals) in targets.iter() {
+                    let position = positions.get(vertex).copied().unwrap_or_default();
+                    let normal = normals.get(vertex).copied().unwrap_or_default();
+                    deltas.push(MorphDelta { position: [position[0], position[1], position[2], 0.0], normal: [normal[0], normal[1], normal[2], 0.0] });
+                }
             }
 
-            // load basic color texture
+            // load material textures, color textures are srgb and data textures are linear
             let material = primitive.material();
             let pbr = material.pbr_metallic_roughness();
+            let srgb = wgpu::TextureFormat::Rgba8UnormSrgb;
+            let linear = wgpu::TextureFormat::Rgba8Unorm;
 
             // create material
             let material = MaterialBuilder {
-                albedo_texture: gltf_texture_to_texture(engine, pbr.base_color_texture(), buffers),
-                roughness_texture: gltf_texture_to_texture(engine, pbr.metallic_roughness_texture(), buffers),
-                emissive_texture: gltf_texture_to_texture(engine, material.emissive_texture(), buffers),
-                normal_texture: gltf_texture_to_texture(engine, material.normal_texture(), buffers),
-                occlusion_texture: gltf_texture_to_texture(engine, material.normal_texture(), buffers),
+                albedo_texture: gltf_texture_to_texture(engine, pbr.base_color_texture(), srgb, buffers, asset_reader)?.map(MaterialTexture::from),
+                roughness_texture: gltf_texture_to_texture(engine, pbr.metallic_roughness_texture(), linear, buffers, asset_reader)?.map(MaterialTexture::from),
+                emissive_texture: gltf_texture_to_texture(engine, material.emissive_texture(), srgb, buffers, asset_reader)?.map(MaterialTexture::from),
+                normal_texture: gltf_texture_to_texture(engine, material.normal_texture(), linear, buffers, asset_reader)?.map(MaterialTexture::from),
+                occlusion_texture: gltf_texture_to_texture(engine, material.occlusion_texture(), linear, buffers, asset_reader)?.map(MaterialTexture::from),
                 albedo_color: color_from_4f32(pbr.base_color_factor()),
                 emissive_color: color_from_3f32(material.emissive_factor()),
                 metallic_factor: pbr.metallic_factor(),
@@ -82,63 +266,141 @@ impl GLTFLoader {
                     gltf::material::AlphaMode::Mask => 2.0,
                     gltf::material::AlphaMode::Blend => 3.0,
                 },
-                alpha_cutoff: if material.alpha_cutoff().is_some() { material.alpha_cutoff().unwrap() } else { 0.0 },
+                alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
             }.build(engine);
 
-            (Mesh::from_raw(&engine.device, &vertices, &indices), material)
-        }).collect()
+            Ok(((mesh, material), deltas))
+        }).collect::<Result<Vec<_>>>()?;
+        Ok(primitives.into_iter().unzip())
     }
 
-    fn unpack_buffers(gltf: &Gltf) -> Vec<Vec<u8>> {
+    /// Creates the morph targets of a node from the deltas of its mesh, if it has any and they are supported.
+    fn unpack_morph_targets(engine: &RenderEngine, node: &gltf::Node, mesh: &gltf::Mesh, deltas: &[Vec<MorphDelta>]) -> Option<MorphTargets> {
+        let num_targets = mesh.primitives().map(|primitive| primitive.morph_targets().len()).max().unwrap_or(0);
+        if num_targets == 0 { return None }
+        if !MorphTargets::is_supported(engine) {
+            log!("Morph targets of mesh {} are not supported by this device and will be ignored", mesh.index());
+            return None
+        }
+
+        // node weights override the default weights of the mesh
+        let weights = node.weights().or(mesh.weights()).map(<[f32]>::to_vec).unwrap_or_default();
+        Some(MorphTargets::new(engine, num_targets, weights, deltas))
+    }
+
+    fn unpack_skin(buffers: &[Vec<u8>], skin: &gltf::Skin) -> Result<Skin> {
+        let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+        if joints.len() > MAX_JOINTS { log!("Skin {} has {} joints, but at most {} are supported, meshes using it are loaded without skinning", skin.index(), joints.len(), MAX_JOINTS); }
+
+        // inverse bind matrices default to identity matrices when not given
+        let reader = skin.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+        let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
+            Some(matrices) => matrices.map(Matrix4::from).collect(),
+            None => vec![Matrix4::identity(); joints.len()]
+        };
+
+        Ok(Skin { joints, inverse_bind_matrices })
+    }
+
+    fn unpack_animation(buffers: &[Vec<u8>], animation: &gltf::Animation) -> Result<AnimationClip> {
+        let mut channels = Vec::new();
+        for channel in animation.channels() {
+            let reader = channel.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+            let times: Vec<f32> = reader.read_inputs().context("Animation channel has no input times")?.collect();
+            let values = match reader.read_outputs().context("Animation channel has no output values")? {
+                gltf::animation::util::ReadOutputs::Translations(values) => ChannelValues::Translation(values.map(Into::into).collect()),
+                gltf::animation::util::ReadOutputs::Rotations(values) => ChannelValues::Rotation(values.into_f32().map(Into::into).collect()),
+                gltf::animation::util::ReadOutputs::Scales(values) => ChannelValues::Scale(values.map(Into::into).collect()),
+                gltf::animation::util::ReadOutputs::MorphTargetWeights(values) => ChannelValues::Weights(values.into_f32().collect())
+            };
+            let interpolation = match channel.sampler().interpolation() {
+                gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                gltf::animation::Interpolation::Step => Interpolation::Step,
+                gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline
+            };
+            channels.push(Channel { node: channel.target().node().index(), interpolation, times, values });
+        }
+
+        // clips last until their final keyframe
+        let duration = channels.iter().filter_map(|channel| channel.times.last().copied()).fold(0.0, f32::max);
+        Ok(AnimationClip { name: animation.name().map(str::to_string), duration, channels })
+    }
+
+    fn unpack_buffers(gltf: &Gltf, reader: &impl AssetReader) -> Result<Vec<Vec<u8>>> {
         let mut buffer_data = Vec::new();
         for buffer in gltf.buffers() {
-            match buffer.source() {
-                gltf::buffer::Source::Uri(_uri) => todo!(),
-                /*{
-                    let uri = percent_encoding::percent_decode_str(uri)
-                        .decode_utf8()
-                        .unwrap();
-                    let uri = uri.as_ref();
-                    let buffer_bytes = match DataUri::parse(uri) {
-                        Ok(data_uri) if VALID_MIME_TYPES.contains(&data_uri.mime_type) => {
-                            data_uri.decode()?
-                        }
-                        Ok(_) => return Err(GltfError::BufferFormatUnsupported),
-                        Err(()) => {
-                            // TODO: Remove this and add dep
-                            // let buffer_path = load_context.path().parent().unwrap().join(uri);
-                            // load_context.read_asset_bytes(buffer_path).await?
-                        }
-                    };
-                    buffer_data.push(buffer_bytes);
-                }*/
-                gltf::buffer::Source::Bin => {
-                    if let Some(blob) = gltf.blob.as_deref() {
-                        buffer_data.push(blob.into());
-                    }
-                }
-            }
+            let mut data = match buffer.source() {
+                gltf::buffer::Source::Uri(uri) => read_uri(uri, reader).with_context(|| format!("Could not load buffer {}", buffer.index()))?,
+                gltf::buffer::Source::Bin => gltf.blob.as_deref().with_context(|| format!("Buffer {} references a binary chunk that does not exist", buffer.index()))?.into()
+            };
+
+            // make sure the buffer is the length it says it is
+            if data.len() < buffer.length() { bail!("Buffer {} is {} bytes long, but should be {} bytes long", buffer.index(), data.len(), buffer.length()); }
+            data.truncate(buffer.length());
+            buffer_data.push(data);
         }
-        return buffer_data;
+        Ok(buffer_data)
     }
 }
 
-fn gltf_texture_to_texture<'a, T: AsRef<gltf::texture::Texture<'a>>>(engine: &RenderEngine, gltf_texture: Option<T>, buffers: &Vec<Vec<u8>>) -> Option<Texture> {
-    if gltf_texture.is_some() {
-        let gltf_texture = gltf_texture.unwrap();
-        let gltf_texture = gltf_texture.as_ref();
-        match gltf_texture.source().source() {
-            gltf::image::Source::View { view, .. } => {
-                let start = view.offset();
-                let end = view.offset() + view.length();
-                let buffer = &buffers[view.buffer().index()][start..end];
-                let texture = Texture::from_bytes(&engine.device, &engine.queue, buffer, "")
-                    .expect("Could not load gltf texture!");
-                Some(texture)
-            },
-            gltf::image::Source::Uri { .. } => todo!(),
-        }
-    } else { None }
+/// Reads the bytes of the given uri, `data:` uris are decoded directly and all other uris are percent decoded and read with the given reader.
+fn read_uri(uri: &str, reader: &impl AssetReader) -> Result<Vec<u8>> {
+    // decode data uris, they are formatted as `data:[<mime type>][;base64],<data>`
+    if let Some(data_uri) = uri.strip_prefix("data:") {
+        let (header, data) = data_uri.split_once(',').context("Data uri is missing a ','")?;
+        return if header.ends_with(";base64") {
+            BASE64_STANDARD.decode(data).context("Could not decode base64 data uri")
+        } else {
+            Ok(percent_decode_str(data).collect())
+        };
+    }
+
+    // otherwise, read relative to the asset
+    let path = percent_decode_str(uri).decode_utf8().with_context(|| format!("Uri {:?} is not valid utf8", uri))?;
+    reader.read(&path)
+}
+
+fn gltf_texture_to_texture<'a, T: AsRef<gltf::texture::Texture<'a>>>(engine: &RenderEngine, gltf_texture: Option<T>, format: wgpu::TextureFormat, buffers: &[Vec<u8>], reader: &impl AssetReader) -> Result<Option<Texture>> {
+    let gltf_texture = match gltf_texture { Some(texture) => texture, None => return Ok(None) };
+    let image = gltf_texture.as_ref().source();
+
+    // load the bytes of the image
+    let bytes = match image.source() {
+        gltf::image::Source::View { view, .. } => {
+            let start = view.offset();
+            let end = view.offset() + view.length();
+            buffers.get(view.buffer().index()).and_then(|buffer| buffer.get(start..end))
+                .with_context(|| format!("Image {} references data outside of its buffer", image.index()))?
+                .to_vec()
+        },
+        gltf::image::Source::Uri { uri, .. } => read_uri(uri, reader).with_context(|| format!("Could not load image {}", image.index()))?
+    };
+
+    let texture = Texture::from_bytes_with_format(&engine.device, &engine.queue, &bytes, "", format)
+        .with_context(|| format!("Could not decode image {}", image.index()))?;
+    Ok(Some(texture))
+}
+
+/// Makes sure the depth only pipeline used to draw a node variant into shadow maps exists.  Shadow maps only bind the camera and node bind groups, so the others are empty.
+fn create_shadow_pipeline(engine: &mut RenderEngine, name: &str, shader: &str, path: &str, vertex: wgpu::VertexBufferLayout, node_layout: wgpu::BindGroupLayoutDescriptor) {
+    engine.verify_pipeline_exists(name, |engine| {
+        PipelineBuilder::new(name, shader)
+            .with_buffers(&[vertex.clone(), TransformRaw::desc()])
+            .with_layouts(&[
+                &engine.device.create_bind_group_layout(&Camera::BIND_LAYOUT),
+                &engine.device.create_bind_group_layout(&ShadowMaps::EMPTY_BIND_LAYOUT),
+                &engine.device.create_bind_group_layout(&ShadowMaps::EMPTY_BIND_LAYOUT),
+                &engine.device.create_bind_group_layout(&node_layout)
+            ])
+            .with_fragment_entry(None)
+            .with_targets(Vec::new())
+            .with_depth(true)
+            .with_depth_compare(ShadowMaps::DEPTH_CONVENTION.compare())
+            .with_depth_bias(ShadowMaps::DEPTH_BIAS)
+            .single_sampled()
+            .with_source_path(engine_shader_path(path))
+            .build(engine)
+    });
 }
 
 fn color_from_4f32(input: [f32; 4]) -> Color {
@@ -148,3 +410,76 @@ fn color_from_4f32(input: [f32; 4]) -> Color {
 fn color_from_3f32(input: [f32; 3]) -> Color {
     Color { red: input[0], green: input[1], blue: input[2], alpha: 1.0 }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    /// A reader that fails on every uri, for uris that must never be read from disk.
+    fn no_reader(uri: &str) -> Result<Vec<u8>> { bail!("Tried to read {:?}", uri) }
+
+    fn engine() -> RenderEngine { pollster::block_on(RenderEngine::new_headless(64, 64, wgpu::TextureFormat::Rgba8UnormSrgb)) }
+
+    /// A glTF file without meshes with one buffer of the given length loaded from the given uri.
+    fn buffer_gltf(uri: &str, length: usize) -> Gltf {
+        let json = format!(r#"{{"asset": {{"version": "2.0"}}, "buffers": [{{"byteLength": {}, "uri": "{}"}}]}}"#, length, uri);
+        Gltf::from_slice(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn read_uri_decodes_data_uris() {
+        // uri, expected bytes
+        let table: [(&str, &[u8]); 6] = [
+            ("data:application/octet-stream;base64,AQID", &[1, 2, 3]),
+            ("data:application/gltf-buffer;base64,AAEC/w==", &[0, 1, 2, 255]),
+            ("data:;base64,", &[]),
+            ("data:text/plain,hello%20world", b"hello world"),
+            ("data:,%00%01%FF", &[0, 1, 255]),
+            ("data:text/plain;charset=utf-8,a,b", b"a,b")
+        ];
+        for (uri, expected) in table {
+            assert_eq!(read_uri(uri, &no_reader).unwrap(), expected, "{}", uri);
+        }
+    }
+
+    #[test]
+    fn read_uri_rejects_bad_data_uris() {
+        for uri in ["data:;base64,not base64!", "data:;base64,AQI", "data:application/octet-stream;base64"] {
+            assert!(read_uri(uri, &no_reader).is_err(), "{}", uri);
+        }
+    }
+
+    #[test]
+    fn read_uri_reads_other_uris_with_reader() {
+        let read = RefCell::new(Vec::new());
+        let reader = |uri: &str| -> Result<Vec<u8>> { read.borrow_mut().push(uri.to_string()); Ok(vec![7]) };
+        assert_eq!(read_uri("textures/brick%20wall.png", &reader).unwrap(), vec![7]);
+        assert_eq!(read_uri("model.bin", &reader).unwrap(), vec![7]);
+        assert_eq!(*read.borrow(), vec!["textures/brick wall.png", "model.bin"]);
+
+        // percent encoded paths must be utf8
+        assert!(read_uri("bad%FF.bin", &reader).is_err());
+        assert_eq!(read.borrow().len(), 2);
+    }
+
+    #[test]
+    fn unpack_static_gltf_rejects_external_uris() {
+        let mut engine = engine();
+        let error = GLTFLoader::unpack_static_gltf(&mut engine, buffer_gltf("external.bin", 4)).unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.contains("Could not load buffer 0") && message.contains("external.bin"), "{}", message);
+    }
+
+    #[test]
+    fn unpack_static_gltf_reads_data_uris() {
+        let mut engine = engine();
+        let model = GLTFLoader::unpack_static_gltf(&mut engine, buffer_gltf("data:application/octet-stream;base64,AQIDBA==", 4)).unwrap();
+        assert!(model.nodes.is_empty());
+
+        // the buffer is shorter than its length
+        let error = GLTFLoader::unpack_static_gltf(&mut engine, buffer_gltf("data:application/octet-stream;base64,AQID", 4)).unwrap_err();
+        assert!(format!("{:#}", error).contains("should be 4 bytes long"), "{:#}", error);
+    }
+}
@@ -1,15 +1,56 @@
 use wgpu::util::DeviceExt;
 
-use crate::{primitives::textures::Texture, render::render_engine::RenderEngine, ui::style::Color};
+use crate::{primitives::textures::Texture, render::render_engine::RenderEngine, ui::style::Color, utils::resources::Handle};
 
+/// A texture used by a material, either owned by the material or kept in the render engine's texture cache, like the texture of a `RenderTarget`.
+#[derive(Debug)]
+pub enum MaterialTexture {
+    Owned(Box<Texture>),
+    Cached(Handle<Texture>)
+}
+
+impl MaterialTexture {
+    /// Gets the texture, looking it up in the given render engine's texture cache if necessary.
+    pub fn texture<'a>(&'a self, engine: &'a RenderEngine) -> &'a Texture {
+        match self {
+            MaterialTexture::Owned(texture) => texture,
+            MaterialTexture::Cached(handle) => engine.texture(handle)
+        }
+    }
+}
+
+impl From<Texture> for MaterialTexture {
+    fn from(texture: Texture) -> Self { MaterialTexture::Owned(Box::new(texture)) }
+}
+
+impl From<Handle<Texture>> for MaterialTexture {
+    fn from(handle: Handle<Texture>) -> Self { MaterialTexture::Cached(handle) }
+}
+
+/// The index of the normal map in a material's textures.
+const NORMAL_TEXTURE: usize = 3;
 
+/// Describes a physically based material using the glTF metallic-roughness model.
+///
+/// Arguments:
+/// * albedo_texture: Option<MaterialTexture> - The base color texture, multiplied by `albedo_color`.
+/// * roughness_texture: Option<MaterialTexture> - The metallic-roughness texture, roughness is read from the green channel and metallic from the blue channel.  Should be linear (`Rgba8Unorm`).
+/// * emissive_texture: Option<MaterialTexture> - The emissive texture, multiplied by `emissive_color`.
+/// * normal_texture: Option<MaterialTexture> - The tangent space normal map.  Should be linear (`Rgba8Unorm`).
+/// * occlusion_texture: Option<MaterialTexture> - The ambient occlusion texture, read from the red channel.  Should be linear (`Rgba8Unorm`).
+/// * albedo_color: Color - The base color.
+/// * emissive_color: Color - The emitted color.
+/// * metallic_factor: f32 - How metallic the material is, from 0 to 1.
+/// * roughness_factor: f32 - How rough the material is, from 0 to 1.
+/// * alpha_mode: f32 - 1 for opaque, 2 to discard pixels with an alpha below `alpha_cutoff`, and 3 to blend.
+/// * alpha_cutoff: f32 - The cutoff used by alpha mode 2.
 #[derive(Debug, Default)]
 pub struct MaterialBuilder {
-    pub albedo_texture: Option<Texture>,
-    pub roughness_texture: Option<Texture>,
-    pub emissive_texture: Option<Texture>,
-    pub normal_texture: Option<Texture>,
-    pub occlusion_texture: Option<Texture>,
+    pub albedo_texture: Option<MaterialTexture>,
+    pub roughness_texture: Option<MaterialTexture>,
+    pub emissive_texture: Option<MaterialTexture>,
+    pub normal_texture: Option<MaterialTexture>,
+    pub occlusion_texture: Option<MaterialTexture>,
 
     pub albedo_color: Color,
     pub emissive_color: Color,
@@ -48,106 +89,47 @@ impl MaterialBuilder {
             }
         );
 
-        // unpack textures
-        let empty_texture = create_empty_texture(engine);
-        let diffuse_texture = if self.albedo_texture.is_some() { self.albedo_texture.as_ref().unwrap() } else { &empty_texture };
-        let roughness_texture = if self.roughness_texture.is_some() { self.roughness_texture.as_ref().unwrap() } else { &empty_texture };
-        let emissive_texture = if self.albedo_texture.is_some() { self.emissive_texture.as_ref().unwrap() } else { &empty_texture };
-        let normal_texture = if self.albedo_texture.is_some() { self.normal_texture.as_ref().unwrap() } else { &empty_texture };
-        let occlusion_texture = if self.albedo_texture.is_some() { self.occlusion_texture.as_ref().unwrap() } else { &empty_texture };
+        // create shadow flags buffer, materials receive shadows until told otherwise
+        let shadow_buffer = engine.device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("material_shadow_buffer"),
+                contents: bytemuck::cast_slice(&[1.0f32, 0.0, 0.0, 0.0]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        );
 
         // create material with bind group
+        let textures = [self.albedo_texture, self.roughness_texture, self.emissive_texture, self.normal_texture, self.occlusion_texture];
+        let bind_group = Material::create_bind_group(engine, &textures, [&albedo_buffer, &emissive_buffer, &metadata_buffer, &shadow_buffer]);
         Material {
-            bind_group: engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("material_bind_group"),
-                layout: &engine.device.create_bind_group_layout(&Material::BIND_LAYOUT),
-                entries: &[
-                    // diffuse texture
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&diffuse_texture.view)
-                    },
-
-                    // diffuse sampler
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler)
-                    },
-                    
-                    // roughness texture
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: wgpu::BindingResource::TextureView(&roughness_texture.view)
-                    },
-
-                    // roughness sampler
-                    wgpu::BindGroupEntry {
-                        binding: 3,
-                        resource: wgpu::BindingResource::Sampler(&roughness_texture.sampler)
-                    },
-                    
-                    // emissive texture
-                    wgpu::BindGroupEntry {
-                        binding: 4,
-                        resource: wgpu::BindingResource::TextureView(&emissive_texture.view)
-                    },
-
-                    // emissive sampler
-                    wgpu::BindGroupEntry {
-                        binding: 5,
-                        resource: wgpu::BindingResource::Sampler(&emissive_texture.sampler)
-                    },
-                    
-                    // normal texture
-                    wgpu::BindGroupEntry {
-                        binding: 6,
-                        resource: wgpu::BindingResource::TextureView(&normal_texture.view)
-                    },
-
-                    // normal sampler
-                    wgpu::BindGroupEntry {
-                        binding: 7,
-                        resource: wgpu::BindingResource::Sampler(&normal_texture.sampler)
-                    },
-                    
-                    // occlusion texture
-                    wgpu::BindGroupEntry {
-                        binding: 8,
-                        resource: wgpu::BindingResource::TextureView(&occlusion_texture.view)
-                    },
-
-                    // occlusion sampler
-                    wgpu::BindGroupEntry {
-                        binding: 9,
-                        resource: wgpu::BindingResource::Sampler(&occlusion_texture.sampler)
-                    },
-                    
-                    // diffuse color buffer
-                    wgpu::BindGroupEntry {
-                        binding: 10,
-                        resource: albedo_buffer.as_entire_binding()
-                    },
-
-                    // emissive color buffer
-                    wgpu::BindGroupEntry {
-                        binding: 11,
-                        resource: emissive_buffer.as_entire_binding()
-                    },
-
-                    // metadata buffer
-                    wgpu::BindGroupEntry {
-                        binding: 12,
-                        resource: metadata_buffer.as_entire_binding()
-                    }
-                ]
-            })
+            blended: self.alpha_mode == 3.0,
+            cast_shadows: true,
+            receive_shadows: true,
+            bound_views: cached_views(engine, &textures),
+            textures,
+            bind_group,
+            albedo_buffer,
+            emissive_buffer,
+            metadata_buffer,
+            shadow_buffer
         }
     }
 }
 
 #[derive(Debug)]
 pub struct Material {
-    bind_group: wgpu::BindGroup
+    blended: bool,
+    cast_shadows: bool,
+    receive_shadows: bool,
+
+    // FORMAT: albedo, roughness, emissive, normal, occlusion
+    textures: [Option<MaterialTexture>; 5],
+    bound_views: Vec<wgpu::Id<wgpu::TextureView>>,
+    bind_group: wgpu::BindGroup,
+    albedo_buffer: wgpu::Buffer,
+    emissive_buffer: wgpu::Buffer,
+    metadata_buffer: wgpu::Buffer,
+    shadow_buffer: wgpu::Buffer
 }
 
 impl Material {
@@ -287,14 +269,177 @@ impl Material {
                     min_binding_size: None
                 },
                 count: None
+            },
+
+            // shadow flags
+            wgpu::BindGroupLayoutEntry {
+                binding: 13,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer { 
+                    ty: wgpu::BufferBindingType::Uniform, 
+                    has_dynamic_offset: false, 
+                    min_binding_size: None
+                },
+                count: None
             }
         ],
         label: Some("texture_bind_group_layout")
     };
 
+    /// Returns true if this material uses the blend alpha mode, blended materials are drawn after all other materials of a model.
+    pub fn is_blended(&self) -> bool { self.blended }
+
+    /// Returns true if meshes drawn with this material are drawn into shadow maps.
+    pub fn casts_shadows(&self) -> bool { self.cast_shadows }
+
+    /// Returns true if meshes drawn with this material are darkened by the shadows of other meshes.
+    pub fn receives_shadows(&self) -> bool { self.receive_shadows }
+
+    /// Sets if meshes drawn with this material are drawn into shadow maps.
+    pub fn set_cast_shadows(&mut self, cast_shadows: bool) { self.cast_shadows = cast_shadows; }
+
+    /// Sets if meshes drawn with this material are darkened by the shadows of other meshes.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine used to update the shadow flags buffer.
+    /// * receive_shadows: bool - True if shadows should be received.
+    pub fn set_receive_shadows(&mut self, engine: &RenderEngine, receive_shadows: bool) {
+        self.receive_shadows = receive_shadows;
+        engine.queue.write_buffer(&self.shadow_buffer, 0, bytemuck::cast_slice(&[if receive_shadows { 1.0f32 } else { 0.0 }, 0.0, 0.0, 0.0]));
+    }
+
+    /// Recreates the bind group of this material from its textures.  Call this after a texture in the render engine's texture cache used by this material is replaced, like when a `RenderTarget` is resized, so the material stops sampling the old texture.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine whose texture cache the material's textures are looked up in.
+    pub fn rebind(&mut self, engine: &RenderEngine) {
+        self.bind_group = Self::create_bind_group(engine, &self.textures, [&self.albedo_buffer, &self.emissive_buffer, &self.metadata_buffer, &self.shadow_buffer]);
+        self.bound_views = cached_views(engine, &self.textures);
+    }
+
+    /// Returns true if a texture in the render engine's texture cache used by this material was replaced since the material's bind group was created, so `rebind` must be called.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine whose texture cache the material's textures are looked up in.
+    pub fn needs_rebind(&self, engine: &RenderEngine) -> bool { self.bound_views != cached_views(engine, &self.textures) }
+
     pub fn bind<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>, idx: u32) { 
         pass.set_bind_group(idx, &self.bind_group, &[]) 
     }
+
+    /// Creates the bind group of a material.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to create the bind group with.
+    /// * textures: &[Option<MaterialTexture>; 5] - The albedo, roughness, emissive, normal and occlusion textures, missing textures are replaced by defaults.
+    /// * buffers: [&wgpu::Buffer; 4] - The albedo, emissive, metadata and shadow flags buffers.
+    fn create_bind_group(engine: &RenderEngine, textures: &[Option<MaterialTexture>; 5], buffers: [&wgpu::Buffer; 4]) -> wgpu::BindGroup {
+        // unpack textures, missing normal maps use a flat normal so the vertex normal is used
+        let empty_texture = create_empty_texture(engine);
+        let flat_normal_texture = create_flat_normal_texture(engine);
+        let [diffuse_texture, roughness_texture, emissive_texture, normal_texture, occlusion_texture] = [0, 1, 2, 3, 4].map(|index| {
+            let default = if index == NORMAL_TEXTURE { &flat_normal_texture } else { &empty_texture };
+            textures[index].as_ref().map_or(default, |texture| texture.texture(engine))
+        });
+        let [albedo_buffer, emissive_buffer, metadata_buffer, shadow_buffer] = buffers;
+
+        engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("material_bind_group"),
+            layout: &engine.device.create_bind_group_layout(&Material::BIND_LAYOUT),
+            entries: &[
+                // diffuse texture
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view)
+                },
+
+                // diffuse sampler
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler)
+                },
+                
+                // roughness texture
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&roughness_texture.view)
+                },
+
+                // roughness sampler
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&roughness_texture.sampler)
+                },
+                
+                // emissive texture
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&emissive_texture.view)
+                },
+
+                // emissive sampler
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Sampler(&emissive_texture.sampler)
+                },
+                
+                // normal texture
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(&normal_texture.view)
+                },
+
+                // normal sampler
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: wgpu::BindingResource::Sampler(&normal_texture.sampler)
+                },
+                
+                // occlusion texture
+                wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: wgpu::BindingResource::TextureView(&occlusion_texture.view)
+                },
+
+                // occlusion sampler
+                wgpu::BindGroupEntry {
+                    binding: 9,
+                    resource: wgpu::BindingResource::Sampler(&occlusion_texture.sampler)
+                },
+                
+                // diffuse color buffer
+                wgpu::BindGroupEntry {
+                    binding: 10,
+                    resource: albedo_buffer.as_entire_binding()
+                },
+
+                // emissive color buffer
+                wgpu::BindGroupEntry {
+                    binding: 11,
+                    resource: emissive_buffer.as_entire_binding()
+                },
+
+                // metadata buffer
+                wgpu::BindGroupEntry {
+                    binding: 12,
+                    resource: metadata_buffer.as_entire_binding()
+                },
+
+                // shadow flags buffer
+                wgpu::BindGroupEntry {
+                    binding: 13,
+                    resource: shadow_buffer.as_entire_binding()
+                }
+            ]
+        })
+    }
+}
+
+/// The views of the given textures that are kept in the render engine's texture cache, in order.
+fn cached_views(engine: &RenderEngine, textures: &[Option<MaterialTexture>; 5]) -> Vec<wgpu::Id<wgpu::TextureView>> {
+    textures.iter().flatten()
+        .filter_map(|texture| match texture { MaterialTexture::Cached(handle) => Some(engine.texture(handle).view.global_id()), MaterialTexture::Owned(_) => None })
+        .collect()
 }
 
 fn create_empty_texture(engine: &RenderEngine) -> Texture {
@@ -304,4 +449,15 @@ fn create_empty_texture(engine: &RenderEngine) -> Texture {
         include_bytes!("empty.png"), 
         "forte.material.blank_texture", 
     ).expect("Empty texture for Material did not create properly.")
-}
\ No newline at end of file
+}
+
+fn create_flat_normal_texture(engine: &RenderEngine) -> Texture {
+    Texture::from_raw_with_format(
+        &engine.device, 
+        &engine.queue, 
+        &[128, 128, 255, 255], 
+        (1, 1), 
+        wgpu::TextureFormat::Rgba8Unorm, 
+        Some("forte.material.flat_normal_texture")
+    ).expect("Flat normal texture for Material did not create properly.")
+}
@@ -1,39 +1,389 @@
-use crate::primitives::mesh::Mesh;
+use std::collections::HashMap;
 
-use self::material::Material;
+use cgmath::{Matrix, Matrix3, Matrix4, SquareMatrix};
+use wgpu::util::DeviceExt;
 
+use crate::{math::transforms::Transform, primitives::mesh::Mesh, render::render_engine::RenderEngine};
+
+use self::{animation::{AnimationClip, Pose}, material::Material, morph::MorphTargets, skin::{Skin, MAX_JOINTS}};
+
+pub mod animation;
 pub mod gltf;
 pub mod material;
+pub mod morph;
+pub mod skin;
 
 #[derive(Debug)]
 pub struct Model {
-    pub nodes: Vec<Node>
+    pub nodes: Vec<Node>,
+    pub skins: Vec<Skin>,
+    pub animations: Vec<AnimationClip>
 }
 
 impl Model {
-    // simply call draws for all nodes in this model
-    pub fn draw<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>, instances: &'rpass wgpu::Buffer, num_instances: u32) {
-        self.nodes.iter().for_each(|node| node.draw(pass, instances, num_instances));
+    // simply call draws for all nodes in this model, each node binds the `forte.gltf` pipeline variant it needs
+    // meshes with blended materials are drawn after all other meshes so they blend with what is behind them
+    pub fn draw<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>, engine: &'rpass RenderEngine, instances: &'rpass wgpu::Buffer, num_instances: u32) {
+        self.nodes.iter().for_each(|node| node.draw_filtered(pass, engine, instances, num_instances, false));
+        self.nodes.iter().for_each(|node| node.draw_filtered(pass, engine, instances, num_instances, true));
+    }
+
+    /// Draws the depth of all meshes in this model whose material casts shadows, each node binds the `forte.gltf` shadow pipeline variant it needs.  Call this from `ShadowCaster::draw_shadow`.
+    ///
+    /// Arguments:
+    /// * pass: &mut wgpu::RenderPass - The shadow map render pass to draw too.
+    /// * engine: &RenderEngine - The render engine the shadow pipelines were created with.
+    /// * instances: &wgpu::Buffer - The instances to draw the model with.
+    /// * num_instances: u32 - The number of instances in the above buffer.
+    pub fn draw_shadows<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>, engine: &'rpass RenderEngine, instances: &'rpass wgpu::Buffer, num_instances: u32) {
+        self.nodes.iter().for_each(|node| node.draw_shadows(pass, engine, instances, num_instances));
+    }
+
+    /// Sets if all meshes in this model cast and receive shadows.  Individual meshes can be changed through their material.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine used to update the materials.
+    /// * cast_shadows: bool - True if the meshes should be drawn into shadow maps.
+    /// * receive_shadows: bool - True if the meshes should be darkened by shadows.
+    pub fn set_shadows(&mut self, engine: &RenderEngine, cast_shadows: bool, receive_shadows: bool) {
+        self.nodes.iter_mut().for_each(|node| node.set_shadows(engine, cast_shadows, receive_shadows));
+    }
+
+    /// Recalculates the world matrices of all nodes from their local transforms, and the joint matrices of all skinned nodes, and uploads them to the GPU.  Call this after changing the transform of any node.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine used to update the node buffers.
+    pub fn update_transforms(&mut self, engine: &RenderEngine) {
+        let mut worlds = HashMap::new();
+        self.nodes.iter_mut().for_each(|node| node.update_transforms(engine, &Matrix4::identity(), &mut worlds));
+
+        // joints can be anywhere in the hierarchy, so skins are updated once all world matrices are known
+        if !self.skins.is_empty() {
+            self.nodes.iter().for_each(|node| node.update_skins(engine, &self.skins, &worlds));
+        }
     }
+
+    /// Uploads the morph target weights of all nodes to the GPU.  Call this after changing the weights of any node's morph targets.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine used to update the weight buffers.
+    pub fn update_morph_weights(&self, engine: &RenderEngine) {
+        self.nodes.iter().for_each(|node| node.update_morph_weights(engine));
+    }
+
+    /// Returns the local transforms and morph target weights of all nodes in this model.
+    pub fn pose(&self) -> Pose {
+        let mut pose = Pose::default();
+        self.nodes.iter().for_each(|node| node.collect_pose(&mut pose));
+        pose
+    }
+
+    /// Sets the local transforms and morph target weights of all nodes in the given pose, `update_transforms` and `update_morph_weights` must be called afterwards for the changes to be drawn.
+    ///
+    /// Arguments:
+    /// * pose: &Pose - The pose to apply.
+    pub fn apply_pose(&mut self, pose: &Pose) {
+        self.nodes.iter_mut().for_each(|node| node.apply_pose(pose));
+    }
+
+    /// Finds the index of the animation clip with the given name.
+    ///
+    /// Returns an option containing the index of the clip in `animations` if it exists.
+    pub fn animation(&self, name: &str) -> Option<usize> { self.animations.iter().position(|clip| clip.name.as_deref() == Some(name)) }
+
+    /// Finds the node with the given glTF node index in this model.
+    ///
+    /// Returns an option containing a reference to the node if it exists.
+    pub fn node(&self, index: usize) -> Option<&Node> { self.nodes.iter().find_map(|node| node.find(index)) }
+
+    /// Finds the node with the given glTF node index in this model.
+    ///
+    /// Returns an option containing a mutable reference to the node if it exists.
+    pub fn node_mut(&mut self, index: usize) -> Option<&mut Node> { self.nodes.iter_mut().find_map(|node| node.find_mut(index)) }
 }
 
+/// A node in a model's scene hierarchy.  Each node has a transform relative to its parent that is applied to its meshes and children.
 #[derive(Debug)]
 pub struct Node {
+    pub index: usize,
+    pub transform: Transform,
     pub meshes: Option<Vec<(Mesh, Material)>>,
-    pub children: Vec<Node>
+    pub children: Vec<Node>,
+    pub skin: Option<usize>,
+    pub morph_targets: Option<MorphTargets>,
+
+    world: Matrix4<f32>,
+    buffer: wgpu::Buffer,
+    joint_buffer: Option<wgpu::Buffer>,
+    bind_group: wgpu::BindGroup
 }
 
+/// The layout entry of a node's transform uniform.
+const NODE_LAYOUT_ENTRY: wgpu::BindGroupLayoutEntry = wgpu::BindGroupLayoutEntry {
+    binding: 0,
+    visibility: wgpu::ShaderStages::VERTEX,
+    count: None,
+    ty: wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Uniform,
+        has_dynamic_offset: false,
+        min_binding_size: None
+    }
+};
+
+/// The layout entry of a skinned node's joint matrices.
+const JOINTS_LAYOUT_ENTRY: wgpu::BindGroupLayoutEntry = wgpu::BindGroupLayoutEntry {
+    binding: 1,
+    visibility: wgpu::ShaderStages::VERTEX,
+    count: None,
+    ty: wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Uniform,
+        has_dynamic_offset: false,
+        min_binding_size: None
+    }
+};
+
+/// The layout entry of a morphed node's deltas, offset to the current mesh when bound.
+const MORPH_DELTAS_LAYOUT_ENTRY: wgpu::BindGroupLayoutEntry = wgpu::BindGroupLayoutEntry {
+    binding: 2,
+    visibility: wgpu::ShaderStages::VERTEX,
+    count: None,
+    ty: wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Storage { read_only: true },
+        has_dynamic_offset: true,
+        min_binding_size: None
+    }
+};
+
+/// The layout entry of a morphed node's weights.
+const MORPH_WEIGHTS_LAYOUT_ENTRY: wgpu::BindGroupLayoutEntry = wgpu::BindGroupLayoutEntry {
+    binding: 3,
+    visibility: wgpu::ShaderStages::VERTEX,
+    count: None,
+    ty: wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Storage { read_only: true },
+        has_dynamic_offset: false,
+        min_binding_size: None
+    }
+};
+
 impl Node {
-    pub fn draw<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>, instances: &'rpass wgpu::Buffer, num_instances: u32) {
+    /// The bind group layout for a nodes transform so there is consistency across implementations that draw nodes.
+    pub const BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("node_bind_layout"),
+        entries: &[NODE_LAYOUT_ENTRY]
+    };
+
+    /// The bind group layout for a skinned nodes transform and joint matrices.
+    pub const SKINNED_BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("skinned_node_bind_layout"),
+        entries: &[NODE_LAYOUT_ENTRY, JOINTS_LAYOUT_ENTRY]
+    };
+
+    /// The bind group layout for a nodes transform and morph targets.
+    pub const MORPH_BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("morph_node_bind_layout"),
+        entries: &[NODE_LAYOUT_ENTRY, MORPH_DELTAS_LAYOUT_ENTRY, MORPH_WEIGHTS_LAYOUT_ENTRY]
+    };
+
+    /// The bind group layout for a skinned nodes transform, joint matrices and morph targets.
+    pub const SKINNED_MORPH_BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("skinned_morph_node_bind_layout"),
+        entries: &[NODE_LAYOUT_ENTRY, JOINTS_LAYOUT_ENTRY, MORPH_DELTAS_LAYOUT_ENTRY, MORPH_WEIGHTS_LAYOUT_ENTRY]
+    };
+
+    /// Creates a new node with its GPU resources.  The world matrix of the node will be the local transform until `Model::update_transforms` is called.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to create the node's buffers with.
+    /// * index: usize - The index of the node in the file it was loaded from.
+    /// * transform: Transform - The transform of this node relative to its parent.
+    /// * meshes: Option<Vec<(Mesh, Material)>> - The meshes drawn by this node.
+    /// * children: Vec<Node> - The children of this node.
+    /// * skin: Option<usize> - The index of the skin in the model that deforms this node's meshes, skinned meshes must use `SkinnedVertex`.
+    /// * morph_targets: Option<MorphTargets> - The morph targets of this node's meshes.
+    pub fn new(engine: &RenderEngine, index: usize, transform: Transform, meshes: Option<Vec<(Mesh, Material)>>, children: Vec<Node>, skin: Option<usize>, morph_targets: Option<MorphTargets>) -> Self {
+        let world = transform.to_mat();
+        let buffer = engine.device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("node_buffer"),
+                contents: bytemuck::cast_slice(&[NodeUniform::from_matrix(&world)]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        );
+
+        // skinned nodes also need a buffer for their joint matrices
+        let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+        let joint_buffer = skin.map(|_| engine.device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("joint_buffer"),
+                contents: bytemuck::cast_slice(&[identity; MAX_JOINTS]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        ));
+        let bind_group = Self::create_bind_group(engine, &buffer, joint_buffer.as_ref(), morph_targets.as_ref());
+
+        Self { index, transform, meshes, children, skin, morph_targets, world, buffer, joint_buffer, bind_group }
+    }
+
+    /// The world matrix of this node as of the last `Model::update_transforms` call.
+    pub fn world_matrix(&self) -> &Matrix4<f32> { &self.world }
+
+    /// Gives each instance drawn with this node its own morph target weights, instances without weights use the default weights of the morph targets.  Does nothing if this node has no morph targets.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine used to update the weights buffer.
+    /// * weights: &[f32] - The weights of each instance, formatted as `[instance][target]`.
+    pub fn set_instance_morph_weights(&mut self, engine: &RenderEngine, weights: &[f32]) {
+        let recreated = match &mut self.morph_targets {
+            Some(morph_targets) => morph_targets.set_instance_weights(engine, weights),
+            None => return
+        };
+        if recreated { self.bind_group = Self::create_bind_group(engine, &self.buffer, self.joint_buffer.as_ref(), self.morph_targets.as_ref()); }
+    }
+
+    pub fn draw<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>, engine: &'rpass RenderEngine, instances: &'rpass wgpu::Buffer, num_instances: u32) {
+        self.draw_filtered(pass, engine, instances, num_instances, false);
+        self.draw_filtered(pass, engine, instances, num_instances, true);
+    }
+
+    /// Draws the meshes of this node and its children whose material is blended or not blended.
+    fn draw_filtered<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>, engine: &'rpass RenderEngine, instances: &'rpass wgpu::Buffer, num_instances: u32, blended: bool) {
         // if we have meshes in this node, draw all meshes w/ its materials
-        if self.meshes.is_some() {
-            self.meshes.as_ref().unwrap().iter().for_each(|drawn| {
+        if let Some(meshes) = self.meshes.as_ref().filter(|meshes| meshes.iter().any(|drawn| drawn.1.is_blended() == blended)) {
+            engine.pipeline_path(self.pipeline(false)).unwrap().bind(pass);
+            if self.morph_targets.is_none() { pass.set_bind_group(3, &self.bind_group, &[]); }
+            meshes.iter().enumerate().filter(|(_, drawn)| drawn.1.is_blended() == blended).for_each(|(index, drawn)| {
+                // morphed meshes each bind their own range of the deltas buffer
+                if let Some(morph_targets) = &self.morph_targets { pass.set_bind_group(3, &self.bind_group, &[morph_targets.offset(index)]); }
                 drawn.1.bind(pass, 1);
                 drawn.0.draw(pass, instances, num_instances);
             });
         }
 
         // draw children
-        self.children.iter().for_each(|node| node.draw(pass, instances, num_instances));
+        self.children.iter().for_each(|node| node.draw_filtered(pass, engine, instances, num_instances, blended));
+    }
+
+    /// Draws the depth of the meshes of this node and its children whose material casts shadows.
+    fn draw_shadows<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>, engine: &'rpass RenderEngine, instances: &'rpass wgpu::Buffer, num_instances: u32) {
+        if let Some(meshes) = self.meshes.as_ref().filter(|meshes| meshes.iter().any(|drawn| drawn.1.casts_shadows())) {
+            engine.pipeline_path(self.pipeline(true)).unwrap().bind(pass);
+            if self.morph_targets.is_none() { pass.set_bind_group(3, &self.bind_group, &[]); }
+            meshes.iter().enumerate().filter(|(_, drawn)| drawn.1.casts_shadows()).for_each(|(index, drawn)| {
+                if let Some(morph_targets) = &self.morph_targets { pass.set_bind_group(3, &self.bind_group, &[morph_targets.offset(index)]); }
+                drawn.0.draw(pass, instances, num_instances);
+            });
+        }
+
+        self.children.iter().for_each(|node| node.draw_shadows(pass, engine, instances, num_instances));
+    }
+
+    /// The name of the `forte.gltf` pipeline variant this node is drawn with, or the variant drawing its shadows.
+    fn pipeline(&self, shadow: bool) -> &'static str {
+        match (self.skin.is_some(), self.morph_targets.is_some(), shadow) {
+            (false, false, false) => "forte.gltf",
+            (true, false, false) => "forte.gltf.skinned",
+            (false, true, false) => "forte.gltf.morph",
+            (true, true, false) => "forte.gltf.skinned.morph",
+            (false, false, true) => "forte.gltf.shadow",
+            (true, false, true) => "forte.gltf.skinned.shadow",
+            (false, true, true) => "forte.gltf.morph.shadow",
+            (true, true, true) => "forte.gltf.skinned.morph.shadow"
+        }
+    }
+
+    /// Sets if the meshes of this node and its children cast and receive shadows.
+    fn set_shadows(&mut self, engine: &RenderEngine, cast_shadows: bool, receive_shadows: bool) {
+        self.meshes.iter_mut().flatten().for_each(|(_, material)| {
+            material.set_cast_shadows(cast_shadows);
+            material.set_receive_shadows(engine, receive_shadows);
+        });
+        self.children.iter_mut().for_each(|child| child.set_shadows(engine, cast_shadows, receive_shadows));
+    }
+
+    /// Creates the bind group of a node with the layout matching the buffers it has.
+    fn create_bind_group(engine: &RenderEngine, buffer: &wgpu::Buffer, joint_buffer: Option<&wgpu::Buffer>, morph_targets: Option<&MorphTargets>) -> wgpu::BindGroup {
+        let layout = match (joint_buffer.is_some(), morph_targets.is_some()) {
+            (false, false) => Self::BIND_LAYOUT,
+            (true, false) => Self::SKINNED_BIND_LAYOUT,
+            (false, true) => Self::MORPH_BIND_LAYOUT,
+            (true, true) => Self::SKINNED_MORPH_BIND_LAYOUT
+        };
+
+        let mut entries = vec![wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }];
+        if let Some(joint_buffer) = joint_buffer { entries.push(wgpu::BindGroupEntry { binding: 1, resource: joint_buffer.as_entire_binding() }); }
+        if let Some(morph_targets) = morph_targets { entries.extend(morph_targets.bind_group_entries()); }
+
+        engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("node_bind_group"),
+            layout: &engine.device.create_bind_group_layout(&layout),
+            entries: &entries
+        })
+    }
+
+    /// Recalculates the world matrix of this node and its children using the given parent world matrix, recording each world matrix by node index.
+    fn update_transforms(&mut self, engine: &RenderEngine, parent: &Matrix4<f32>, worlds: &mut HashMap<usize, Matrix4<f32>>) {
+        self.world = parent * self.transform.to_mat();
+        engine.queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[NodeUniform::from_matrix(&self.world)]));
+        worlds.insert(self.index, self.world);
+
+        let world = self.world;
+        self.children.iter_mut().for_each(|child| child.update_transforms(engine, &world, worlds));
+    }
+
+    /// Uploads the joint matrices of this node and its children if they are skinned.
+    fn update_skins(&self, engine: &RenderEngine, skins: &[Skin], worlds: &HashMap<usize, Matrix4<f32>>) {
+        if let (Some(skin), Some(joint_buffer)) = (self.skin.and_then(|skin| skins.get(skin)), &self.joint_buffer) {
+            let mut matrices = skin.joint_matrices(worlds);
+            matrices.truncate(MAX_JOINTS);
+            engine.queue.write_buffer(joint_buffer, 0, bytemuck::cast_slice(&matrices));
+        }
+
+        self.children.iter().for_each(|child| child.update_skins(engine, skins, worlds));
+    }
+
+    /// Uploads the morph target weights of this node and its children.
+    fn update_morph_weights(&self, engine: &RenderEngine) {
+        if let Some(morph_targets) = &self.morph_targets { morph_targets.update(engine); }
+        self.children.iter().for_each(|child| child.update_morph_weights(engine));
+    }
+
+    fn collect_pose(&self, pose: &mut Pose) {
+        pose.transforms.insert(self.index, self.transform);
+        if let Some(morph_targets) = &self.morph_targets { pose.weights.insert(self.index, morph_targets.weights.clone()); }
+        self.children.iter().for_each(|child| child.collect_pose(pose));
+    }
+
+    fn apply_pose(&mut self, pose: &Pose) {
+        if let Some(transform) = pose.transforms.get(&self.index) { self.transform = *transform; }
+        if let (Some(morph_targets), Some(weights)) = (&mut self.morph_targets, pose.weights.get(&self.index)) { morph_targets.weights.clone_from(weights); }
+        self.children.iter_mut().for_each(|child| child.apply_pose(pose));
+    }
+
+    fn find(&self, index: usize) -> Option<&Node> {
+        if self.index == index { return Some(self) }
+        self.children.iter().find_map(|child| child.find(index))
+    }
+
+    fn find_mut(&mut self, index: usize) -> Option<&mut Node> {
+        if self.index == index { return Some(self) }
+        self.children.iter_mut().find_map(|child| child.find_mut(index))
+    }
+}
+
+/// The rust representation of a node's world transform that is passed to the shaders.  The normal matrix is stored as a 4x4 matrix to match WGSL alignment.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct NodeUniform {
+    pub model: [[f32; 4]; 4],
+    pub normal: [[f32; 4]; 4]
+}
+
+impl NodeUniform {
+    /// Creates a node uniform from the given world matrix, the normal matrix is the inverse transpose of the matrix's rotation and scale.
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Self {
+        let upper = Matrix3::from_cols(matrix.x.truncate(), matrix.y.truncate(), matrix.z.truncate());
+        let normal = upper.invert().map(|inverse| inverse.transpose()).unwrap_or(upper);
+        Self { model: (*matrix).into(), normal: Matrix4::from(normal).into() }
     }
 }
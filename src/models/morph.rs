@@ -0,0 +1,136 @@
+use wgpu::util::DeviceExt;
+
+use crate::{lights::clusters::LightClusters, render::render_engine::RenderEngine};
+
+/// The rust representation of the position and normal offset of one vertex for one morph target that is passed to the shaders.  Stored as 4 component vectors to match WGSL alignment.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MorphDelta {
+    pub position: [f32; 4],
+    pub normal: [f32; 4]
+}
+
+/// The morph targets (blend shapes) of a node's meshes and the weights they are blended with.
+///
+/// The deltas of all meshes in the node are stored in one storage buffer, each mesh binds its own range with a dynamic offset.  Weights are stored as a row of default weights followed by a row for each instance that overrides them.
+#[derive(Debug)]
+pub struct MorphTargets {
+    /// The weight of each target used by every instance that has not been given its own weights.
+    pub weights: Vec<f32>,
+
+    num_targets: usize,
+    instance_weights: Vec<f32>,
+    offsets: Vec<u32>,
+    deltas: wgpu::Buffer,
+    deltas_size: u64,
+    weights_buffer: wgpu::Buffer
+}
+
+impl MorphTargets {
+    /// Returns true if the given engine supports morph targets, they need storage buffers in vertex shaders which WebGL does not have.  Their shaders also expect lights in a storage buffer, so clustered lighting must be supported too.
+    pub fn is_supported(engine: &RenderEngine) -> bool {
+        engine.device.limits().max_storage_buffers_per_shader_stage >= 2 && LightClusters::is_supported(engine)
+    }
+
+    /// Creates new morph targets for a node.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to create the buffers with.
+    /// * num_targets: usize - The number of morph targets, this is the same for every mesh of the node.
+    /// * weights: Vec<f32> - The default weight of each target.
+    /// * mesh_deltas: &[Vec<MorphDelta>] - The deltas of each mesh in the node, formatted as `[vertex][target]`.
+    pub fn new(engine: &RenderEngine, num_targets: usize, mut weights: Vec<f32>, mesh_deltas: &[Vec<MorphDelta>]) -> Self {
+        weights.resize(num_targets, 0.0);
+
+        // pack the deltas of each mesh at offsets the device can bind
+        let alignment = engine.device.limits().min_storage_buffer_offset_alignment as usize;
+        let delta_size = std::mem::size_of::<MorphDelta>();
+        let deltas_size = mesh_deltas.iter().map(|deltas| deltas.len().max(1) * delta_size).max().unwrap_or(delta_size);
+        let mut contents: Vec<u8> = Vec::new();
+        let offsets = mesh_deltas.iter().map(|deltas| {
+            contents.resize(contents.len().div_ceil(alignment) * alignment, 0);
+            let offset = contents.len() as u32;
+            contents.extend_from_slice(bytemuck::cast_slice(deltas));
+            offset
+        }).collect::<Vec<u32>>();
+        contents.resize(offsets.last().copied().unwrap_or(0) as usize + deltas_size, 0);
+
+        let deltas = engine.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("morph_deltas_buffer"),
+            contents: &contents,
+            usage: wgpu::BufferUsages::STORAGE
+        });
+        let weights_buffer = create_weights_buffer(engine, &weights_contents(num_targets, &weights, &[]));
+
+        Self { weights, num_targets, instance_weights: Vec::new(), offsets, deltas, deltas_size: deltas_size as u64, weights_buffer }
+    }
+
+    /// The number of morph targets.
+    pub fn num_targets(&self) -> usize { self.num_targets }
+
+    /// The weights given to each instance by `Node::set_instance_morph_weights`, formatted as `[instance][target]`.
+    pub fn instance_weights(&self) -> &[f32] { &self.instance_weights }
+
+    /// Uploads the default weights and instance weights to the GPU.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine used to update the weights buffer.
+    pub fn update(&self, engine: &RenderEngine) {
+        engine.queue.write_buffer(&self.weights_buffer, 0, &weights_contents(self.num_targets, &self.weights, &self.instance_weights));
+    }
+
+    /// Sets the weights of each instance, returning true if the weights buffer had to be recreated and any bind groups using it are now out of date.
+    pub(crate) fn set_instance_weights(&mut self, engine: &RenderEngine, weights: &[f32]) -> bool {
+        self.instance_weights = weights.to_vec();
+        let contents = weights_contents(self.num_targets, &self.weights, &self.instance_weights);
+        if contents.len() as u64 > self.weights_buffer.size() {
+            self.weights_buffer = create_weights_buffer(engine, &contents);
+            true
+        } else {
+            engine.queue.write_buffer(&self.weights_buffer, 0, &contents);
+            false
+        }
+    }
+
+    /// The dynamic offset of the deltas of the mesh at the given index.
+    pub(crate) fn offset(&self, mesh: usize) -> u32 { self.offsets.get(mesh).copied().unwrap_or(0) }
+
+    /// The bind group entries of the morph targets, bound alongside the node's other buffers.
+    pub(crate) fn bind_group_entries(&self) -> [wgpu::BindGroupEntry<'_>; 2] {
+        [
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &self.deltas,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(self.deltas_size)
+                })
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: self.weights_buffer.as_entire_binding()
+            }
+        ]
+    }
+}
+
+/// Formats the weights buffer as the number of targets, the number of instances, the default weights and then the weights of each instance.
+fn weights_contents(num_targets: usize, weights: &[f32], instance_weights: &[f32]) -> Vec<u8> {
+    let num_instances = instance_weights.len().checked_div(num_targets).unwrap_or(0);
+    let mut contents: Vec<u8> = Vec::new();
+    contents.extend_from_slice(bytemuck::cast_slice(&[num_targets as u32, num_instances as u32]));
+    weights.iter().copied().chain(std::iter::repeat(0.0)).take(num_targets).for_each(|weight| contents.extend_from_slice(&weight.to_ne_bytes()));
+    contents.extend_from_slice(bytemuck::cast_slice(&instance_weights[.. num_instances * num_targets]));
+
+    // storage buffers must have at least one element in their runtime sized array
+    contents.resize(contents.len().max(12), 0);
+    contents
+}
+
+fn create_weights_buffer(engine: &RenderEngine, contents: &[u8]) -> wgpu::Buffer {
+    engine.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("morph_weights_buffer"),
+        contents,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
+    })
+}
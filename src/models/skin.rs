@@ -0,0 +1,33 @@
+use std::collections::HashMap;
+
+use cgmath::{Matrix4, SquareMatrix};
+
+/// The maximum number of joints a single skin can have, this must match the size of the joints array in the skinned shader.
+pub const MAX_JOINTS: usize = 128;
+
+/// A skin that deforms the vertices of a node's meshes using the world transforms of other nodes in the model, its joints.
+///
+/// Arguments:
+/// * joints: Vec<usize> - The indices of the nodes used as joints, `SkinnedVertex::joints` index into this list.
+/// * inverse_bind_matrices: Vec<Matrix4<f32>> - For each joint, the matrix that transforms a vertex from model space into the joint's space when in bind pose.
+#[derive(Debug, Clone)]
+pub struct Skin {
+    pub joints: Vec<usize>,
+    pub inverse_bind_matrices: Vec<Matrix4<f32>>
+}
+
+impl Skin {
+    /// Calculates the matrix of each joint that moves a vertex from its bind pose to the joint's current pose.
+    ///
+    /// Arguments:
+    /// * worlds: &HashMap<usize, Matrix4<f32>> - The world matrices of the model's nodes, keyed by node index.
+    ///
+    /// Returns a list of joint matrices in the order of `joints`, ready to be uploaded to the GPU.
+    pub fn joint_matrices(&self, worlds: &HashMap<usize, Matrix4<f32>>) -> Vec<[[f32; 4]; 4]> {
+        self.joints.iter().enumerate().map(|(i, joint)| {
+            let world = worlds.get(joint).copied().unwrap_or(Matrix4::identity());
+            let inverse_bind = self.inverse_bind_matrices.get(i).copied().unwrap_or(Matrix4::identity());
+            (world * inverse_bind).into()
+        }).collect()
+    }
+}
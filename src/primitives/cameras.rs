@@ -1,9 +1,144 @@
+use std::borrow::Borrow;
+
 use cgmath::*;
 use wgpu::util::DeviceExt;
 
-use crate::render::{OPENGL_TO_WGPU_MATRIX, render_engine::*};
+use crate::{math::ray::Ray, render::{OPENGL_TO_WGPU_MATRIX, render_engine::*}};
+
+/// How a camera projects what it sees onto the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// A perspective projection where distant objects appear smaller.
+    ///
+    /// Arguments:
+    /// * fovy: f32 - The field of view on the vertical axis in degrees.
+    /// * znear: f32 - The distance to the near clipping plane.
+    /// * zfar: f32 - The distance to the far clipping plane.
+    Perspective { fovy: f32, znear: f32, zfar: f32 },
+
+    /// A perspective projection without a far clipping plane that uses reverse-Z, the near plane has a depth of 1 and depth approaches 0 infinitely far away, which spreads depth precision evenly over the whole view.  Render with it after setting the render engine's depth convention to `DepthConvention::Reverse` (see `RenderEngine::set_depth_convention`), so depth textures are cleared to 0 and pipelines compare depth with `Greater`.
+    ///
+    /// Arguments:
+    /// * fovy: f32 - The field of view on the vertical axis in degrees.
+    /// * znear: f32 - The distance to the near clipping plane.
+    InfinitePerspective { fovy: f32, znear: f32 },
+
+    /// An orthographic projection where objects appear the same size at any distance, for 2D, isometric and CAD-style views.
+    ///
+    /// Arguments:
+    /// * size: f32 - The height of the view in world units, its width follows the aspect ratio.
+    /// * znear: f32 - The distance to the near clipping plane, this may be negative.
+    /// * zfar: f32 - The distance to the far clipping plane.
+    Orthographic { size: f32, znear: f32, zfar: f32 },
+
+    /// A projection matrix built elsewhere, which must map view space to WGPU's clip space where depth ranges from 0 to 1.  It is used as is, ignoring the aspect ratio.
+    Custom(Matrix4<f32>)
+}
+
+impl Projection {
+    /// Builds the projection matrix, mapping view space to WGPU's clip space.
+    ///
+    /// Arguments:
+    /// * aspect: f32 - The width of the view divided by its height.
+    ///
+    /// Returns the projection matrix.
+    pub fn matrix(&self, aspect: f32) -> Matrix4<f32> {
+        match *self {
+            Projection::Perspective { fovy, znear, zfar } => OPENGL_TO_WGPU_MATRIX * cgmath::perspective(Deg(fovy), aspect, znear, zfar),
+            Projection::InfinitePerspective { fovy, znear } => {
+                // depth is znear / distance, so 1 at the near plane and 0 infinitely far away
+                let focal = 1.0 / Deg(fovy / 2.0).tan();
+                #[rustfmt::skip]
+                let matrix = Matrix4::new(
+                    focal / aspect, 0.0, 0.0, 0.0,
+                    0.0, focal, 0.0, 0.0,
+                    0.0, 0.0, 0.0, -1.0,
+                    0.0, 0.0, znear, 0.0
+                );
+                matrix
+            },
+            Projection::Orthographic { size, znear, zfar } => {
+                let half_height = size / 2.0;
+                let half_width = half_height * aspect;
+                OPENGL_TO_WGPU_MATRIX * cgmath::ortho(-half_width, half_width, -half_height, half_height, znear, zfar)
+            },
+            Projection::Custom(matrix) => matrix
+        }
+    }
+
+    /// The distances from the camera to the near and far clipping planes.  The far distance of an `InfinitePerspective` projection is infinite, and the planes of a `Custom` projection are found by unprojecting the center of the view.
+    pub fn depth_range(&self) -> (f32, f32) {
+        match *self {
+            Projection::Perspective { znear, zfar, .. } | Projection::Orthographic { znear, zfar, .. } => (znear, zfar),
+            Projection::InfinitePerspective { znear, .. } => (znear, f32::INFINITY),
+            Projection::Custom(matrix) => {
+                let inverse = matrix.invert().unwrap_or(Matrix4::identity());
+                let distance = |depth: f32| { let point = inverse * Vector4::new(0.0, 0.0, depth, 1.0); -point.z / point.w };
+                let (first, second) = (distance(0.0), distance(1.0));
+                (first.min(second), first.max(second))
+            }
+        }
+    }
+}
+
+/// The part of the surface or render target a camera renders into, measured from the top left as a fraction of its size, so it stays the same as the surface resizes.
+///
+/// Arguments:
+/// * x: f32 - The left edge, from 0 to 1.
+/// * y: f32 - The top edge, from 0 to 1.
+/// * width: f32 - The width, from 0 to 1.
+/// * height: f32 - The height, from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32
+}
+
+impl Default for Viewport {
+    fn default() -> Self { Self::FULL }
+}
+
+impl Viewport {
+    /// A viewport covering the whole surface or render target.
+    pub const FULL: Viewport = Viewport { x: 0.0, y: 0.0, width: 1.0, height: 1.0 };
+
+    /// Creates a new viewport.
+    ///
+    /// Arguments:
+    /// * x: f32 - The left edge, from 0 to 1.
+    /// * y: f32 - The top edge, from 0 to 1.
+    /// * width: f32 - The width, from 0 to 1.
+    /// * height: f32 - The height, from 0 to 1.
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self { Self { x, y, width, height } }
+
+    /// Finds the pixels this viewport covers in a target of the given size.
+    ///
+    /// Arguments:
+    /// * target_size: (u32, u32) - The width and height of the surface or render target in pixels.
+    ///
+    /// Returns the left edge, top edge, width and height in whole pixels, clamped to the target and at least 1 pixel wide and tall.
+    pub fn pixels(&self, target_size: (u32, u32)) -> (u32, u32, u32, u32) {
+        let (target_width, target_height) = (target_size.0.max(1), target_size.1.max(1));
+        let x = ((self.x * target_width as f32).round() as u32).min(target_width - 1);
+        let y = ((self.y * target_height as f32).round() as u32).min(target_height - 1);
+        let right = (((self.x + self.width) * target_width as f32).round() as u32).clamp(x + 1, target_width);
+        let bottom = (((self.y + self.height) * target_height as f32).round() as u32).clamp(y + 1, target_height);
+        (x, y, right - x, bottom - y)
+    }
+}
 
 /// A representation of all information needed to properly use a camera in WGPU.
+///
+/// Arguments:
+/// * position: Vector3<f32> - The position of the camera in world space.
+/// * rotation: Quaternion<f32> - The rotation from world space into the camera's view, where the camera looks down -Z with +Y up.  Use `look_at` rather than setting it by hand.
+/// * projection: Projection - How the camera projects what it sees onto the screen.
+/// * aspect: Option<f32> - The width of the camera's view divided by its height, or none to follow the shape of its viewport.
+/// * viewport: Viewport - The part of the target the camera renders into, the whole target by default.  Cameras with different viewports can share a pass for split-screen, picture-in-picture and editor views.
+/// * order: i32 - The order cameras sharing a target should be rendered in, lowest first, so cameras drawn over others, like picture-in-picture, come last.  Sort cameras by it with `sort_by_order`.
+/// * target_size: Option<(u32, u32)> - The size in pixels of the texture the camera renders into, or none for the surface.  Set this to `RenderTarget::size` when rendering into a render target.
 #[derive(Debug)]
 pub struct Camera {
     pub uniform: CameraUniform,
@@ -13,10 +148,11 @@ pub struct Camera {
 
     pub position: cgmath::Vector3<f32>,
     pub rotation: cgmath::Quaternion<f32>,
-    pub aspect: f32,
-    pub fovy: f32,
-    pub znear: f32,
-    pub zfar: f32
+    pub projection: Projection,
+    pub aspect: Option<f32>,
+    pub viewport: Viewport,
+    pub order: i32,
+    pub target_size: Option<(u32, u32)>
 }
 
 impl Camera {
@@ -37,23 +173,16 @@ impl Camera {
         ]
     };
 
-    /// Creates a new camera.
+    /// Creates a new camera at the origin looking down -Z, rendering into the whole surface.
     /// 
     /// Arguments:
     /// * engine: &RenderEngine - The render engine that this camera will belong too.
-    /// * aspect: f32 - The aspect ratio of this camera
-    /// * fovy: f32 - The field of view on the vertical axis of the camera.
-    /// * znear: f32 - The distance to the nearest clipping plane where the rendering will stop.
-    /// * zfar: f32 - The distance to the farthest clipping plane where the rendering will stop.
+    /// * projection: Projection - How the camera projects what it sees onto the screen.
     /// 
-    /// Note: only objects between the two mentioned clipping planes will be rendered.
+    /// Note: only objects between the near and far clipping planes of the projection will be rendered.
     /// 
     /// Returns a new camera.
-    pub fn new(
-        engine: &RenderEngine, 
-        aspect: f32, fovy: f32, 
-        znear: f32, zfar: f32
-    ) -> Self {
+    pub fn new(engine: &RenderEngine, projection: Projection) -> Self {
         let uniform = CameraUniform::new();
 
         // create camera uniform and buffer
@@ -81,18 +210,146 @@ impl Camera {
             uniform, buffer, bind_layout, bind_group,
             position: (0.0, 0.0, 0.0).into(), 
             rotation: (0.0, 0.0, 0.0, 1.0).into(),
-            aspect, fovy, znear, zfar
+            projection,
+            aspect: None,
+            viewport: Viewport::FULL,
+            order: 0,
+            target_size: None
         }
     }
 
+    /// The width of the camera's view divided by its height, `aspect` if it is set, otherwise the shape of its viewport.
+    pub fn aspect_ratio(&self, engine: &RenderEngine) -> f32 {
+        let (width, height) = self.target_size(engine);
+        self.screen_aspect(Vector2::new(width as f32, height as f32))
+    }
+
+    /// The size in pixels of the texture this camera renders into, `target_size` if it is set, otherwise the size of the surface.
+    pub fn target_size(&self, engine: &RenderEngine) -> (u32, u32) { self.target_size.unwrap_or((engine.config.width, engine.config.height)) }
+
+    /// Builds the view matrix of this camera, mapping world space into the camera's view.
+    pub fn view_matrix(&self) -> Matrix4<f32> { Matrix4::from(self.rotation) * Matrix4::from_translation(-self.position) }
+
+    /// Builds the projection matrix of this camera with its aspect ratio, mapping the camera's view to WGPU's clip space.
+    pub fn projection_matrix(&self, engine: &RenderEngine) -> Matrix4<f32> { self.projection.matrix(self.aspect_ratio(engine)) }
+
     /// Builds a view projection matrix for this camera.
     /// 
     /// Returns the view and projection matrix for this camera.
     pub fn build_view_projection_matrix(&self, engine: &RenderEngine) -> cgmath::Matrix4<f32> {
-        let view = Matrix4::from(self.rotation) * Matrix4::from_translation(-self.position);
-        let proj = cgmath::perspective(cgmath::Deg(self.fovy), engine.config.width as f32 / engine.config.height as f32, self.znear, self.zfar);
-        // println!("Build")
-        return OPENGL_TO_WGPU_MATRIX * proj * view;
+        self.projection_matrix(engine) * self.view_matrix()
+    }
+
+    /// Rotates this camera to look at the given point.
+    ///
+    /// Arguments:
+    /// * target: Vector3<f32> - The point to look at in world space.
+    /// * up: Vector3<f32> - The direction that should appear up, a perpendicular direction is used if the camera looks straight along it.
+    pub fn look_at(&mut self, target: Vector3<f32>, up: Vector3<f32>) { self.look_to(target - self.position, up); }
+
+    /// Rotates this camera to look in the given direction.
+    ///
+    /// Arguments:
+    /// * direction: Vector3<f32> - The direction to look in, the camera does not rotate if this is zero.
+    /// * up: Vector3<f32> - The direction that should appear up, a perpendicular direction is used if the camera looks straight along it.
+    pub fn look_to(&mut self, direction: Vector3<f32>, up: Vector3<f32>) {
+        if direction.magnitude2() <= f32::EPSILON { return }
+
+        let direction = direction.normalize();
+        let up = if direction.cross(up).magnitude2() > 1e-6 { up } else if direction.y.abs() < 0.9 { Vector3::unit_y() } else { Vector3::unit_z() };
+        self.rotation = Quaternion::from(Matrix3::look_to_rh(direction, up));
+    }
+
+    /// The direction this camera looks in, in world space.
+    pub fn forward(&self) -> Vector3<f32> { self.rotation.conjugate().rotate_vector(-Vector3::unit_z()) }
+
+    /// The direction to the right of this camera's view, in world space.
+    pub fn right(&self) -> Vector3<f32> { self.rotation.conjugate().rotate_vector(Vector3::unit_x()) }
+
+    /// The direction to the top of this camera's view, in world space.
+    pub fn up(&self) -> Vector3<f32> { self.rotation.conjugate().rotate_vector(Vector3::unit_y()) }
+
+    /// Finds the corners of the slice of this camera's view between two distances, for example, to fit shadow maps around it.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine whose surface the aspect ratio may follow.
+    /// * near: f32 - The distance from the camera to the near side of the slice.
+    /// * far: f32 - The distance from the camera to the far side of the slice.
+    ///
+    /// Returns the 8 corners of the slice in world space, the 4 near corners followed by the 4 far corners.
+    pub fn frustum_corners(&self, engine: &RenderEngine, near: f32, far: f32) -> [Vector3<f32>; 8] {
+        let inverse_projection = self.projection_matrix(engine).invert().unwrap_or(Matrix4::identity());
+        let inverse_view = self.view_matrix().invert().unwrap_or(Matrix4::identity());
+        std::array::from_fn(|index| {
+            let ndc = Vector2::new([-1.0, 1.0, 1.0, -1.0][index % 4], [-1.0, -1.0, 1.0, 1.0][index % 4]);
+            let point = view_point(&inverse_projection, ndc, if index < 4 { near } else { far });
+            (inverse_view * point.extend(1.0)).truncate()
+        })
+    }
+
+    /// Finds the ray going through the given pixel into the scene, for example, to find what is under the cursor.  The ray starts on the near plane and goes away from the camera, rays of orthographic projections are all parallel.
+    ///
+    /// Arguments:
+    /// * pixel: Vector2<f32> - The pixel, measured from the top left of the screen.
+    /// * screen_size: Vector2<f32> - The size in pixels of the surface or render target the camera renders into.
+    ///
+    /// Returns the ray in world space.
+    pub fn screen_to_ray(&self, pixel: Vector2<f32>, screen_size: Vector2<f32>) -> Ray {
+        let ndc = self.pixel_to_ndc(pixel, screen_size);
+        let inverse_projection = self.projection.matrix(self.screen_aspect(screen_size)).invert().unwrap_or(Matrix4::identity());
+        let inverse_view = self.view_matrix().invert().unwrap_or(Matrix4::identity());
+
+        // only the direction matters past the near plane, so projections without a far plane use any point beyond it
+        let (near, far) = self.projection.depth_range();
+        let far = if far.is_finite() && far > near { far } else { near + 1.0 };
+        let start = view_point(&inverse_projection, ndc, near);
+        let end = view_point(&inverse_projection, ndc, far);
+        Ray::new((inverse_view * start.extend(1.0)).truncate(), (inverse_view * (end - start).extend(0.0)).truncate())
+    }
+
+    /// Projects a point in world space onto the screen, for example, to place UI labels over 3D points.
+    ///
+    /// Arguments:
+    /// * point: Vector3<f32> - The point in world space.
+    /// * screen_size: Vector2<f32> - The size in pixels of the surface or render target the camera renders into.
+    ///
+    /// Returns an option containing the pixel the point is drawn at, measured from the top left of the screen, with the point's depth from 0 to 1 as z, or none if the point is behind a perspective camera.  Points outside the view are still projected, outside of the screen.
+    pub fn world_to_screen(&self, point: Vector3<f32>, screen_size: Vector2<f32>) -> Option<Vector3<f32>> {
+        let clip = self.projection.matrix(self.screen_aspect(screen_size)) * self.view_matrix() * point.extend(1.0);
+        if clip.w <= f32::EPSILON { return None }
+
+        let ndc = clip.truncate() / clip.w;
+        let x = screen_size.x * (self.viewport.x + (ndc.x + 1.0) * 0.5 * self.viewport.width);
+        let y = screen_size.y * (self.viewport.y + (1.0 - ndc.y) * 0.5 * self.viewport.height);
+        Some(Vector3::new(x, y, ndc.z))
+    }
+
+    /// Finds the point in world space drawn at the given pixel and depth, the inverse of `world_to_screen`.
+    ///
+    /// Arguments:
+    /// * pixel: Vector2<f32> - The pixel, measured from the top left of the screen.
+    /// * depth: f32 - The depth of the point from 0 to 1, as read from a depth texture.
+    /// * screen_size: Vector2<f32> - The size in pixels of the surface or render target the camera renders into.
+    ///
+    /// Returns the point in world space.
+    pub fn unproject(&self, pixel: Vector2<f32>, depth: f32, screen_size: Vector2<f32>) -> Vector3<f32> {
+        let ndc = self.pixel_to_ndc(pixel, screen_size);
+        let view_projection = self.projection.matrix(self.screen_aspect(screen_size)) * self.view_matrix();
+        let point = view_projection.invert().unwrap_or(Matrix4::identity()) * Vector4::new(ndc.x, ndc.y, depth, 1.0);
+        point.truncate() / point.w
+    }
+
+    /// The aspect ratio used for a screen of the given size, `aspect` if it is set, otherwise the shape of the viewport on the screen.
+    fn screen_aspect(&self, screen_size: Vector2<f32>) -> f32 {
+        self.aspect.unwrap_or((screen_size.x * self.viewport.width) / (screen_size.y * self.viewport.height).max(1.0))
+    }
+
+    /// Converts a pixel measured from the top left of the screen to normalized device coordinates of this camera's viewport.
+    fn pixel_to_ndc(&self, pixel: Vector2<f32>, screen_size: Vector2<f32>) -> Vector2<f32> {
+        let width = (screen_size.x * self.viewport.width).max(1.0);
+        let height = (screen_size.y * self.viewport.height).max(1.0);
+        let (x, y) = (pixel.x - screen_size.x * self.viewport.x, pixel.y - screen_size.y * self.viewport.y);
+        Vector2::new(x / width * 2.0 - 1.0, 1.0 - y / height * 2.0)
     }
 
     /// Updates the camera and its WGPU buffers with the given render engine.
@@ -105,7 +362,13 @@ impl Camera {
         engine.queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
     }
 
-    /// Binds this camera to the given render pass at the given bind group index.
+    /// Sorts the given cameras by their `order`, lowest first, so binding and drawing with each of them in turn draws cameras with a higher order over the others.  Cameras with the same order keep their order.
+    ///
+    /// Arguments:
+    /// * cameras: &mut [C] - The cameras to sort, or references to them.
+    pub fn sort_by_order<C: Borrow<Camera>>(cameras: &mut [C]) { cameras.sort_by_key(|camera| camera.borrow().order); }
+
+    /// Binds this camera to the given render pass at the given bind group index, and limits drawing to the camera's viewport.  Everything drawn in the pass afterwards stays in the viewport until another camera is bound.
     /// 
     /// Arguments:
     /// * &self - The camera to be bound.
@@ -118,8 +381,26 @@ impl Camera {
         index: u32
     ) {
         self.update(engine);
+        self.set_viewport(pass, engine);
         pass.set_bind_group(index, &self.bind_group, &[]);
     }
+
+    /// Sets the viewport of the given render pass to this camera's viewport, nothing drawn afterwards reaches outside of it.  No scissor rectangle is set, as the GL backend only resolves multisampled targets inside the scissor rectangle left at the end of the pass.
+    /// 
+    /// Arguments:
+    /// * pass: &mut wgpu::RenderPass - The render pass to limit.
+    /// * engine: &RenderEngine - The render engine whose surface is rendered too if `target_size` is not set.
+    pub fn set_viewport(&self, pass: &mut wgpu::RenderPass, engine: &RenderEngine) {
+        let (x, y, width, height) = self.viewport.pixels(self.target_size(engine));
+        pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+    }
+}
+
+/// The point in view space at the given distance in front of the camera that projects onto the given normalized device coordinates.  Two points along the line of points that project onto the coordinates are used so that any projection works.
+fn view_point(inverse_projection: &Matrix4<f32>, ndc: Vector2<f32>, distance: f32) -> Vector3<f32> {
+    let unproject = |depth: f32| { let point = inverse_projection * Vector4::new(ndc.x, ndc.y, depth, 1.0); point.truncate() / point.w };
+    let (start, end) = (unproject(0.25), unproject(0.75));
+    start + (end - start) * ((-distance - start.z) / (end.z - start.z))
 }
 
 /// The camera uniform, this is the rust representation of the camera data is passed to the shaders.
@@ -136,3 +417,108 @@ impl CameraUniform {
         Self { view_position: [0.0; 4], view_proj: cgmath::Matrix4::identity().into() }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Vector2<f32> = Vector2::new(800.0, 600.0);
+
+    /// Points in front of the camera from `cameras`, some off the center of its view.
+    const POINTS: [[f32; 3]; 4] = [[0.0, 0.0, 0.0], [0.5, -0.3, 0.2], [-1.0, 0.5, -2.0], [0.8, 0.9, 1.5]];
+
+    /// A camera for every projection with every viewport, looking at the origin from off its axes.
+    fn cameras(engine: &RenderEngine) -> Vec<Camera> {
+        let projections = [
+            Projection::Perspective { fovy: 60.0, znear: 0.1, zfar: 100.0 },
+            Projection::Orthographic { size: 6.0, znear: -10.0, zfar: 20.0 },
+            Projection::InfinitePerspective { fovy: 60.0, znear: 0.1 }
+        ];
+        let viewports = [Viewport::FULL, Viewport::new(0.5, 0.25, 0.4, 0.6)];
+        projections.iter().flat_map(|projection| viewports.iter().map(|viewport| {
+            let mut camera = Camera::new(engine, *projection);
+            camera.position = Vector3::new(2.0, 3.0, 6.0);
+            camera.viewport = *viewport;
+            camera.look_at(Vector3::new(0.0, 0.0, 0.0), Vector3::unit_y());
+            camera
+        })).collect()
+    }
+
+    fn engine() -> RenderEngine { pollster::block_on(RenderEngine::new_headless(64, 64, wgpu::TextureFormat::Rgba8UnormSrgb)) }
+
+    fn assert_close(actual: Vector3<f32>, expected: Vector3<f32>, camera: &Camera) {
+        assert!((actual - expected).magnitude() < 1e-3, "expected {:?}, got {:?} with {:?} in {:?}", expected, actual, camera.projection, camera.viewport);
+    }
+
+    #[test]
+    fn screen_to_ray_hits_projected_point() {
+        let engine = engine();
+        for camera in cameras(&engine) {
+            for point in POINTS.map(Vector3::from) {
+                let screen = camera.world_to_screen(point, SCREEN).unwrap();
+                let ray = camera.screen_to_ray(screen.truncate(), SCREEN);
+                let distance = (point - ray.origin).dot(ray.direction);
+                assert!(distance > 0.0);
+                assert_close(ray.at(distance), point, &camera);
+            }
+        }
+    }
+
+    #[test]
+    fn unproject_returns_projected_point() {
+        let engine = engine();
+        for camera in cameras(&engine) {
+            for point in POINTS.map(Vector3::from) {
+                let screen = camera.world_to_screen(point, SCREEN).unwrap();
+                assert_close(camera.unproject(screen.truncate(), screen.z, SCREEN), point, &camera);
+            }
+        }
+    }
+
+    #[test]
+    fn projected_points_stay_in_viewport() {
+        let engine = engine();
+        for camera in cameras(&engine) {
+            let (x, y, width, height) = camera.viewport.pixels((SCREEN.x as u32, SCREEN.y as u32));
+            for point in POINTS.map(Vector3::from) {
+                let screen = camera.world_to_screen(point, SCREEN).unwrap();
+                assert!(screen.x > x as f32 && screen.x < (x + width) as f32, "{:?} is outside {:?}", screen, camera.viewport);
+                assert!(screen.y > y as f32 && screen.y < (y + height) as f32, "{:?} is outside {:?}", screen, camera.viewport);
+                assert!(screen.z > 0.0 && screen.z < 1.0);
+            }
+
+            // the center of the view looks at the origin
+            let center = Vector2::new(x as f32 + width as f32 * 0.5, y as f32 + height as f32 * 0.5);
+            let ray = camera.screen_to_ray(center, SCREEN);
+            assert_close(ray.direction, camera.forward(), &camera);
+        }
+    }
+
+    #[test]
+    fn reverse_z_depth_decreases_with_distance() {
+        let engine = engine();
+        let camera = Camera::new(&engine, Projection::InfinitePerspective { fovy: 60.0, znear: 0.1 });
+        let depth = |distance: f32| camera.world_to_screen(Vector3::new(0.0, 0.0, -distance), SCREEN).unwrap().z;
+        assert!((depth(0.1) - 1.0).abs() < 1e-4);
+        assert!(depth(1.0) > depth(10.0) && depth(10.0) > depth(1000.0) && depth(1000.0) > 0.0);
+        assert!(camera.world_to_screen(Vector3::new(0.0, 0.0, 1.0), SCREEN).is_none());
+    }
+
+    #[test]
+    fn sort_by_order_keeps_ties_in_place() {
+        let engine = engine();
+        let mut cameras: Vec<Camera> = [2, 0, 1, 0].iter().enumerate().map(|(index, order)| {
+            let mut camera = Camera::new(&engine, Projection::Orthographic { size: 1.0, znear: 0.0, zfar: 1.0 });
+            camera.order = *order;
+            camera.position.x = index as f32;
+            camera
+        }).collect();
+
+        Camera::sort_by_order(&mut cameras);
+        assert_eq!(cameras.iter().map(|camera| (camera.order, camera.position.x as usize)).collect::<Vec<_>>(), vec![(0, 1), (0, 3), (1, 2), (2, 0)]);
+
+        let mut references: Vec<&Camera> = cameras.iter().rev().collect();
+        Camera::sort_by_order(&mut references);
+        assert_eq!(references.iter().map(|camera| camera.position.x as usize).collect::<Vec<_>>(), vec![3, 1, 2, 0]);
+    }
+}
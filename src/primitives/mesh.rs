@@ -1,24 +1,37 @@
 use wgpu::util::DeviceExt;
 
-use crate::primitives::vertices::Vertex;
+use crate::math::bounds::BoundingBox;
+
+use super::vertices::MeshVertex;
+
+/// An integer type a mesh can be indexed with, `u16` for meshes with up to 65536 vertices and `u32` for larger ones.
+pub trait MeshIndex: bytemuck::Pod {
+    /// The index format WGPU draws with for this type.
+    const FORMAT: wgpu::IndexFormat;
+}
+
+impl MeshIndex for u16 { const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint16; }
+impl MeshIndex for u32 { const FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32; }
 
 /// A simple struct that contains the information of mesh in a way that can be used by WGPU.
 #[derive(Debug)]
 pub struct Mesh {
     pub(crate) vertex_buf: wgpu::Buffer,
     pub(crate) index_buf: wgpu::Buffer,
+    pub(crate) index_format: wgpu::IndexFormat,
     pub(crate) num_indices: u32,
-    pub(crate) num_vertices: u32
+    pub(crate) num_vertices: u32,
+    pub(crate) bounds: Option<BoundingBox>
 }
 
 impl Mesh {
-    /// Create a new mesh from a WGPU device with vertices and indices arrays.
+    /// Create a new mesh from a WGPU device with vertices and indices arrays.  The mesh does not know where its vertices are, so it has no bounds and is never culled, use `from_vertices` for meshes that should be.
     /// 
     /// Arguments:
     /// * device: &wgpu::Device - The WGPU device to be used to create the buffers for this mesh.
-    /// * vertices: &[Vertex] - The array of vertices for this mesh.
-    /// * indices: &[u16] - The indices of this mesh.
-    pub fn from_raw(device: &wgpu::Device, vertices: &[Vertex], indices: &[u16]) -> Self {
+    /// * vertices: &[V] - The array of vertices for this mesh.
+    /// * indices: &[I] - The indices of this mesh, `u16` or `u32`.
+    pub fn from_raw<V: bytemuck::Pod, I: MeshIndex>(device: &wgpu::Device, vertices: &[V], indices: &[I]) -> Self {
         Self {
             vertex_buf: device.create_buffer_init(
                 &wgpu::util::BufferInitDescriptor {
@@ -34,11 +47,48 @@ impl Mesh {
                     usage: wgpu::BufferUsages::INDEX
                 }
             ),
+            index_format: I::FORMAT,
             num_indices: indices.len() as u32,
-            num_vertices: vertices.len() as u32
+            num_vertices: vertices.len() as u32,
+            bounds: None
         }
     }
 
+    /// Create a new mesh from vertices with positions, calculating the box bounding them so the mesh can be culled.
+    /// 
+    /// Arguments:
+    /// * device: &wgpu::Device - The WGPU device to be used to create the buffers for this mesh.
+    /// * vertices: &[V] - The array of vertices for this mesh, normally `Vertex` or `SkinnedVertex`.
+    /// * indices: &[I] - The indices of this mesh, `u16` or `u32`.
+    pub fn from_vertices<V: MeshVertex, I: MeshIndex>(device: &wgpu::Device, vertices: &[V], indices: &[I]) -> Self {
+        Self {
+            bounds: Some(BoundingBox::from_points(vertices.iter().map(|vertex| vertex.position().into()))),
+            ..Self::from_raw(device, vertices, indices)
+        }
+    }
+
+    /// Create a new mesh from vertices with positions and 32 bit indices, like those loaded from a model.  The indices are stored as `u16` when every index fits, halving their size, and as `u32` otherwise.
+    /// 
+    /// Arguments:
+    /// * device: &wgpu::Device - The WGPU device to be used to create the buffers for this mesh.
+    /// * vertices: &[V] - The array of vertices for this mesh, normally `Vertex` or `SkinnedVertex`.
+    /// * indices: &[u32] - The indices of this mesh.
+    pub fn from_vertices_u32<V: MeshVertex>(device: &wgpu::Device, vertices: &[V], indices: &[u32]) -> Self {
+        if indices.iter().any(|index| *index > u16::MAX as u32) { return Self::from_vertices(device, vertices, indices) }
+
+        let indices: Vec<u16> = indices.iter().map(|index| *index as u16).collect();
+        Self::from_vertices(device, vertices, &indices)
+    }
+
+    /// Returns an option containing the box bounding the vertices of this mesh, before they are moved by instances, skins or morph targets, or none if the mesh was created with `from_raw`.
+    pub fn bounds(&self) -> Option<BoundingBox> { self.bounds }
+
+    /// The format of the indices of this mesh.
+    pub fn index_format(&self) -> wgpu::IndexFormat { self.index_format }
+
+    /// The number of indices drawn by this mesh.
+    pub fn num_indices(&self) -> u32 { self.num_indices }
+
     /// Draws a mesh to this render pass.
     /// 
     /// Arguments:
@@ -54,10 +104,29 @@ impl Mesh {
     ) {
         pass.set_vertex_buffer(0, self.vertex_buf.slice(..));
         pass.set_vertex_buffer(1, instance_buffer.slice(..));
-        pass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint16);
+        pass.set_index_buffer(self.index_buf.slice(..), self.index_format);
         pass.draw_indexed(0..self.num_indices, 0, 0..instance_count);
     }
 
+    /// Draws a mesh to this render pass with the number of instances read from an indirect buffer, like the buffer written by an `InstanceCuller`.
+    /// 
+    /// Arguments:
+    /// * self: &self - The mesh to be rendered
+    /// * pass: &mut wgpu::RenderPass - The render pass to render too.
+    /// * instance_buf: &wgpu::Buffer - The instances buffer to draw the mesh with.
+    /// * indirect_buffer: &wgpu::Buffer - A buffer containing a `wgpu::util::DrawIndexedIndirectArgs` for this mesh.
+    pub fn draw_indirect<'rpass>(
+        &'rpass self,
+        pass: &mut wgpu::RenderPass<'rpass>,
+        instance_buffer: &'rpass wgpu::Buffer,
+        indirect_buffer: &'rpass wgpu::Buffer
+    ) {
+        pass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        pass.set_index_buffer(self.index_buf.slice(..), self.index_format);
+        pass.draw_indexed_indirect(indirect_buffer, 0);
+    }
+
     /// Draws a mesh to this render pass only using its vertices buffer.
     /// 
     /// Arguments:
@@ -73,7 +142,7 @@ impl Mesh {
     ) {
         pass.set_vertex_buffer(0, self.vertex_buf.slice(..));
         pass.set_vertex_buffer(1, instance_buffer.slice(..));
-        pass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint16);
+        pass.set_index_buffer(self.index_buf.slice(..), self.index_format);
         pass.draw(0 .. self.num_vertices, 0..instance_count);
     }
 }
@@ -10,31 +10,56 @@ impl DepthTexture {
     /// The texture format to be used for depth textures.
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
+    /// The texture format to be used for depth textures with a stencil, add a render graph texture with this format for passes using `PipelineBuilder::with_stencil`.
+    pub const DEPTH_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
     /// Create a new depth texture for WGPU.
     /// 
     /// Arguments:
     /// * config: &wgpu::SurfaceConfiguration - A WGPU surface configuration to be used to create the depth texture.
     /// * device: &wgpu::Device - A WGPU device to be used to create the depth texture.
     /// * label: &str - The label for the depth texture.
+    /// * sample_count: u32 - The number of samples per pixel, this must match the color targets it is used with.  Multisampled depth textures cannot be sampled.
     /// 
     /// Returns the created depth texture.
     pub fn new(
         config: &wgpu::SurfaceConfiguration,
         device: &wgpu::Device, 
-        label: &str
+        label: &str,
+        sample_count: u32
+    ) -> Self {
+        Self::with_size(device, label, config.width, config.height, sample_count)
+    }
+
+    /// Create a new depth texture for WGPU with the given size, for render targets other than the surface.
+    /// 
+    /// Arguments:
+    /// * device: &wgpu::Device - A WGPU device to be used to create the depth texture.
+    /// * label: &str - The label for the depth texture.
+    /// * width: u32 - The width of the depth texture.
+    /// * height: u32 - The height of the depth texture.
+    /// * sample_count: u32 - The number of samples per pixel, this must match the color targets it is used with.
+    /// 
+    /// Returns the created depth texture.
+    pub fn with_size(
+        device: &wgpu::Device, 
+        label: &str,
+        width: u32,
+        height: u32,
+        sample_count: u32
     ) -> Self {
         // create size and description for depth texture
         let size = wgpu::Extent3d {
-            width: config.width,
-            height: config.height,
+            width,
+            height,
             depth_or_array_layers: 1
         };
         let desc = wgpu::TextureDescriptor {
             label: Some(label),
-            size, mip_level_count: 1, sample_count: 1,
+            size, mip_level_count: 1, sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: Self::DEPTH_FORMAT,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            usage: if sample_count > 1 { wgpu::TextureUsages::RENDER_ATTACHMENT } else { wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING },
             view_formats: &[]
         };
 
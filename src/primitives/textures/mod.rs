@@ -2,6 +2,7 @@ use image::GenericImageView;
 use anyhow::*;
 
 pub mod depth_textures;
+pub mod render_target;
 
 /// A representation of a WGPU texture, along with its bind group, view, and sampler for shaders.
 #[derive(Debug)]
@@ -55,6 +56,27 @@ impl Texture {
         Self::from_image(device, queue, &img, Some(label))
     }
 
+    /// Create a new texture from the given bytes with the given format.  Use `Rgba8UnormSrgb` for colors and `Rgba8Unorm` for data like normal maps.
+    /// 
+    /// Arguments:
+    /// * device: &wgpu::Device - A wgpu device used to create the texture.
+    /// * queue: &wgpu::Queue - A wgpu queue that will be used to load the texture to the GPU.
+    /// * bytes: &[u8] - The bytes of the image.
+    /// * label: &str - A label for the texture.
+    /// * format: wgpu::TextureFormat - The format of the texture, must be a 4 byte per pixel rgba format.
+    /// 
+    /// Returns a result that will contain the texture if it was loaded properly, otherwise, an error will be thrown.
+    pub fn from_bytes_with_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8], 
+        label: &str,
+        format: wgpu::TextureFormat
+    ) -> Result<Self> {
+        let img = image::load_from_memory(bytes)?;
+        Self::from_raw_with_format(device, queue, &img.to_rgba8(), img.dimensions(), format, Some(label))
+    }
+
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
@@ -72,6 +94,17 @@ impl Texture {
         rgba: &[u8],
         dimensions: (u32, u32),
         label: Option<&str>
+    ) -> Result<Self> {
+        Self::from_raw_with_format(device, queue, rgba, dimensions, wgpu::TextureFormat::Rgba8UnormSrgb, label)
+    }
+
+    pub fn from_raw_with_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rgba: &[u8],
+        dimensions: (u32, u32),
+        format: wgpu::TextureFormat,
+        label: Option<&str>
     ) -> Result<Self> {
         // create texture
         let size = wgpu::Extent3d {
@@ -86,7 +119,7 @@ impl Texture {
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                format,
                 usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
                 view_formats: &[],
             }
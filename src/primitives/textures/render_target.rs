@@ -0,0 +1,180 @@
+use std::marker::PhantomData;
+
+use anyhow::*;
+
+use crate::{render::{graph::{self, GraphTextureDescriptor, TextureSize}, render_engine::RenderEngine}, utils::resources::Handle};
+
+use super::{depth_textures::DepthTexture, Texture};
+
+/// A texture that passes can render too and that can then be sampled like any other texture, for example, for security monitors, mirrors, minimaps or item previews in the UI.
+///
+/// The color texture is kept in the render engine's texture cache under the name of the target, so its handle can be bound with `Texture::BIND_LAYOUT`, used in a `MaterialBuilder` or shown with `UIElement::image`.  The target owns its depth texture, and when the render engine uses MSAA, a multisampled companion texture that is resolved into the color texture at the end of each pass.
+///
+/// Targets are rendered too from encoder passes of the render graph, see `begin_pass`.  Give those passes `PREPARE_PASS_ORDER` so they run before the passes sampling the target.
+#[derive(Debug)]
+pub struct RenderTarget {
+    name: String,
+    texture: Handle<Texture>,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    has_depth: bool,
+    sample_count: u32,
+    multisampled_view: Option<wgpu::TextureView>,
+    depth_texture: Option<DepthTexture>,
+
+    /// The color the target is cleared to at the start of each pass.
+    pub clear_color: wgpu::Color
+}
+
+impl RenderTarget {
+    /// Creates a new render target in the scene format of the render engine, so it can be rendered too with the same pipelines as the scene.
+    ///
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine whose texture cache the color texture is stored in.
+    /// * name: impl Into<String> - The path ID of the color texture in the texture cache, replacing any texture with the same path ID.
+    /// * width: u32 - The width of the target in pixels.
+    /// * height: u32 - The height of the target in pixels.
+    /// * depth: bool - True if the target has a depth texture, needed to render 3D scenes.
+    ///
+    /// Returns a result containing the render target, or an error if the scene format cannot be sampled with filtering.
+    pub fn new(engine: &mut RenderEngine, name: impl Into<String>, width: u32, height: u32, depth: bool) -> Result<Self> {
+        let name = name.into();
+        let format = engine.scene_format();
+        let texture = create_color_texture(engine, &name, format, width, height)?;
+        let mut target = Self {
+            name, texture, format, width, height,
+            has_depth: depth,
+            sample_count: engine.sample_count(),
+            multisampled_view: None,
+            depth_texture: None,
+            clear_color: wgpu::Color::BLACK
+        };
+        target.create_attachments(engine);
+        Ok(target)
+    }
+
+    /// A handle to the color texture in the render engine's texture cache.
+    pub fn handle(&self) -> Handle<Texture> { Handle { hash: self.texture.hash, data: PhantomData } }
+
+    /// The name of the target, also the path ID of its color texture.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// The width and height of the target in pixels.
+    pub fn size(&self) -> (u32, u32) { (self.width, self.height) }
+
+    /// The width of the target divided by its height, for the aspect ratio of cameras rendering into it.
+    pub fn aspect(&self) -> f32 { self.width as f32 / self.height as f32 }
+
+    /// The color format of the target.
+    pub fn format(&self) -> wgpu::TextureFormat { self.format }
+
+    /// The depth texture of the target, if it has one.
+    pub fn depth_texture(&self) -> Option<&DepthTexture> { self.depth_texture.as_ref() }
+
+    /// Resizes the target, replacing its color texture in the texture cache.  UI images look the texture up each time they are drawn, but materials using it must be rebound with `Material::rebind`, see `Material::needs_rebind`.
+    ///
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine the target was created with.
+    /// * width: u32 - The new width of the target in pixels.
+    /// * height: u32 - The new height of the target in pixels.
+    ///
+    /// Returns a result that is an error if the color texture could not be created.
+    pub fn resize(&mut self, engine: &mut RenderEngine, width: u32, height: u32) -> Result<()> {
+        if self.width == width && self.height == height { return Ok(()) }
+
+        self.texture = create_color_texture(engine, &self.name, self.format, width, height)?;
+        self.width = width;
+        self.height = height;
+        self.create_attachments(engine);
+        Ok(())
+    }
+
+    /// Begins a render pass drawing into this target, clearing its color to `clear_color` and its depth following the render engine's depth convention.  The attachments are recreated first if the render engine's sample count changed, so the pass always matches the engine's pipelines.
+    ///
+    /// Arguments:
+    /// * engine: &'a RenderEngine - The render engine the target was created with.
+    /// * encoder: &'a mut wgpu::CommandEncoder - The frame's command encoder.
+    ///
+    /// Returns the render pass.
+    pub fn begin_pass<'a>(&'a mut self, engine: &'a RenderEngine, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::RenderPass<'a> {
+        if self.sample_count != engine.sample_count() { self.create_attachments(engine); }
+
+        let resolved = &engine.texture(&self.texture).view;
+        let (view, resolve_target) = match &self.multisampled_view {
+            Some(multisampled) => (multisampled, Some(resolved)),
+            None => (resolved, None)
+        };
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&self.name),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view, resolve_target,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(self.clear_color), store: wgpu::StoreOp::Store }
+            })],
+            depth_stencil_attachment: self.depth_texture.as_ref().map(|depth| wgpu::RenderPassDepthStencilAttachment {
+                view: &depth.view,
+                depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(engine.render_graph.clear_depth()), store: wgpu::StoreOp::Store }),
+                stencil_ops: None
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None
+        })
+    }
+
+    /// Creates the multisampled companion and depth textures with the render engine's sample count.
+    fn create_attachments(&mut self, engine: &RenderEngine) {
+        self.sample_count = engine.sample_count();
+        let size = wgpu::Extent3d { width: self.width.max(1), height: self.height.max(1), depth_or_array_layers: 1 };
+        self.multisampled_view = (self.sample_count > 1).then(|| graph::create_multisampled_view(&engine.device, &self.name, self.format, size, self.sample_count));
+        self.depth_texture = self.has_depth.then(|| DepthTexture::with_size(&engine.device, &format!("{}_depth", self.name), size.width, size.height, self.sample_count));
+    }
+}
+
+/// Creates the color texture of a render target and stores it in the texture cache.
+fn create_color_texture(engine: &mut RenderEngine, name: &str, format: wgpu::TextureFormat, width: u32, height: u32) -> Result<Handle<Texture>> {
+    let descriptor = GraphTextureDescriptor { size: TextureSize::Fixed(width, height), usage: wgpu::TextureUsages::COPY_SRC, ..GraphTextureDescriptor::new(format) };
+    let created = graph::create_texture(&engine.device, name, &descriptor, (width, height), 1);
+    let bind_group = created.bind_group.with_context(|| format!("Render target {:?} cannot use the format {:?}, it cannot be sampled with filtering.", name, format))?;
+    Ok(engine.insert_texture(name, Texture { texture: created.texture, view: created.view, sampler: created.sampler, bind_group }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::material::{MaterialBuilder, MaterialTexture};
+
+    fn engine() -> RenderEngine { pollster::block_on(RenderEngine::new_headless(64, 64, wgpu::TextureFormat::Rgba8UnormSrgb)) }
+
+    #[test]
+    fn resize_replaces_cached_texture() {
+        let mut engine = engine();
+        let mut target = RenderTarget::new(&mut engine, "test_target", 16, 8, true).unwrap();
+        let old_view = engine.texture(&target.handle()).view.global_id();
+
+        target.resize(&mut engine, 32, 24).unwrap();
+        assert_eq!(target.size(), (32, 24));
+        assert_eq!(engine.texture(&target.handle()).texture.size(), wgpu::Extent3d { width: 32, height: 24, depth_or_array_layers: 1 });
+        assert_ne!(engine.texture(&target.handle()).view.global_id(), old_view);
+        assert_eq!(target.depth_texture().unwrap().texture.size().width, 32);
+    }
+
+    #[test]
+    fn materials_are_rebound_after_resize() {
+        let mut engine = engine();
+        let mut target = RenderTarget::new(&mut engine, "test_material_target", 16, 16, true).unwrap();
+        let mut material = MaterialBuilder { albedo_texture: Some(MaterialTexture::Cached(target.handle())), ..Default::default() }.build(&engine);
+        let owned = MaterialBuilder::default().build(&engine);
+        assert!(!material.needs_rebind(&engine));
+
+        // resizing to the same size keeps the texture
+        target.resize(&mut engine, 16, 16).unwrap();
+        assert!(!material.needs_rebind(&engine));
+
+        target.resize(&mut engine, 48, 32).unwrap();
+        assert!(material.needs_rebind(&engine));
+        assert!(!owned.needs_rebind(&engine));
+
+        material.rebind(&engine);
+        assert!(!material.needs_rebind(&engine));
+    }
+}
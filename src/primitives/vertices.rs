@@ -1,3 +1,11 @@
+use cgmath::{InnerSpace, Vector2, Vector3, Zero};
+
+/// A vertex with a position, so meshes can calculate the box bounding their vertices.
+pub trait MeshVertex: bytemuck::Pod {
+    /// The position of this vertex.
+    fn position(&self) -> [f32; 3];
+}
+
 /// A rust representation of what a Vertex that is passed to mesh and onto WGPU and its shaders.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -7,6 +15,10 @@ pub struct Vertex {
     pub normal: [f32; 3]
 }
 
+impl MeshVertex for Vertex {
+    fn position(&self) -> [f32; 3] { self.position }
+}
+
 impl Vertex {
     /// This is the vertex attributes used by vertices.  This is here to promote consistency across implementations.
     const ATTRIBS: [wgpu::VertexAttribute; 3] =
@@ -21,3 +33,108 @@ impl Vertex {
         }
     }
 }
+
+/// A rust representation of a vertex of a model, with a tangent for normal mapping.  The w component of the tangent is 1 or -1 and gives the direction of the bitangent.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ModelVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+    pub tangent: [f32; 4]
+}
+
+impl MeshVertex for ModelVertex {
+    fn position(&self) -> [f32; 3] { self.position }
+}
+
+impl ModelVertex {
+    /// This is the vertex attributes used by model vertices, the first 3 match `Vertex` and the tangent uses location 12 as locations 3 and 4 are used by skinning and 5 to 11 by instances.  This is here to promote consistency across implementations.
+    const ATTRIBS: [wgpu::VertexAttribute; 4] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x3, 12 => Float32x4];
+
+    // Creates a new vertex buffer layout that should be given to pipelines so they know how to use model vertices properly.  This is here to promote consistency across implementations.
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ModelVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS
+        }
+    }
+
+    /// Generates the tangents of the given triangles from their texture coordinates, for meshes that were not given tangents.
+    /// 
+    /// Arguments:
+    /// * vertices: &mut [ModelVertex] - The vertices to generate tangents for.
+    /// * indices: &[u32] - The indices of the triangles of the vertices.
+    pub fn generate_tangents(vertices: &mut [ModelVertex], indices: &[u32]) {
+        let mut tangents = vec![Vector3::<f32>::zero(); vertices.len()];
+        let mut bitangents = vec![Vector3::<f32>::zero(); vertices.len()];
+
+        // accumulate the tangent and bitangent of each triangle onto its vertices
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+            if a >= vertices.len() || b >= vertices.len() || c >= vertices.len() { continue }
+            let (va, vb, vc) = (&vertices[a], &vertices[b], &vertices[c]);
+
+            let edge_1 = Vector3::from(vb.position) - Vector3::from(va.position);
+            let edge_2 = Vector3::from(vc.position) - Vector3::from(va.position);
+            let uv_1 = Vector2::from(vb.tex_coords) - Vector2::from(va.tex_coords);
+            let uv_2 = Vector2::from(vc.tex_coords) - Vector2::from(va.tex_coords);
+            let determinant = uv_1.x * uv_2.y - uv_2.x * uv_1.y;
+            if determinant.abs() < f32::EPSILON { continue }
+
+            let tangent = (edge_1 * uv_2.y - edge_2 * uv_1.y) / determinant;
+            let bitangent = (edge_2 * uv_1.x - edge_1 * uv_2.x) / determinant;
+            for index in [a, b, c] {
+                tangents[index] += tangent;
+                bitangents[index] += bitangent;
+            }
+        }
+
+        // make each tangent perpendicular to its normal and find the direction of its bitangent
+        for (vertex, (tangent, bitangent)) in vertices.iter_mut().zip(tangents.iter().zip(bitangents.iter())) {
+            let normal = Vector3::from(vertex.normal);
+            let mut orthogonal = tangent - normal * normal.dot(*tangent);
+            if orthogonal.magnitude2() < f32::EPSILON {
+                // no usable texture coordinates, pick any direction perpendicular to the normal
+                let axis = if normal.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+                orthogonal = axis - normal * normal.dot(axis);
+            }
+            let orthogonal = orthogonal.normalize();
+            let handedness = if normal.cross(orthogonal).dot(*bitangent) < 0.0 { -1.0 } else { 1.0 };
+            vertex.tangent = [orthogonal.x, orthogonal.y, orthogonal.z, handedness];
+        }
+    }
+}
+
+/// A rust representation of a vertex that is deformed by up to 4 joints of a skin.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkinnedVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub normal: [f32; 3],
+    pub tangent: [f32; 4],
+    pub joints: [u32; 4],
+    pub weights: [f32; 4]
+}
+
+impl MeshVertex for SkinnedVertex {
+    fn position(&self) -> [f32; 3] { self.position }
+}
+
+impl SkinnedVertex {
+    /// This is the vertex attributes used by skinned vertices, the first 4 match `ModelVertex`.  This is here to promote consistency across implementations.
+    const ATTRIBS: [wgpu::VertexAttribute; 6] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x3, 12 => Float32x4, 3 => Uint32x4, 4 => Float32x4];
+
+    // Creates a new vertex buffer layout that should be given to pipelines so they know how to use skinned vertices properly.  This is here to promote consistency across implementations.
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SkinnedVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS
+        }
+    }
+}
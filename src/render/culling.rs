@@ -0,0 +1,440 @@
+use std::marker::PhantomData;
+
+use cgmath::Matrix4;
+use wgpu::util::{DeviceExt, DrawIndexedIndirectArgs};
+
+use crate::{create_compute_pipeline, log, math::bounds::{BoundingBox, Frustum}, primitives::{cameras::Camera, mesh::Mesh, transforms::TransformRaw}, render::render_engine::RenderEngine, utils::resources::Handle};
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/culling.wgsl")]
+mod culling_shader {}
+
+/// The number of instances tested by a single compute workgroup, this must match the workgroup size in `culling.wgsl`.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// The rust representation of the culling information passed to the compute shader.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CullingUniform {
+    planes: [[f32; 4]; 6],
+
+    // FORMAT: center of the mesh bounds, unused
+    center: [f32; 4],
+
+    // FORMAT: half the size of the mesh bounds, unused
+    extents: [f32; 4],
+
+    // FORMAT: number of instances, unused, unused, unused
+    counts: [u32; 4]
+}
+
+/// Where an `InstanceCuller` tests its instances against the camera's frustum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullingMode {
+    /// Instances are culled and compacted by a compute shader, and drawn with the number of visible instances it counted.
+    Gpu,
+
+    /// Instances are culled on the CPU and the visible ones are uploaded each time they are culled.  This works everywhere, including WebGL.
+    Cpu
+}
+
+impl CullingMode {
+    /// Returns `Gpu` if the given engine supports GPU culling, and `Cpu` otherwise.
+    pub fn best(engine: &RenderEngine) -> Self {
+        if InstanceCuller::is_supported(engine) { Self::Gpu } else { Self::Cpu }
+    }
+}
+
+/// The GPU resources used when culling on the GPU.
+#[derive(Debug)]
+struct GpuCulling {
+    uniform_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    indirect_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pending: bool
+}
+
+/// Culls the instances of a mesh that are outside a camera's view, so large instanced scenes only draw what can be seen.  Each instance moves the bounds of the mesh with its model matrix, and the instances whose bounds are outside the camera's frustum are skipped.
+///
+/// Call `cull` after the camera moves and `compute` from `EngineComponent::compute`, then `draw` the mesh.  Until the instances are culled, every instance is drawn.  Meshes created without bounds, with `Mesh::from_raw`, are never culled.
+///
+/// The visible instances are compacted, so `instance_index` in the vertex shader is an instance's position among the visible instances and not among all instances.  Because of this, the culled instances can only be drawn with `draw`, and glTF models whose morph target weights are picked per instance are never culled.
+#[derive(Debug)]
+pub struct InstanceCuller {
+    mode: CullingMode,
+    mesh: Handle<Mesh>,
+    bounds: Option<BoundingBox>,
+    index_count: u32,
+    instances: Vec<TransformRaw>,
+    capacity: usize,
+    visible_buffer: wgpu::Buffer,
+    visible_count: u32,
+    gpu: Option<GpuCulling>
+}
+
+impl InstanceCuller {
+    /// The name of the compute pipeline that culls the instances.
+    pub const PIPELINE: &'static str = "forte.culling";
+
+    /// The name of the compute pipeline that resets the number of visible instances before they are culled.
+    pub const RESET_PIPELINE: &'static str = "forte.culling.reset";
+
+    /// The bind group layout of the compute shader that culls the instances.
+    pub const COMPUTE_BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("culling_bind_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }
+        ]
+    };
+
+    /// Returns true if the given engine supports culling on the GPU, it needs compute shaders and indirect draws which WebGL does not have.
+    pub fn is_supported(engine: &RenderEngine) -> bool {
+        engine.downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS | wgpu::DownlevelFlags::INDIRECT_EXECUTION)
+    }
+
+    /// Creates a new culler for the instances of the given mesh.
+    ///
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine to create the culler with.
+    /// * mesh: &Handle<Mesh> - The mesh the instances draw, its bounds are tested against the camera.
+    /// * instances: &[TransformRaw] - The instances of the mesh.
+    /// * mode: CullingMode - Where the instances are culled, use `CullingMode::best` to cull on the GPU when it is supported.  If GPU culling is not supported, the instances are culled on the CPU instead.
+    pub fn new(engine: &mut RenderEngine, mesh: &Handle<Mesh>, instances: &[TransformRaw], mode: CullingMode) -> Self {
+        let mode = if mode == CullingMode::Gpu && !Self::is_supported(engine) {
+            log!("GPU culling is not supported, culling instances on the CPU instead.");
+            CullingMode::Cpu
+        } else { mode };
+
+        // make sure compute pipelines exist
+        if mode == CullingMode::Gpu {
+            for (name, entry_point) in [(Self::RESET_PIPELINE, "cs_reset"), (Self::PIPELINE, "cs_main")] {
+                create_compute_pipeline! {
+                    NAME => name,
+                    ENGINE => engine,
+                    SHADER => culling_shader::SOURCE,
+                    BIND_GROUPS => [Self::COMPUTE_BIND_LAYOUT],
+                    ENTRY_POINT => entry_point
+                }
+            }
+        }
+
+        let mut culler = Self {
+            mode,
+            mesh: Handle { hash: mesh.hash, data: PhantomData },
+            bounds: engine.mesh(mesh).bounds(),
+            index_count: engine.mesh(mesh).num_indices(),
+            instances: Vec::new(),
+            capacity: 0,
+            visible_buffer: Self::create_instance_buffer(engine, "culling_visible_buffer", 0, mode),
+            visible_count: 0,
+            gpu: None
+        };
+        culler.set_instances(engine, instances);
+        culler
+    }
+
+    /// Replaces the instances of the mesh, every instance is drawn until they are culled again.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine the culler was created with.
+    /// * instances: &[TransformRaw] - The new instances of the mesh.
+    pub fn set_instances(&mut self, engine: &RenderEngine, instances: &[TransformRaw]) {
+        // grow the buffers if needed
+        if instances.len() > self.capacity || self.gpu.is_none() && self.mode == CullingMode::Gpu {
+            self.capacity = instances.len();
+            self.visible_buffer = Self::create_instance_buffer(engine, "culling_visible_buffer", instances.len(), self.mode);
+            if self.mode == CullingMode::Gpu { self.gpu = Some(self.create_gpu_culling(engine, instances.len())); }
+        }
+        self.instances.clear();
+        self.instances.extend_from_slice(instances);
+
+        // draw every instance until culled
+        self.visible_count = instances.len() as u32;
+        engine.queue.write_buffer(&self.visible_buffer, 0, bytemuck::cast_slice(instances));
+        let args = self.indirect_args(self.visible_count);
+        if let Some(gpu) = &mut self.gpu {
+            engine.queue.write_buffer(&gpu.instance_buffer, 0, bytemuck::cast_slice(instances));
+            engine.queue.write_buffer(&gpu.indirect_buffer, 0, args.as_bytes());
+            gpu.pending = false;
+        }
+    }
+
+    /// Culls the instances against the view of the given camera.  On the CPU, the visible instances are uploaded right away.  On the GPU, they are culled next time `compute` is called.  Nothing is culled if the mesh has no bounds.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine the camera renders with.
+    /// * camera: &Camera - The camera the instances will be viewed from.
+    pub fn cull(&mut self, engine: &RenderEngine, camera: &Camera) {
+        let Some(bounds) = self.bounds else { return };
+        let frustum = Frustum::from_matrix(&camera.build_view_projection_matrix(engine));
+        match &mut self.gpu {
+            // the visible instances are counted from 0 by the reset shader in `compute`, so the previous count is drawn until then
+            Some(gpu) => {
+                let uniform = CullingUniform {
+                    planes: frustum.planes.map(|plane| plane.into()),
+                    center: bounds.center().extend(0.0).into(),
+                    extents: bounds.extents().extend(0.0).into(),
+                    counts: [self.instances.len() as u32, 0, 0, 0]
+                };
+                engine.queue.write_buffer(&gpu.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+                gpu.pending = true;
+            },
+            None => {
+                let visible: Vec<TransformRaw> = self.instances.iter()
+                    .filter(|instance| frustum.intersects_box(&bounds.transformed(&Matrix4::from(instance.model))))
+                    .copied()
+                    .collect();
+                self.visible_count = visible.len() as u32;
+                engine.queue.write_buffer(&self.visible_buffer, 0, bytemuck::cast_slice(&visible));
+            }
+        }
+    }
+
+    /// Records the culling compute shader onto the given pass if the instances were culled on the GPU since the last call.  Call this from `EngineComponent::compute`.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine the culler was created with.
+    /// * pass: &mut wgpu::ComputePass - The frame's compute pass.
+    pub fn compute<'pass>(&'pass mut self, engine: &'pass RenderEngine, pass: &mut wgpu::ComputePass<'pass>) {
+        let Some(gpu) = self.gpu.as_mut().filter(|gpu| gpu.pending) else { return };
+        let (Some(reset), Some(pipeline)) = (engine.compute_pipeline_path(Self::RESET_PIPELINE), engine.compute_pipeline_path(Self::PIPELINE)) else { return };
+        gpu.pending = false;
+
+        // reset the number of visible instances in the same pass, so it is never drawn before the instances are culled
+        reset.bind(pass);
+        pass.set_bind_group(0, &gpu.bind_group, &[]);
+        pass.dispatch_workgroups(1, 1, 1);
+
+        // run one invocation per instance
+        pipeline.bind(pass);
+        pass.set_bind_group(0, &gpu.bind_group, &[]);
+        pass.dispatch_workgroups((self.instances.len() as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// Draws the visible instances of the mesh.
+    ///
+    /// Arguments:
+    /// * pass: &mut wgpu::RenderPass - The render pass to draw too.
+    /// * engine: &RenderEngine - The render engine the mesh belongs too.
+    pub fn draw<'rpass>(&'rpass self, pass: &mut wgpu::RenderPass<'rpass>, engine: &'rpass RenderEngine) {
+        let mesh = engine.mesh(&self.mesh);
+        match &self.gpu {
+            Some(gpu) => mesh.draw_indirect(pass, &self.visible_buffer, &gpu.indirect_buffer),
+            None => mesh.draw(pass, &self.visible_buffer, self.visible_count)
+        }
+    }
+
+    /// Where the instances are culled.
+    pub fn mode(&self) -> CullingMode { self.mode }
+
+    /// The number of instances drawn by `draw` when culling on the CPU.  Returns `None` when culling on the GPU, the visible instances are only counted on the GPU and can be read from `indirect_buffer`.
+    pub fn visible_count(&self) -> Option<u32> { if self.gpu.is_some() { None } else { Some(self.visible_count) } }
+
+    /// The indirect draw arguments used by `draw` when culling on the GPU, a `wgpu::util::DrawIndexedIndirectArgs` whose `instance_count` is the number of visible instances.  Returns `None` when culling on the CPU.
+    pub fn indirect_buffer(&self) -> Option<&wgpu::Buffer> { self.gpu.as_ref().map(|gpu| &gpu.indirect_buffer) }
+
+    /// The indirect draw arguments of the mesh with the given number of instances.
+    fn indirect_args(&self, instance_count: u32) -> DrawIndexedIndirectArgs {
+        DrawIndexedIndirectArgs { index_count: self.index_count, instance_count, first_index: 0, base_vertex: 0, first_instance: 0 }
+    }
+
+    /// Creates a buffer for the given number of instances, the buffer always has room for at least one so it can be bound.
+    fn create_instance_buffer(engine: &RenderEngine, label: &str, count: usize, mode: CullingMode) -> wgpu::Buffer {
+        let storage = if mode == CullingMode::Gpu { wgpu::BufferUsages::STORAGE } else { wgpu::BufferUsages::empty() };
+        engine.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (count.max(1) * std::mem::size_of::<TransformRaw>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC | storage,
+            mapped_at_creation: false
+        })
+    }
+
+    /// Creates the buffers and bind group used to cull the given number of instances on the GPU.
+    fn create_gpu_culling(&self, engine: &RenderEngine, count: usize) -> GpuCulling {
+        let uniform_buffer = engine.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("culling_buffer"),
+            contents: bytemuck::cast_slice(&[<CullingUniform as bytemuck::Zeroable>::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+        });
+        let instance_buffer = Self::create_instance_buffer(engine, "culling_instance_buffer", count, CullingMode::Gpu);
+        let indirect_buffer = engine.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("culling_indirect_buffer"),
+            contents: self.indirect_args(0).as_bytes(),
+            usage: wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC
+        });
+        let bind_group = engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("culling_bind_group"),
+            layout: &engine.device.create_bind_group_layout(&Self::COMPUTE_BIND_LAYOUT),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: uniform_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: instance_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: self.visible_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: indirect_buffer.as_entire_binding() }
+            ]
+        });
+        GpuCulling { uniform_buffer, instance_buffer, indirect_buffer, bind_group, pending: false }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Vector3, Zero};
+
+    use super::*;
+    use crate::{math::transforms::Transform, primitives::{cameras::Projection, vertices::Vertex}};
+
+    fn engine() -> RenderEngine { pollster::block_on(RenderEngine::new_headless(64, 64, wgpu::TextureFormat::Rgba8UnormSrgb)) }
+
+    /// A unit quad centered on the origin.
+    fn quad(engine: &mut RenderEngine) -> Handle<Mesh> {
+        let vertex = |x: f32, y: f32| Vertex { position: [x, y, 0.0], tex_coords: [0.0, 0.0], normal: [0.0, 0.0, 1.0] };
+        engine.create_mesh("culling_test_quad", &[vertex(-0.5, -0.5), vertex(0.5, -0.5), vertex(0.5, 0.5), vertex(-0.5, 0.5)], &[0, 1, 2, 0, 2, 3])
+    }
+
+    /// A camera at the origin looking down -Z.
+    fn camera(engine: &RenderEngine) -> Camera {
+        let mut camera = Camera::new(engine, Projection::Perspective { fovy: 60.0, znear: 0.1, zfar: 100.0 });
+        camera.position = Vector3::zero();
+        camera.look_to(-Vector3::unit_z(), Vector3::unit_y());
+        camera
+    }
+
+    /// Instances at the given positions.
+    fn instances(positions: &[[f32; 3]]) -> Vec<TransformRaw> {
+        positions.iter().map(|position| TransformRaw::from_generic(&Transform { position: (*position).into(), ..Default::default() })).collect()
+    }
+
+    /// Reads the positions of the first `count` instances in the culler's visible buffer.
+    fn visible_positions(engine: &RenderEngine, culler: &InstanceCuller, count: u32) -> Vec<[f32; 3]> {
+        let size = (count as usize * std::mem::size_of::<TransformRaw>()) as u64;
+        let buffer = engine.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false
+        });
+        let mut encoder = engine.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_buffer_to_buffer(&culler.visible_buffer, 0, &buffer, 0, size);
+        engine.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        engine.device.poll(wgpu::Maintain::Wait);
+        let data = slice.get_mapped_range();
+        bytemuck::cast_slice::<u8, TransformRaw>(&data).iter().map(|instance| [instance.model[3][0], instance.model[3][1], instance.model[3][2]]).collect()
+    }
+
+    #[test]
+    fn cpu_culling_compacts_visible_instances_in_order() {
+        let mut engine = engine();
+        let mesh = quad(&mut engine);
+        let positions = [[0.0, 0.0, -5.0], [0.0, 0.0, 5.0], [1.0, 0.5, -10.0], [100.0, 0.0, -5.0], [0.0, 0.0, -200.0], [-1.0, 0.0, -3.0]];
+        let mut culler = InstanceCuller::new(&mut engine, &mesh, &instances(&positions), CullingMode::Cpu);
+        assert_eq!(culler.mode(), CullingMode::Cpu);
+        assert!(culler.indirect_buffer().is_none());
+
+        // every instance is drawn until culled
+        assert_eq!(culler.visible_count(), Some(6));
+
+        culler.cull(&engine, &camera(&engine));
+        assert_eq!(culler.visible_count(), Some(3));
+        assert_eq!(visible_positions(&engine, &culler, 3), vec![positions[0], positions[2], positions[5]]);
+    }
+
+    #[test]
+    fn cpu_culling_keeps_instances_straddling_the_frustum() {
+        let mut engine = engine();
+        let mesh = quad(&mut engine);
+
+        // the left edge of the square view is at x = -tan(30) * 5, the quads are 1 wide so only the first overlaps it
+        let edge = -(30.0f32).to_radians().tan() * 5.0;
+        let positions = [[edge - 0.4, 0.0, -5.0], [edge - 0.6, 0.0, -5.0], [0.0, 0.0, -99.9], [0.0, 0.0, -0.05]];
+        let mut culler = InstanceCuller::new(&mut engine, &mesh, &instances(&positions), CullingMode::Cpu);
+        culler.cull(&engine, &camera(&engine));
+        assert_eq!(culler.visible_count(), Some(2));
+        assert_eq!(visible_positions(&engine, &culler, 2), vec![positions[0], positions[2]]);
+    }
+
+    #[test]
+    fn set_instances_draws_every_instance_until_culled() {
+        let mut engine = engine();
+        let mesh = quad(&mut engine);
+        let camera = camera(&engine);
+        let mut culler = InstanceCuller::new(&mut engine, &mesh, &instances(&[[0.0, 0.0, 5.0]]), CullingMode::Cpu);
+        culler.cull(&engine, &camera);
+        assert_eq!(culler.visible_count(), Some(0));
+
+        // growing the instances recreates the buffers
+        let positions = [[0.0, 0.0, 5.0], [0.0, 0.0, -5.0], [0.0, 0.0, -6.0]];
+        culler.set_instances(&engine, &instances(&positions));
+        assert_eq!(culler.visible_count(), Some(3));
+        assert_eq!(visible_positions(&engine, &culler, 3), positions.to_vec());
+
+        culler.cull(&engine, &camera);
+        assert_eq!(culler.visible_count(), Some(2));
+        assert_eq!(visible_positions(&engine, &culler, 2), positions[1 ..].to_vec());
+
+        // shrinking keeps the buffers
+        culler.set_instances(&engine, &instances(&positions[.. 1]));
+        assert_eq!(culler.visible_count(), Some(1));
+        culler.cull(&engine, &camera);
+        assert_eq!(culler.visible_count(), Some(0));
+    }
+
+    #[test]
+    fn gpu_culling_only_counts_on_the_gpu() {
+        let mut engine = engine();
+        let mesh = quad(&mut engine);
+        let culler = InstanceCuller::new(&mut engine, &mesh, &instances(&[[0.0, 0.0, -5.0]]), CullingMode::Gpu);
+        match culler.mode() {
+            CullingMode::Gpu => {
+                assert!(InstanceCuller::is_supported(&engine));
+                assert_eq!(culler.visible_count(), None);
+                assert!(culler.indirect_buffer().is_some());
+            },
+            CullingMode::Cpu => {
+                assert!(!InstanceCuller::is_supported(&engine));
+                assert_eq!(culler.visible_count(), Some(1));
+            }
+        }
+    }
+}
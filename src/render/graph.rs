@@ -0,0 +1,706 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::*;
+
+use crate::primitives::textures::{depth_textures::DepthTexture, Texture};
+
+use super::render_engine::RenderEngine;
+
+/// The name of the texture being presented this frame, the window's surface texture or a headless engine's texture.
+pub const SURFACE_TEXTURE: &str = "forte.surface";
+
+/// The name of the render engine's depth texture.
+pub const DEPTH_TEXTURE: &str = "forte.depth";
+
+/// The order of passes that prepare data used by other passes, like encoder passes running compute shaders, so that they run before the passes using it.
+pub const PREPARE_PASS_ORDER: i32 = -100;
+
+/// The order of passes that draw the user interface, so that they draw over everything else that writes to the same textures.
+pub const UI_PASS_ORDER: i32 = 100;
+
+/// The size of a texture in a render graph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureSize {
+    /// The same size as the surface, resized with it.
+    Surface,
+
+    /// The size of the surface multiplied by the given scale, resized with it.
+    Scaled(f32),
+
+    /// A fixed width and height.
+    Fixed(u32, u32)
+}
+
+/// A description of a texture owned by a render graph.
+///
+/// Arguments:
+/// * format: wgpu::TextureFormat - The format of the texture.
+/// * size: TextureSize - The size of the texture.
+/// * usage: wgpu::TextureUsages - Any extra usages of the texture, it can always be rendered too and sampled.
+/// * clear_color: wgpu::Color - The color the texture is cleared to by the first pass that writes to it each frame.  Depth textures are cleared to the graph's clear depth, and their stencil to 0 if their format has one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphTextureDescriptor {
+    pub format: wgpu::TextureFormat,
+    pub size: TextureSize,
+    pub usage: wgpu::TextureUsages,
+    pub clear_color: wgpu::Color
+}
+
+impl GraphTextureDescriptor {
+    /// Creates a description of a texture the size of the surface with the given format.
+    ///
+    /// Arguments:
+    /// * format: wgpu::TextureFormat - The format of the texture.
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self { format, size: TextureSize::Surface, usage: wgpu::TextureUsages::empty(), clear_color: wgpu::Color::TRANSPARENT }
+    }
+}
+
+/// A texture allocated by a render graph.  Textures are recreated when the surface resizes or the sample count changes, so do not hold onto bind groups using them for longer than `RenderGraph::generation` stays the same.
+///
+/// When the render engine uses MSAA, passes render color into a multisampled companion texture that is resolved into `texture` at the end of each pass, so `texture` can always be sampled.  Depth textures are multisampled themselves, as depth cannot be resolved, so they cannot be sampled while MSAA is used.
+///
+/// Arguments:
+/// * texture: wgpu::Texture - The WGPU texture.
+/// * view: wgpu::TextureView - A view of the whole texture.
+/// * sampler: wgpu::Sampler - A linear sampler for the texture.
+/// * bind_group: Option<wgpu::BindGroup> - A bind group of the view and sampler using `Texture::BIND_LAYOUT`, none if the format cannot be filtered, like depth formats.
+/// * multisampled_view: Option<wgpu::TextureView> - A view of the multisampled companion texture, none if the texture is rendered too directly.
+#[derive(Debug)]
+pub struct GraphTexture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub bind_group: Option<wgpu::BindGroup>,
+    pub multisampled_view: Option<wgpu::TextureView>
+}
+
+impl GraphTexture {
+    /// The view passes render too and the view it is resolved into, if any.
+    fn attachment(&self) -> (&wgpu::TextureView, Option<&wgpu::TextureView>) {
+        match &self.multisampled_view {
+            Some(multisampled) => (multisampled, Some(&self.view)),
+            None => (&self.view, None)
+        }
+    }
+}
+
+/// A pass in a render graph.  Render passes are given a `wgpu::RenderPass` with their color and depth targets attached, encoder passes are given the frame's `wgpu::CommandEncoder` to record whatever they need, like compute passes or passes into textures outside of the graph.
+///
+/// Passes that read a texture run after every pass that writes it.  Passes that write the same texture run in ascending order, then in the order they were added.
+///
+/// Arguments:
+/// * name: String - The name of the pass, used to find who renders it.
+/// * color: Vec<String> - The names of the textures rendered too as color targets.
+/// * depth: Option<String> - The name of the texture used as the depth target.
+/// * reads: Vec<String> - The names of the textures sampled by this pass.
+/// * writes: Vec<String> - The names of any textures written by this pass other than its targets, used by encoder passes.
+/// * order: i32 - The order of this pass among the passes writing the same textures.
+/// * encoder: bool - True if this pass records itself onto the frame's command encoder instead of being given a render pass.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphPass {
+    pub name: String,
+    pub color: Vec<String>,
+    pub depth: Option<String>,
+    pub reads: Vec<String>,
+    pub writes: Vec<String>,
+    pub order: i32,
+    pub encoder: bool
+}
+
+impl GraphPass {
+    /// Creates a new render pass with no targets.
+    ///
+    /// Arguments:
+    /// * name: impl Into<String> - The name of the pass.
+    pub fn new(name: impl Into<String>) -> Self { Self { name: name.into(), ..Default::default() } }
+
+    /// Creates a new encoder pass that records itself onto the frame's command encoder.
+    ///
+    /// Arguments:
+    /// * name: impl Into<String> - The name of the pass.
+    pub fn encoder(name: impl Into<String>) -> Self { Self { name: name.into(), encoder: true, ..Default::default() } }
+
+    /// Adds a color target to this pass.
+    pub fn with_color(mut self, texture: impl Into<String>) -> Self { self.color.push(texture.into()); self }
+
+    /// Sets the depth target of this pass.
+    pub fn with_depth(mut self, texture: impl Into<String>) -> Self { self.depth = Some(texture.into()); self }
+
+    /// Adds a texture sampled by this pass.
+    pub fn with_read(mut self, texture: impl Into<String>) -> Self { self.reads.push(texture.into()); self }
+
+    /// Adds a texture written by this pass other than its targets.
+    pub fn with_write(mut self, texture: impl Into<String>) -> Self { self.writes.push(texture.into()); self }
+
+    /// Sets the order of this pass among the passes writing the same textures.
+    pub fn with_order(mut self, order: i32) -> Self { self.order = order; self }
+
+    /// All textures written by this pass.
+    fn written(&self) -> impl Iterator<Item = &String> { self.color.iter().chain(self.depth.iter()).chain(self.writes.iter()) }
+}
+
+/// A texture declared in a render graph and its allocation.
+#[derive(Debug)]
+struct GraphTextureSlot {
+    descriptor: GraphTextureDescriptor,
+    texture: Option<GraphTexture>
+}
+
+/// How depth is stored in depth textures, which decides what they are cleared too and how pipelines compare depth.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DepthConvention {
+    /// The near plane has a depth of 0 and the far plane a depth of 1, so depth textures are cleared to 1 and closer fragments have less depth.
+    #[default]
+    Standard,
+
+    /// Reverse-Z, the near plane has a depth of 1 and depth approaches 0 far away, like with `Projection::InfinitePerspective`.  Depth textures are cleared to 0 and closer fragments have greater depth.
+    Reverse
+}
+
+impl DepthConvention {
+    /// The depth of nothing, depth textures are cleared to this.
+    pub fn clear_depth(&self) -> f32 {
+        match self {
+            DepthConvention::Standard => 1.0,
+            DepthConvention::Reverse => 0.0
+        }
+    }
+
+    /// The compare function that lets fragments closer than what is in the depth texture pass.
+    pub fn compare(&self) -> wgpu::CompareFunction {
+        match self {
+            DepthConvention::Standard => wgpu::CompareFunction::Less,
+            DepthConvention::Reverse => wgpu::CompareFunction::Greater
+        }
+    }
+}
+
+/// A pass in execution order, with which of its targets it clears.
+#[derive(Debug)]
+struct CompiledPass {
+    pass: usize,
+    clear_color: Vec<bool>,
+    clear_depth: bool
+}
+
+/// A declarative description of everything rendered each frame.  Passes declare the textures they read and write, the graph allocates those textures, resizes them with the surface and runs the passes in an order that respects their dependencies.
+///
+/// The surface and the render engine's depth texture are always available as `SURFACE_TEXTURE` and `DEPTH_TEXTURE`.  Every other texture must be added with `add_texture` before it is used.
+#[derive(Debug)]
+pub struct RenderGraph {
+    clear_color: wgpu::Color,
+    depth_convention: DepthConvention,
+    textures: HashMap<String, GraphTextureSlot>,
+    passes: Vec<GraphPass>,
+    order: Vec<CompiledPass>,
+    generation: u32,
+    sample_count: u32,
+    multisampled_surface: Option<wgpu::TextureView>,
+    dirty: bool
+}
+
+impl Default for RenderGraph {
+    fn default() -> Self {
+        Self {
+            clear_color: wgpu::Color::BLACK,
+            depth_convention: DepthConvention::Standard,
+            textures: HashMap::new(),
+            passes: Vec::new(),
+            order: Vec::new(),
+            generation: 0,
+            sample_count: 1,
+            multisampled_surface: None,
+            dirty: true
+        }
+    }
+}
+
+impl RenderGraph {
+    /// Sets the color the surface is cleared to by the first pass that writes it each frame.
+    pub fn set_clear_color(&mut self, color: wgpu::Color) { self.clear_color = color; }
+
+    /// The color the surface is cleared to by the first pass that writes it each frame.
+    pub fn clear_color(&self) -> wgpu::Color { self.clear_color }
+
+    /// Sets how depth is stored in the depth textures of this graph, use `RenderEngine::set_depth_convention` so pipelines follow it too.
+    pub(crate) fn set_depth_convention(&mut self, convention: DepthConvention) { self.depth_convention = convention; }
+
+    /// How depth is stored in the depth textures of this graph, see `RenderEngine::set_depth_convention`.
+    pub fn depth_convention(&self) -> DepthConvention { self.depth_convention }
+
+    /// The depth every depth texture is cleared to by the first pass that writes it each frame, this follows the depth convention.
+    pub fn clear_depth(&self) -> f32 { self.depth_convention.clear_depth() }
+
+    /// Adds a texture to this graph, replacing any texture with the same name.  The texture is allocated next time the graph is compiled.
+    ///
+    /// Arguments:
+    /// * name: impl Into<String> - The name passes use to refer to the texture.
+    /// * descriptor: GraphTextureDescriptor - The description of the texture.
+    pub fn add_texture(&mut self, name: impl Into<String>, descriptor: GraphTextureDescriptor) {
+        self.textures.insert(name.into(), GraphTextureSlot { descriptor, texture: None });
+        self.dirty = true;
+    }
+
+    /// Removes a texture from this graph.
+    pub fn remove_texture(&mut self, name: &str) {
+        self.textures.remove(name);
+        self.dirty = true;
+    }
+
+    /// Adds a pass to this graph, replacing any pass with the same name.
+    ///
+    /// Arguments:
+    /// * pass: GraphPass - The pass to add.
+    pub fn add_pass(&mut self, pass: GraphPass) {
+        match self.passes.iter_mut().find(|existing| existing.name == pass.name) {
+            Some(existing) => *existing = pass,
+            None => self.passes.push(pass)
+        }
+        self.dirty = true;
+    }
+
+    /// Removes the pass with the given name from this graph.
+    pub fn remove_pass(&mut self, name: &str) {
+        self.passes.retain(|pass| pass.name != name);
+        self.dirty = true;
+    }
+
+    /// Returns true if a texture with the given name was added to this graph.
+    pub fn has_texture(&self, name: &str) -> bool { self.textures.contains_key(name) }
+
+    /// Returns an option containing the allocated texture with the given name, or none if it does not exist or the graph has not been compiled since it was added.
+    pub fn texture(&self, name: &str) -> Option<&GraphTexture> { self.textures.get(name)?.texture.as_ref() }
+
+    /// A number that changes every time the graph's textures are reallocated, for example, when the surface is resized.  Bind groups using the graph's textures should be recreated when it changes.
+    pub fn generation(&self) -> u32 { self.generation }
+
+    /// The number of samples per pixel passes render with, this follows `RenderEngine::sample_count`.
+    pub fn sample_count(&self) -> u32 { self.sample_count }
+
+    /// Returns an option containing the multisampled texture passes render too instead of the surface, which is resolved into the surface at the end of each pass, or none if the surface is rendered too directly.
+    pub fn multisampled_surface(&self) -> Option<&wgpu::TextureView> { self.multisampled_surface.as_ref() }
+
+    /// The number of passes in the compiled graph.
+    pub fn num_passes(&self) -> usize { self.order.len() }
+
+    /// Gets the pass at the given index of the compiled graph's execution order.
+    pub fn pass(&self, index: usize) -> &GraphPass { &self.passes[self.order[index].pass] }
+
+    /// Reallocates every texture sized relative to the surface.  This is called by `RenderEngine::resize`.
+    ///
+    /// Arguments:
+    /// * device: &wgpu::Device - The device to allocate the textures with.
+    /// * config: &wgpu::SurfaceConfiguration - The configuration of the resized surface.
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        let mut reallocated = false;
+        let sample_count = self.sample_count;
+        self.textures.iter_mut()
+            .filter(|(_, slot)| !matches!(slot.descriptor.size, TextureSize::Fixed(..)))
+            .for_each(|(name, slot)| {
+                slot.texture = Some(create_texture(device, name, &slot.descriptor, (config.width, config.height), sample_count));
+                reallocated = true;
+            });
+        if reallocated { self.generation = self.generation.wrapping_add(1); }
+        self.multisampled_surface = create_multisampled_surface(device, config, sample_count);
+    }
+
+    /// Reallocates every texture with the given number of samples per pixel.  This is called by `RenderEngine::set_sample_count`.
+    ///
+    /// Arguments:
+    /// * device: &wgpu::Device - The device to allocate the textures with.
+    /// * config: &wgpu::SurfaceConfiguration - The configuration of the surface.
+    /// * sample_count: u32 - The new number of samples per pixel.
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) {
+        self.sample_count = sample_count;
+        self.textures.iter_mut()
+            .filter(|(_, slot)| slot.texture.is_some())
+            .for_each(|(name, slot)| slot.texture = Some(create_texture(device, name, &slot.descriptor, (config.width, config.height), sample_count)));
+        self.generation = self.generation.wrapping_add(1);
+        self.multisampled_surface = create_multisampled_surface(device, config, sample_count);
+    }
+
+    /// Allocates any new textures and sorts the passes into execution order if anything changed since the last compile.
+    ///
+    /// Arguments:
+    /// * device: &wgpu::Device - The device to allocate the textures with.
+    /// * config: &wgpu::SurfaceConfiguration - The configuration of the surface.
+    ///
+    /// Returns a result that is an error if a pass uses a texture that does not exist, reads a texture it also writes, or the passes could not be ordered, what they read and write forms a cycle.  After an error no pass runs until a texture or pass is added or removed.
+    pub fn compile(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Result<()> {
+        if !self.dirty { return Ok(()) }
+
+        // allocate new textures
+        let mut allocated = false;
+        let sample_count = self.sample_count;
+        self.textures.iter_mut()
+            .filter(|(_, slot)| slot.texture.is_none())
+            .for_each(|(name, slot)| {
+                slot.texture = Some(create_texture(device, name, &slot.descriptor, (config.width, config.height), sample_count));
+                allocated = true;
+            });
+        if allocated { self.generation = self.generation.wrapping_add(1); }
+
+        // an invalid graph runs nothing, instead of passes that may no longer exist
+        self.dirty = false;
+        match self.sort() {
+            Result::Ok(order) => { self.order = order; Ok(()) },
+            Err(error) => { self.order.clear(); Err(error) }
+        }
+    }
+
+    /// Begins the render pass at the given index of the compiled graph's execution order, with its targets attached.
+    ///
+    /// Arguments:
+    /// * index: usize - The index of the pass in the execution order.
+    /// * engine: &RenderEngine - The render engine whose depth texture is used as `DEPTH_TEXTURE`.
+    /// * encoder: &mut wgpu::CommandEncoder - The frame's command encoder.
+    /// * surface: &wgpu::TextureView - The view used as `SURFACE_TEXTURE`.
+    ///
+    /// Returns the render pass.
+    pub fn begin_pass<'a>(&'a self, index: usize, engine: &'a RenderEngine, encoder: &'a mut wgpu::CommandEncoder, surface: &'a wgpu::TextureView) -> wgpu::RenderPass<'a> {
+        let compiled = &self.order[index];
+        let pass = &self.passes[compiled.pass];
+
+        // the first pass to write each target clears it, the rest load what came before
+        let color_attachments: Vec<Option<wgpu::RenderPassColorAttachment>> = pass.color.iter().zip(compiled.clear_color.iter())
+            .map(|(name, clear)| {
+                let ((view, resolve_target), clear_color) = match name.as_str() {
+                    SURFACE_TEXTURE => (self.surface_attachment(surface), self.clear_color),
+                    _ => {
+                        let slot = &self.textures[name];
+                        (slot.texture.as_ref().unwrap().attachment(), slot.descriptor.clear_color)
+                    }
+                };
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: if *clear { wgpu::LoadOp::Clear(clear_color) } else { wgpu::LoadOp::Load },
+                        store: wgpu::StoreOp::Store
+                    }
+                })
+            })
+            .collect();
+        let depth_stencil_attachment = pass.depth.as_ref().map(|name| {
+            let (view, format) = match name.as_str() {
+                DEPTH_TEXTURE => (&engine.depth_texture.view, DepthTexture::DEPTH_FORMAT),
+                _ => {
+                    let slot = &self.textures[name];
+                    (&slot.texture.as_ref().unwrap().view, slot.descriptor.format)
+                }
+            };
+            wgpu::RenderPassDepthStencilAttachment {
+                view,
+                depth_ops: Some(wgpu::Operations {
+                    load: if compiled.clear_depth { wgpu::LoadOp::Clear(self.clear_depth()) } else { wgpu::LoadOp::Load },
+                    store: wgpu::StoreOp::Store
+                }),
+                stencil_ops: format.has_stencil_aspect().then_some(wgpu::Operations {
+                    load: if compiled.clear_depth { wgpu::LoadOp::Clear(0) } else { wgpu::LoadOp::Load },
+                    store: wgpu::StoreOp::Store
+                })
+            }
+        });
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&pass.name),
+            color_attachments: &color_attachments,
+            depth_stencil_attachment,
+            occlusion_query_set: None,
+            timestamp_writes: None
+        })
+    }
+
+    /// Begins a render pass drawing into the graph texture with the given name, for encoder passes that draw into graph textures themselves.  The texture is cleared to its clear color, and when MSAA is used the pass renders into its multisampled companion and resolves into it.
+    ///
+    /// Arguments:
+    /// * encoder: &'a mut wgpu::CommandEncoder - The frame's command encoder.
+    /// * name: &str - The name of the texture.
+    ///
+    /// Returns an option containing the render pass, or none if the texture is not allocated.
+    pub fn begin_texture_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, name: &str) -> Option<wgpu::RenderPass<'a>> {
+        let slot = self.textures.get(name)?;
+        let (view, resolve_target) = slot.texture.as_ref()?.attachment();
+        Some(encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(name),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view, resolve_target,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(slot.descriptor.clear_color), store: wgpu::StoreOp::Store }
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None
+        }))
+    }
+
+    /// The view passes render too for the surface and the view it is resolved into, if any.
+    fn surface_attachment<'a>(&'a self, surface: &'a wgpu::TextureView) -> (&'a wgpu::TextureView, Option<&'a wgpu::TextureView>) {
+        match &self.multisampled_surface {
+            Some(multisampled) => (multisampled, Some(surface)),
+            None => (surface, None)
+        }
+    }
+
+    /// Sorts the passes so that every pass runs after the passes it depends on.
+    fn sort(&self) -> Result<Vec<CompiledPass>> {
+        // make sure every texture used exists and no pass reads what it writes
+        for pass in self.passes.iter() {
+            for name in pass.written().chain(pass.reads.iter()) {
+                if name != SURFACE_TEXTURE && name != DEPTH_TEXTURE && !self.textures.contains_key(name) {
+                    bail!("Render pass {:?} uses the texture {:?} which was never added to the render graph.", pass.name, name);
+                }
+            }
+            if let Some(name) = pass.reads.iter().find(|name| pass.written().any(|written| written == *name)) {
+                bail!("Render pass {:?} cannot read the texture {:?} while writing to it.", pass.name, name);
+            }
+            if pass.reads.iter().any(|name| name == SURFACE_TEXTURE) {
+                bail!("Render pass {:?} cannot read the surface texture.", pass.name);
+            }
+        }
+
+        // passes writing the same texture run in order, readers run after every writer
+        let key = |index: usize| (self.passes[index].order, index);
+        let mut dependencies: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); self.passes.len()];
+        let mut writers: HashMap<&String, Vec<usize>> = HashMap::new();
+        self.passes.iter().enumerate().for_each(|(index, pass)| pass.written().for_each(|name| writers.entry(name).or_default().push(index)));
+        writers.values_mut().for_each(|indices| {
+            indices.sort_by_key(|index| key(*index));
+            indices.dedup();
+            indices.windows(2).for_each(|pair| { dependencies[pair[1]].insert(pair[0]); });
+        });
+        self.passes.iter().enumerate().for_each(|(index, pass)| {
+            pass.reads.iter().filter_map(|name| writers.get(name)).flatten().for_each(|writer| { dependencies[index].insert(*writer); });
+        });
+
+        // run whichever ready pass has the lowest order first
+        let mut ready: BTreeSet<(i32, usize)> = (0 .. self.passes.len()).filter(|index| dependencies[*index].is_empty()).map(key).collect();
+        let mut sorted = Vec::with_capacity(self.passes.len());
+        while let Some((_, index)) = ready.pop_first() {
+            sorted.push(index);
+            (0 .. self.passes.len()).for_each(|other| {
+                if dependencies[other].remove(&index) && dependencies[other].is_empty() { ready.insert(key(other)); }
+            });
+        }
+        if sorted.len() < self.passes.len() {
+            let stuck: Vec<&String> = (0 .. self.passes.len()).filter(|index| !sorted.contains(index)).map(|index| &self.passes[index].name).collect();
+            bail!("Render passes {:?} could not be ordered, what they read and write forms a cycle.", stuck);
+        }
+
+        // the first pass to write each texture clears it
+        let mut written = BTreeSet::new();
+        Ok(sorted.into_iter().map(|index| {
+            let pass = &self.passes[index];
+            let clear_color = pass.color.iter().map(|name| written.insert(name)).collect();
+            let clear_depth = pass.depth.as_ref().map(|name| written.insert(name)).unwrap_or(false);
+            pass.writes.iter().for_each(|name| { written.insert(name); });
+            CompiledPass { pass: index, clear_color, clear_depth }
+        }).collect())
+    }
+}
+
+/// Allocates a texture of a render graph, this is also used for textures that effects size themselves.  Color textures get a multisampled companion when the sample count is above 1, depth textures are multisampled themselves.
+pub(crate) fn create_texture(device: &wgpu::Device, name: &str, descriptor: &GraphTextureDescriptor, surface_size: (u32, u32), sample_count: u32) -> GraphTexture {
+    let (width, height) = match descriptor.size {
+        TextureSize::Surface => surface_size,
+        TextureSize::Scaled(scale) => ((surface_size.0 as f32 * scale) as u32, (surface_size.1 as f32 * scale) as u32),
+        TextureSize::Fixed(width, height) => (width, height)
+    };
+    let size = wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: 1 };
+    let depth = descriptor.format.is_depth_stencil_format();
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(name),
+        size,
+        mip_level_count: 1,
+        sample_count: if depth { sample_count } else { 1 },
+        dimension: wgpu::TextureDimension::D2,
+        format: descriptor.format,
+        usage: if depth && sample_count > 1 { wgpu::TextureUsages::RENDER_ATTACHMENT | descriptor.usage } else { wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | descriptor.usage },
+        view_formats: &[]
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    });
+
+    // only filterable textures can be bound with the standard texture layout
+    let filterable = descriptor.format.sample_type(None, Some(device.features())) == Some(wgpu::TextureSampleType::Float { filterable: true });
+    let bind_group = filterable.then(|| device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(name),
+        layout: &device.create_bind_group_layout(&Texture::BIND_LAYOUT),
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&sampler) }
+        ]
+    }));
+
+    let multisampled_view = (!depth && sample_count > 1).then(|| create_multisampled_view(device, name, descriptor.format, size, sample_count));
+    GraphTexture { texture, view, sampler, bind_group, multisampled_view }
+}
+
+/// Allocates the multisampled texture rendered too instead of the surface, or none if the sample count is 1.
+fn create_multisampled_surface(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> Option<wgpu::TextureView> {
+    let size = wgpu::Extent3d { width: config.width.max(1), height: config.height.max(1), depth_or_array_layers: 1 };
+    (sample_count > 1).then(|| create_multisampled_view(device, SURFACE_TEXTURE, config.format, size, sample_count))
+}
+
+/// Allocates a multisampled texture that is only rendered too and resolved, returning a view of it.
+pub(crate) fn create_multisampled_view(device: &wgpu::Device, name: &str, format: wgpu::TextureFormat, size: wgpu::Extent3d, sample_count: u32) -> wgpu::TextureView {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(&format!("{}_multisampled", name)),
+        size,
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[]
+    }).create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A graph with the given color textures added.
+    fn graph(textures: &[&str]) -> RenderGraph {
+        let mut graph = RenderGraph::default();
+        textures.iter().for_each(|name| graph.add_texture(*name, GraphTextureDescriptor::new(wgpu::TextureFormat::Rgba8Unorm)));
+        graph
+    }
+
+    /// The names of the graph's passes in the order they are sorted into.
+    fn sorted_names(graph: &RenderGraph) -> Vec<&str> {
+        graph.sort().unwrap().iter().map(|compiled| graph.passes[compiled.pass].name.as_str()).collect()
+    }
+
+    fn engine() -> RenderEngine { pollster::block_on(RenderEngine::new_headless(64, 64, wgpu::TextureFormat::Rgba8UnormSrgb)) }
+
+    #[test]
+    fn writers_run_by_order_then_insertion() {
+        let mut graph = graph(&["other"]);
+        graph.add_pass(GraphPass::new("late_other").with_color("other").with_order(5));
+        graph.add_pass(GraphPass::new("first_added").with_color(SURFACE_TEXTURE));
+        graph.add_pass(GraphPass::new("early").with_color(SURFACE_TEXTURE).with_order(-1));
+        graph.add_pass(GraphPass::new("second_added").with_color(SURFACE_TEXTURE));
+        graph.add_pass(GraphPass::new("last").with_depth(DEPTH_TEXTURE).with_color(SURFACE_TEXTURE).with_order(1));
+        assert_eq!(sorted_names(&graph), vec!["early", "first_added", "second_added", "last", "late_other"]);
+
+        // replacing a pass keeps its place among passes with the same order
+        graph.add_pass(GraphPass::new("first_added").with_color(SURFACE_TEXTURE).with_write("other"));
+        assert_eq!(graph.passes.len(), 5);
+        assert_eq!(sorted_names(&graph), vec!["early", "first_added", "second_added", "last", "late_other"]);
+    }
+
+    #[test]
+    fn readers_run_after_every_writer() {
+        let mut graph = graph(&["scene", "bloom", "unused"]);
+        graph.add_pass(GraphPass::new("composite").with_read("scene").with_read("bloom").with_color(SURFACE_TEXTURE).with_order(-10));
+        graph.add_pass(GraphPass::new("bloom").with_read("scene").with_color("bloom"));
+        graph.add_pass(GraphPass::new("scene").with_color("scene"));
+        graph.add_pass(GraphPass::new("scene_overlay").with_color("scene").with_order(5));
+        graph.add_pass(GraphPass::encoder("reads_unwritten").with_read("unused"));
+        assert_eq!(sorted_names(&graph), vec!["scene", "reads_unwritten", "scene_overlay", "bloom", "composite"]);
+    }
+
+    #[test]
+    fn sort_rejects_cycles_and_invalid_textures() {
+        let mut cycle = graph(&["a", "b"]);
+        cycle.add_pass(GraphPass::new("first").with_read("a").with_color("b"));
+        cycle.add_pass(GraphPass::new("second").with_read("b").with_color("a"));
+        cycle.add_pass(GraphPass::new("free").with_color(SURFACE_TEXTURE));
+        let error = cycle.sort().unwrap_err().to_string();
+        assert!(error.contains("cycle") && error.contains("first") && error.contains("second") && !error.contains("free"), "{}", error);
+
+        let mut missing = graph(&[]);
+        missing.add_pass(GraphPass::new("pass").with_color("missing"));
+        assert!(missing.sort().unwrap_err().to_string().contains("missing"));
+
+        let mut read_write = graph(&["a"]);
+        read_write.add_pass(GraphPass::new("pass").with_read("a").with_color("a"));
+        assert!(read_write.sort().is_err());
+
+        let mut surface = graph(&[]);
+        surface.add_pass(GraphPass::new("pass").with_read(SURFACE_TEXTURE));
+        assert!(surface.sort().is_err());
+    }
+
+    #[test]
+    fn first_writer_clears_each_texture() {
+        let mut graph = graph(&["scene", "bloom"]);
+        graph.add_pass(GraphPass::new("ui").with_color(SURFACE_TEXTURE).with_order(10));
+        graph.add_pass(GraphPass::new("main").with_color(SURFACE_TEXTURE).with_color("scene").with_depth(DEPTH_TEXTURE));
+        graph.add_pass(GraphPass::new("overlay").with_color("scene").with_depth(DEPTH_TEXTURE).with_order(1));
+        graph.add_pass(GraphPass::encoder("bloom_prepare").with_write("bloom"));
+        graph.add_pass(GraphPass::new("bloom").with_read("scene").with_color("bloom"));
+
+        let sorted = graph.sort().unwrap();
+        let compiled = |name: &str| sorted.iter().find(|compiled| graph.passes[compiled.pass].name == name).unwrap();
+        assert_eq!((compiled("main").clear_color.clone(), compiled("main").clear_depth), (vec![true, true], true));
+        assert_eq!((compiled("overlay").clear_color.clone(), compiled("overlay").clear_depth), (vec![false], false));
+        assert_eq!((compiled("ui").clear_color.clone(), compiled("ui").clear_depth), (vec![false], false));
+
+        // the encoder pass wrote the texture first
+        assert_eq!(compiled("bloom").clear_color, vec![false]);
+    }
+
+    #[test]
+    fn compile_allocates_msaa_companions() {
+        let engine = engine();
+        let mut graph = graph(&["color"]);
+        graph.add_texture("depth", GraphTextureDescriptor::new(DepthTexture::DEPTH_FORMAT));
+        graph.add_pass(GraphPass::new("pass").with_color("color").with_depth("depth"));
+        graph.compile(&engine.device, &engine.config).unwrap();
+        assert!(graph.texture("color").unwrap().multisampled_view.is_none());
+        assert!(graph.multisampled_surface().is_none());
+        let generation = graph.generation();
+
+        // color textures get a multisampled companion, depth textures are multisampled themselves
+        graph.set_sample_count(&engine.device, &engine.config, 4);
+        assert!(graph.generation() != generation);
+        assert_eq!(graph.texture("color").unwrap().texture.sample_count(), 1);
+        assert!(graph.texture("color").unwrap().multisampled_view.is_some());
+        assert_eq!(graph.texture("depth").unwrap().texture.sample_count(), 4);
+        assert!(graph.texture("depth").unwrap().multisampled_view.is_none());
+        assert!(graph.multisampled_surface().is_some());
+
+        // textures added later are allocated with the current sample count
+        graph.add_texture("later", GraphTextureDescriptor::new(wgpu::TextureFormat::Rgba8Unorm));
+        graph.compile(&engine.device, &engine.config).unwrap();
+        assert!(graph.texture("later").unwrap().multisampled_view.is_some());
+
+        graph.set_sample_count(&engine.device, &engine.config, 1);
+        assert!(graph.texture("color").unwrap().multisampled_view.is_none());
+        assert!(graph.multisampled_surface().is_none());
+    }
+
+    #[test]
+    fn failed_compile_runs_no_passes_until_changed() {
+        let engine = engine();
+        let mut graph = graph(&["scene"]);
+        graph.add_pass(GraphPass::new("scene").with_color("scene"));
+        graph.add_pass(GraphPass::new("present").with_read("scene").with_color(SURFACE_TEXTURE));
+        graph.compile(&engine.device, &engine.config).unwrap();
+        assert_eq!(graph.num_passes(), 2);
+
+        graph.add_pass(GraphPass::new("broken").with_color("missing"));
+        assert!(graph.compile(&engine.device, &engine.config).is_err());
+        assert_eq!(graph.num_passes(), 0);
+
+        // the error is only reported once
+        assert!(graph.compile(&engine.device, &engine.config).is_ok());
+        assert_eq!(graph.num_passes(), 0);
+
+        graph.remove_pass("broken");
+        graph.compile(&engine.device, &engine.config).unwrap();
+        assert_eq!(graph.num_passes(), 2);
+        assert_eq!(graph.pass(1).name, "present");
+    }
+}
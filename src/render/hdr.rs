@@ -0,0 +1,437 @@
+use wgpu::util::DeviceExt;
+
+use crate::{component_app::EngineComponent, create_compute_pipeline};
+
+use super::{graph::{GraphPass, GraphTextureDescriptor, PREPARE_PASS_ORDER, SURFACE_TEXTURE}, pipelines::{engine_shader_path, PipelineBuilder}, render_engine::RenderEngine};
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/tonemap.wgsl")]
+mod tonemap_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/auto_exposure.wgsl")]
+mod auto_exposure_shader {}
+
+/// The number of pixels along each axis counted by a single workgroup of the histogram compute shader, this must match the workgroup size in `auto_exposure.wgsl`.
+const HISTOGRAM_WORKGROUP_SIZE: u32 = 16;
+
+/// The number of bins in the luminance histogram, this must match the histogram size in `auto_exposure.wgsl`.
+const HISTOGRAM_BINS: u32 = 256;
+
+/// The operator used to map HDR colors into the range the surface can display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TonemapOperator {
+    /// A fit of the ACES filmic curve, with strong contrast and saturated highlights.
+    #[default]
+    Aces,
+
+    /// Reinhard's operator applied to the luminance, soft and low contrast.
+    Reinhard,
+
+    /// AgX, which desaturates bright colors towards white instead of skewing their hue.
+    AgX
+}
+
+/// Settings used to adjust the exposure automatically so that the average luminance of the scene is displayed as middle grey.  The average is taken from a histogram of the scene's luminance built with compute shaders each frame.
+///
+/// Arguments:
+/// * min_exposure: f32 - The lowest exposure in stops, used to stop bright scenes from getting too dark.
+/// * max_exposure: f32 - The highest exposure in stops, used to stop dark scenes from getting too bright.
+/// * speed: f32 - How quickly the exposure adapts, higher is faster.
+/// * min_log_luminance: f32 - The log2 of the darkest luminance counted by the histogram.
+/// * max_log_luminance: f32 - The log2 of the brightest luminance counted by the histogram.
+/// * low_percentile: f32 - The fraction of the darkest pixels ignored by the average.
+/// * high_percentile: f32 - One minus the fraction of the brightest pixels ignored by the average.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoExposure {
+    pub min_exposure: f32,
+    pub max_exposure: f32,
+    pub speed: f32,
+    pub min_log_luminance: f32,
+    pub max_log_luminance: f32,
+    pub low_percentile: f32,
+    pub high_percentile: f32
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        Self {
+            min_exposure: -6.0,
+            max_exposure: 6.0,
+            speed: 3.0,
+            min_log_luminance: -8.0,
+            max_log_luminance: 4.0,
+            low_percentile: 0.1,
+            high_percentile: 0.9
+        }
+    }
+}
+
+/// The rust representation of the tonemapping settings passed to the shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct TonemapUniform {
+    // FORMAT: operator, exposure multiplier, 1 if auto exposure is used, 1 if the output must be gamma encoded
+    settings: [f32; 4]
+}
+
+/// The rust representation of the auto exposure settings passed to the shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct AutoExposureUniform {
+    // FORMAT: min log2 luminance, log2 luminance range, low percentile, high percentile
+    histogram: [f32; 4],
+
+    // FORMAT: min exposure, max exposure, speed, seconds since the last frame
+    adaption: [f32; 4]
+}
+
+/// The compute pipelines and buffers used for auto exposure.
+#[derive(Debug)]
+struct AutoExposurePipelines {
+    settings_buffer: wgpu::Buffer,
+    histogram_buffer: wgpu::Buffer,
+    bind_group: Option<(u32, wgpu::BindGroup)>
+}
+
+/// A engine that renders 3D scenes in HDR.  On creation, it adds the `HdrEngine::TEXTURE` texture to the render graph and makes it the scene format, so `Pipeline::new` creates pipelines that render into it.  Its tonemapping pass then maps the texture onto the surface, before any UI passes draw on top.
+///
+/// As pipelines are created for the scene format, this engine must be listed before any component that creates pipelines.
+///
+/// Example:
+/// ```rust
+/// create_app! {
+///     CLEAR_COLOR = wgpu::Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 },
+///
+///     APP {
+///         hdr: HdrEngine[render_engine],
+///         light_engine: LightEngine[render_engine],
+///         test: TestComponent[render_engine, light_engine]
+///     },
+///
+///     PASSES {
+///         "main": {
+///             COLOR: ["forte.hdr"],
+///             DEPTH: "forte.depth",
+///             PARTS: [...]
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct HdrEngine {
+    operator: TonemapOperator,
+    exposure: f32,
+    auto_exposure: Option<AutoExposure>,
+    settings_buffer: wgpu::Buffer,
+    exposure_buffer: wgpu::Buffer,
+    auto_exposure_pipelines: Option<AutoExposurePipelines>,
+    bind_group: Option<(u32, wgpu::BindGroup)>,
+    dirty: bool
+}
+
+impl HdrEngine {
+    /// The name of the HDR texture in the render graph, 3D passes should render into it.
+    pub const TEXTURE: &'static str = "forte.hdr";
+
+    /// The format of the HDR texture.
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    /// The name of the render graph pass that tonemaps the HDR texture onto the surface.
+    pub const PASS: &'static str = "forte.tonemap";
+
+    /// The name of the render graph pass that measures the HDR texture's luminance for auto exposure.
+    pub const EXPOSURE_PASS: &'static str = "forte.auto_exposure";
+
+    /// The name of the compute pipeline that counts the pixels of the HDR texture into a histogram of their luminance.
+    pub const HISTOGRAM_PIPELINE: &'static str = "forte.auto_exposure.histogram";
+
+    /// The name of the compute pipeline that averages the histogram into the exposure.
+    pub const AVERAGE_PIPELINE: &'static str = "forte.auto_exposure.average";
+
+    /// The bind group layout of the tonemapping shader.
+    pub const BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("tonemap_bind_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }
+        ]
+    };
+
+    /// The bind group layout of the auto exposure compute shaders.
+    pub const AUTO_EXPOSURE_BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("auto_exposure_bind_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }
+        ]
+    };
+
+    /// Returns true if the given engine supports auto exposure, it needs compute shaders which WebGL does not have.
+    pub fn supports_auto_exposure(engine: &RenderEngine) -> bool {
+        let limits = engine.device.limits();
+        limits.max_storage_buffers_per_shader_stage >= 2 && limits.max_compute_invocations_per_workgroup >= HISTOGRAM_BINS && limits.max_compute_workgroup_size_x >= HISTOGRAM_BINS
+    }
+
+    /// The operator used to map HDR colors onto the surface.
+    pub fn operator(&self) -> TonemapOperator { self.operator }
+
+    /// Sets the operator used to map HDR colors onto the surface.
+    pub fn set_operator(&mut self, operator: TonemapOperator) { self.operator = operator; self.dirty = true; }
+
+    /// The exposure in stops, each stop doubles the brightness of the scene.  With auto exposure, this is added to the automatic exposure as compensation.
+    pub fn exposure(&self) -> f32 { self.exposure }
+
+    /// Sets the exposure in stops, each stop doubles the brightness of the scene.  With auto exposure, this is added to the automatic exposure as compensation.
+    pub fn set_exposure(&mut self, exposure: f32) { self.exposure = exposure; self.dirty = true; }
+
+    /// The auto exposure settings, none if auto exposure is off.
+    pub fn auto_exposure(&self) -> Option<&AutoExposure> { self.auto_exposure.as_ref() }
+
+    /// Sets the auto exposure settings, or turns auto exposure off if none.  Does nothing on devices without auto exposure (see `HdrEngine::supports_auto_exposure`).
+    pub fn set_auto_exposure(&mut self, auto_exposure: Option<AutoExposure>) {
+        if self.auto_exposure_pipelines.is_none() { return }
+        self.auto_exposure = auto_exposure;
+        self.dirty = true;
+    }
+
+    /// Recreates the tonemapping bind group if the render graph's textures changed since it was created.
+    fn update_bind_group(&mut self, engine: &RenderEngine) {
+        let generation = engine.render_graph.generation();
+        if self.bind_group.as_ref().is_some_and(|(created, _)| *created == generation) { return }
+        let Some(texture) = engine.render_graph.texture(Self::TEXTURE) else { return };
+
+        self.bind_group = Some((generation, engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("tonemap_bind_group"),
+            layout: &engine.device.create_bind_group_layout(&Self::BIND_LAYOUT),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&texture.view) },
+                wgpu::BindGroupEntry { binding: 1, resource: self.settings_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: self.exposure_buffer.as_entire_binding() }
+            ]
+        })));
+    }
+}
+
+impl EngineComponent<&mut RenderEngine> for HdrEngine {
+    /// Creates a new HDR engine, adding its texture and passes to the render graph.
+    ///
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine to render in HDR.
+    fn create(engine: &mut RenderEngine) -> Self {
+        // render the scene into the hdr texture, then tonemap it onto the surface
+        engine.scene_format = Self::FORMAT;
+        engine.render_graph.add_texture(Self::TEXTURE, GraphTextureDescriptor { clear_color: engine.render_graph.clear_color(), ..GraphTextureDescriptor::new(Self::FORMAT) });
+        engine.render_graph.add_pass(GraphPass::new(Self::PASS).with_color(SURFACE_TEXTURE).with_read(Self::TEXTURE));
+        engine.verify_pipeline_exists(Self::PASS, |engine| {
+            PipelineBuilder::new(Self::PASS, tonemap_shader::SOURCE)
+                .with_layouts(&[&engine.device.create_bind_group_layout(&Self::BIND_LAYOUT)])
+                .with_format(engine.config.format)
+                .with_source_path(engine_shader_path("shaders/tonemap.wgsl"))
+                .build(engine)
+        });
+
+        // create buffers, the exposure buffer is written by the auto exposure compute shaders when they are supported
+        let supports_auto_exposure = Self::supports_auto_exposure(engine);
+        let settings_buffer = engine.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("tonemap_settings_buffer"),
+            contents: bytemuck::cast_slice(&[TonemapUniform { settings: [0.0, 1.0, 0.0, 0.0] }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+        });
+        let exposure_usage = if supports_auto_exposure { wgpu::BufferUsages::STORAGE } else { wgpu::BufferUsages::empty() };
+        let exposure_buffer = engine.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("auto_exposure_buffer"),
+            contents: bytemuck::cast_slice(&[1.0f32, 0.0, 0.18, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST | exposure_usage
+        });
+
+        // create auto exposure pipelines, the histogram is measured after the hdr texture is drawn and before it is tonemapped
+        let auto_exposure_pipelines = supports_auto_exposure.then(|| {
+            engine.render_graph.add_pass(GraphPass::encoder(Self::EXPOSURE_PASS).with_read(Self::TEXTURE).with_order(PREPARE_PASS_ORDER));
+
+            for (name, entry_point) in [(Self::HISTOGRAM_PIPELINE, "cs_histogram"), (Self::AVERAGE_PIPELINE, "cs_average")] {
+                create_compute_pipeline! {
+                    NAME => name,
+                    ENGINE => engine,
+                    SHADER => auto_exposure_shader::SOURCE,
+                    BIND_GROUPS => [Self::AUTO_EXPOSURE_BIND_LAYOUT],
+                    ENTRY_POINT => entry_point
+                }
+            }
+
+            AutoExposurePipelines {
+                settings_buffer: engine.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("auto_exposure_settings_buffer"),
+                    contents: bytemuck::cast_slice(&[<AutoExposureUniform as bytemuck::Zeroable>::zeroed()]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+                }),
+                histogram_buffer: engine.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("auto_exposure_histogram_buffer"),
+                    size: HISTOGRAM_BINS as u64 * 4,
+                    usage: wgpu::BufferUsages::STORAGE,
+                    mapped_at_creation: false
+                }),
+                bind_group: None
+            }
+        });
+
+        Self {
+            operator: TonemapOperator::default(),
+            exposure: 0.0,
+            auto_exposure: None,
+            settings_buffer, exposure_buffer, auto_exposure_pipelines,
+            bind_group: None,
+            dirty: true
+        }
+    }
+
+    /// Uploads any changed settings.
+    ///
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine this HDR engine is being used with.
+    fn update(&mut self, engine: &mut RenderEngine) {
+        if self.dirty {
+            self.dirty = false;
+            let operator = match self.operator {
+                TonemapOperator::Aces => 0.0,
+                TonemapOperator::Reinhard => 1.0,
+                TonemapOperator::AgX => 2.0
+            };
+            let settings = [operator, self.exposure.exp2(), self.auto_exposure.is_some() as u32 as f32, !engine.config.format.is_srgb() as u32 as f32];
+            engine.queue.write_buffer(&self.settings_buffer, 0, bytemuck::cast_slice(&[TonemapUniform { settings }]));
+        }
+
+        // the auto exposure adapts with the time since the last frame, so its settings are written every frame
+        if let (Some(auto_exposure), Some(pipelines)) = (&self.auto_exposure, &self.auto_exposure_pipelines) {
+            let uniform = AutoExposureUniform {
+                histogram: [
+                    auto_exposure.min_log_luminance, (auto_exposure.max_log_luminance - auto_exposure.min_log_luminance).max(0.0001),
+                    auto_exposure.low_percentile, auto_exposure.high_percentile
+                ],
+                adaption: [auto_exposure.min_exposure, auto_exposure.max_exposure, auto_exposure.speed, engine.delta_time]
+            };
+            engine.queue.write_buffer(&pipelines.settings_buffer, 0, bytemuck::cast_slice(&[uniform]));
+        }
+    }
+
+    /// On the render graph's `EXPOSURE_PASS`, this engine measures the luminance of the HDR texture and adapts the exposure.
+    ///
+    /// Arguments:
+    /// * name: &str - The name of the pass being run.
+    /// * engine: &RenderEngine - The render engine being rendered with.
+    /// * encoder: &mut wgpu::CommandEncoder - The frame's command encoder.
+    fn encode_graph_pass(&mut self, name: &str, engine: &RenderEngine, encoder: &mut wgpu::CommandEncoder) {
+        if name != Self::EXPOSURE_PASS || self.auto_exposure.is_none() { return }
+        let Some(pipelines) = &mut self.auto_exposure_pipelines else { return };
+        let Some(texture) = engine.render_graph.texture(Self::TEXTURE) else { return };
+
+        // recreate the bind group if the hdr texture was reallocated
+        let generation = engine.render_graph.generation();
+        if pipelines.bind_group.as_ref().is_none_or(|(created, _)| *created != generation) {
+            pipelines.bind_group = Some((generation, engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("auto_exposure_bind_group"),
+                layout: &engine.device.create_bind_group_layout(&Self::AUTO_EXPOSURE_BIND_LAYOUT),
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&texture.view) },
+                    wgpu::BindGroupEntry { binding: 1, resource: pipelines.settings_buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 2, resource: pipelines.histogram_buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 3, resource: self.exposure_buffer.as_entire_binding() }
+                ]
+            })));
+        }
+        let Some((_, bind_group)) = &pipelines.bind_group else { return };
+        let (Some(histogram_pipeline), Some(average_pipeline)) = (engine.compute_pipeline_path(Self::HISTOGRAM_PIPELINE), engine.compute_pipeline_path(Self::AVERAGE_PIPELINE)) else { return };
+
+        // count the pixels into the histogram, then average it into the exposure
+        let size = texture.texture.size();
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("auto_exposure_pass"), timestamp_writes: None });
+        pass.set_bind_group(0, bind_group, &[]);
+        histogram_pipeline.bind(&mut pass);
+        pass.dispatch_workgroups(size.width.div_ceil(HISTOGRAM_WORKGROUP_SIZE), size.height.div_ceil(HISTOGRAM_WORKGROUP_SIZE), 1);
+        average_pipeline.bind(&mut pass);
+        pass.dispatch_workgroups(1, 1, 1);
+    }
+
+    /// On the render graph's `PASS`, this engine tonemaps the HDR texture onto the surface.
+    ///
+    /// Arguments:
+    /// * name: &str - The name of the pass being run.
+    /// * engine: &RenderEngine - The render engine being rendered with.
+    /// * pass: &mut wgpu::RenderPass - The render pass to draw too.
+    fn render_graph_pass<'rpass>(&'rpass mut self, name: &str, engine: &'rpass RenderEngine, pass: &mut wgpu::RenderPass<'rpass>) {
+        if name != Self::PASS { return }
+        self.update_bind_group(engine);
+        let Some((_, bind_group)) = &self.bind_group else { return };
+        engine.pipeline_path(Self::PASS).unwrap().bind(pass);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.draw(0 .. 3, 0 .. 1);
+    }
+
+    fn render<'rpass>(&'rpass mut self, _: &'rpass RenderEngine, _: &mut wgpu::RenderPass<'rpass>) {}
+    fn start(&mut self, _: &mut RenderEngine) {}
+    fn exit(&mut self, _: &mut RenderEngine) {}
+}
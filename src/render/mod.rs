@@ -1,4 +1,8 @@
+pub mod culling;
+pub mod graph;
+pub mod hdr;
 pub mod pipelines;
+pub mod post_process;
 pub mod render_engine;
 pub mod render_utils;
 
@@ -7,6 +11,6 @@ pub mod render_utils;
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
     1.0, 0.0, 0.0, 0.0,
     0.0, 1.0, 0.0, 0.0,
-    0.0, 0.0, 0.5, 0.5,
-    0.0, 0.0, 0.0, 1.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
 );
@@ -0,0 +1,508 @@
+use std::{collections::{BTreeMap, HashMap}, fmt::Display, path::{Path, PathBuf}};
+
+use anyhow::*;
+use naga_oil::compose::{ComposableModuleDescriptor, Composer, NagaModuleDescriptor, ShaderDefValue};
+
+/// The WGSL source of a shader composed from a file, with the files it was composed from.
+///
+/// Arguments:
+/// * source: String - The composed WGSL source, with every import resolved.
+/// * files: Vec<PathBuf> - The file the shader was composed from, followed by every file it imports.
+#[derive(Debug, Clone)]
+pub struct ComposedShader {
+    pub source: String,
+    pub files: Vec<PathBuf>
+}
+
+/// Composes the WGSL shader in the given file at runtime.  Its `#import ./path.wgsl as Name` directives are resolved relative to the importing file the same way `include_wgsl_oil` resolves them at compile time, and the result is validated.
+///
+/// Arguments:
+/// * path: impl AsRef<Path> - The path of the WGSL file.
+///
+/// Returns a result containing the composed shader, or an error if a file could not be read, the imports form a cycle or the shader is invalid.
+pub fn compose_file(path: impl AsRef<Path>) -> Result<ComposedShader> {
+    let root = path.as_ref().canonicalize().with_context(|| format!("Could not find shader {:?}", path.as_ref()))?;
+
+    // find every imported file, imports come before the files importing them
+    let mut sources = HashMap::new();
+    let mut imports = Vec::new();
+    find_imports(&root, &mut Vec::new(), &mut imports, &mut sources)?;
+    imports.retain(|import| *import != root);
+
+    // name each imported module after its file, numbering files with the same name
+    let mut names: HashMap<PathBuf, String> = HashMap::new();
+    for import in imports.iter() {
+        let stem = import.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let mut name = stem.clone();
+        let mut index = 1;
+        while names.values().any(|existing| *existing == name) {
+            name = format!("{}_{}", stem, index);
+            index += 1;
+        }
+        names.insert(import.clone(), name);
+    }
+
+    // add the imported modules in order, then the shader itself
+    let mut composer = Composer::default();
+    composer.capabilities = naga::valid::Capabilities::all();
+    for import in imports.iter() {
+        let source = replace_imports(import, &sources[import], &names);
+        let file_path = import.to_string_lossy();
+        let added = composer.add_composable_module(ComposableModuleDescriptor {
+            source: &source,
+            file_path: &file_path,
+            as_name: Some(names[import].clone()),
+            ..Default::default()
+        }).map(|_| ());
+        added.map_err(|error| anyhow!(error.emit_to_string(&composer)))?;
+    }
+    let source = replace_imports(&root, &sources[&root], &names);
+    let file_path = root.to_string_lossy();
+    let module = composer.make_naga_module(NagaModuleDescriptor {
+        source: &source,
+        file_path: &file_path,
+        ..Default::default()
+    }).map_err(|error| anyhow!(error.emit_to_string(&composer)))?;
+
+    let source = validate_and_write(&module, &format!("{:?}", root))?;
+    let mut files = vec![root];
+    files.extend(imports);
+    Ok(ComposedShader { source, files })
+}
+
+/// Reads the given file and every file it imports, adding them to `imports` after the files they import.
+fn find_imports(path: &Path, stack: &mut Vec<PathBuf>, imports: &mut Vec<PathBuf>, sources: &mut HashMap<PathBuf, String>) -> Result<()> {
+    if stack.iter().any(|importing| importing == path) {
+        bail!("Shader imports form a cycle: {:?}", stack);
+    }
+    if sources.contains_key(path) { return Ok(()) }
+
+    let source = std::fs::read_to_string(path).with_context(|| format!("Could not read shader {:?}", path))?;
+    stack.push(path.to_path_buf());
+    for import in import_paths(&source) {
+        let resolved = resolve_import(path, import)?;
+        find_imports(&resolved, stack, imports, sources)?;
+    }
+    stack.pop();
+
+    sources.insert(path.to_path_buf(), source);
+    imports.push(path.to_path_buf());
+    Ok(())
+}
+
+/// The paths of the files imported by the given source.
+fn import_paths(source: &str) -> impl Iterator<Item = &str> {
+    source.lines().filter_map(|line| {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() != Some("#import") { return None }
+        tokens.next().filter(|path| path.ends_with(".wgsl"))
+    })
+}
+
+/// Resolves an import path relative to the file importing it.
+fn resolve_import(importing: &Path, import: &str) -> Result<PathBuf> {
+    let directory = importing.parent().unwrap_or(Path::new("."));
+    directory.join(import).canonicalize().with_context(|| format!("Could not find shader {:?} imported by {:?}", import, importing))
+}
+
+/// Replaces the paths of the imports in the given source with the names of their modules.
+fn replace_imports(path: &Path, source: &str, names: &HashMap<PathBuf, String>) -> String {
+    source.lines().map(|line| {
+        let Some(import) = import_paths(line).next() else { return line.to_string() };
+        match resolve_import(path, import).ok().and_then(|resolved| names.get(&resolved)) {
+            Some(name) => line.replacen(import, name, 1),
+            None => line.to_string()
+        }
+    }).collect::<Vec<String>>().join("\n")
+}
+
+/// Validates a composed module and writes it back to WGSL.
+fn validate_and_write(module: &naga::Module, name: &str) -> Result<String> {
+    let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+        .validate(module)
+        .map_err(|error| anyhow!("Shader {} is invalid: {}", name, error.into_inner()))?;
+    naga::back::wgsl::write_string(module, &info, naga::back::wgsl::WriterFlags::empty())
+        .with_context(|| format!("Could not write shader {}", name))
+}
+
+/// The value of a define a shader is composed with.  `#ifdef NAME` blocks are kept if a define is set, `#if NAME == value` compares its value, and `#NAME` in the source is replaced by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ShaderDefine {
+    Bool(bool),
+    Int(i32),
+    UInt(u32)
+}
+
+impl From<ShaderDefine> for ShaderDefValue {
+    fn from(value: ShaderDefine) -> Self {
+        match value {
+            ShaderDefine::Bool(value) => ShaderDefValue::Bool(value),
+            ShaderDefine::Int(value) => ShaderDefValue::Int(value),
+            ShaderDefine::UInt(value) => ShaderDefValue::UInt(value)
+        }
+    }
+}
+
+/// Identifies a variant of a shader registered with a `ShaderComposer`, by the name of the shader and the defines it is composed with.  Its string form, like `forte.gltf[NORMAL_MAP, MAX_LIGHTS=4]`, is the name the variant's pipeline is cached under.
+///
+/// Arguments:
+/// * shader: String - The name the shader was registered with.
+/// * defines: BTreeMap<String, ShaderDefine> - The defines the shader is composed with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShaderKey {
+    pub shader: String,
+    pub defines: BTreeMap<String, ShaderDefine>
+}
+
+impl ShaderKey {
+    /// Creates a key for the given shader without any defines.
+    ///
+    /// Arguments:
+    /// * shader: impl Into<String> - The name the shader was registered with.
+    pub fn new(shader: impl Into<String>) -> Self { Self { shader: shader.into(), defines: BTreeMap::new() } }
+
+    /// Sets a feature flag, a define with the value `true`.
+    pub fn with_flag(self, name: impl Into<String>) -> Self { self.with_define(name, ShaderDefine::Bool(true)) }
+
+    /// Sets a define with the given value.
+    ///
+    /// Arguments:
+    /// * name: impl Into<String> - The name of the define.
+    /// * value: ShaderDefine - The value of the define.
+    pub fn with_define(mut self, name: impl Into<String>, value: ShaderDefine) -> Self { self.defines.insert(name.into(), value); self }
+}
+
+impl Display for ShaderKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.shader)?;
+        if self.defines.is_empty() { return std::fmt::Result::Ok(()) }
+
+        let defines: Vec<String> = self.defines.iter().map(|(name, value)| match value {
+            ShaderDefine::Bool(true) => name.clone(),
+            ShaderDefine::Bool(value) => format!("{}={}", name, value),
+            ShaderDefine::Int(value) => format!("{}={}", name, value),
+            ShaderDefine::UInt(value) => format!("{}={}", name, value)
+        }).collect();
+        write!(f, "[{}]", defines.join(", "))
+    }
+}
+
+/// Composes WGSL shaders at runtime from a set of registered modules, so variants of a shader can be built with different defines.  Shaders import registered modules with `#import name` and use their items as `name::item`, and can set their own defines with `#define NAME value`.  The composed source of every variant is cached until the shader or a module is registered again.
+#[derive(Debug)]
+pub struct ShaderComposer {
+    composer: Composer,
+    shaders: HashMap<String, (String, u64)>,
+    variants: HashMap<ShaderKey, String>,
+    registrations: u64,
+    modules_changed: u64
+}
+
+impl Default for ShaderComposer {
+    fn default() -> Self { Self::new() }
+}
+
+impl ShaderComposer {
+    /// Creates a new composer without any modules or shaders.
+    pub fn new() -> Self {
+        let mut composer = Composer::default();
+        composer.capabilities = naga::valid::Capabilities::all();
+        Self { composer, shaders: HashMap::new(), variants: HashMap::new(), registrations: 0, modules_changed: 0 }
+    }
+
+    /// Registers a module that shaders and modules registered after it can import.  A module registered again with the same name replaces the old one.
+    ///
+    /// Arguments:
+    /// * name: &str - The name the module is imported with.
+    /// * source: &str - The WGSL source of the module.
+    ///
+    /// Returns a result that is an error if the module could not be parsed or imports a module that is not registered.
+    pub fn add_module(&mut self, name: &str, source: &str) -> Result<()> {
+        let added = self.composer.add_composable_module(ComposableModuleDescriptor {
+            source,
+            file_path: name,
+            as_name: Some(name.to_string()),
+            ..Default::default()
+        }).map(|_| ());
+        added.map_err(|error| anyhow!(error.emit_to_string(&self.composer)))?;
+        self.variants.clear();
+        self.registrations += 1;
+        self.modules_changed = self.registrations;
+        Ok(())
+    }
+
+    /// Returns true if a module with the given name is registered.
+    pub fn has_module(&self, name: &str) -> bool { self.composer.contains_module(name) }
+
+    /// Registers a shader that variants can be composed from.  A shader registered again with the same name replaces the old one.
+    ///
+    /// Arguments:
+    /// * name: &str - The name of the shader, used as the `shader` of a `ShaderKey`.
+    /// * source: &str - The WGSL source of the shader.
+    pub fn add_shader(&mut self, name: &str, source: &str) {
+        self.registrations += 1;
+        self.shaders.insert(name.to_string(), (source.to_string(), self.registrations));
+        self.variants.retain(|key, _| key.shader != name);
+    }
+
+    /// Returns true if a shader with the given name is registered.
+    pub fn has_shader(&self, name: &str) -> bool { self.shaders.contains_key(name) }
+
+    /// A number that changes every time the shader with the given name or any module is registered, so pipelines created from its variants can tell when they must be recreated.
+    ///
+    /// Arguments:
+    /// * name: &str - The name of the shader.
+    ///
+    /// Returns an option containing the generation, or none if no shader with the given name is registered.
+    pub fn generation(&self, name: &str) -> Option<u64> { self.shaders.get(name).map(|(_, registered)| (*registered).max(self.modules_changed)) }
+
+    /// Composes the variant of a shader with the given key, or returns the cached source if it was composed before.
+    ///
+    /// Arguments:
+    /// * key: &ShaderKey - The shader and the defines to compose it with.
+    ///
+    /// Returns a result containing the composed and validated WGSL source, or an error if the shader is not registered, imports a module that is not registered, or is invalid with the given defines.
+    pub fn compose(&mut self, key: &ShaderKey) -> Result<&str> {
+        if !self.variants.contains_key(key) {
+            let (source, _) = self.shaders.get(&key.shader).with_context(|| format!("No shader named {} is registered", key.shader))?;
+            let name = key.to_string();
+            let module = self.composer.make_naga_module(NagaModuleDescriptor {
+                source,
+                file_path: &name,
+                shader_defs: key.defines.iter().map(|(name, value)| (name.clone(), (*value).into())).collect(),
+                ..Default::default()
+            }).map_err(|error| anyhow!(error.emit_to_string(&self.composer)))?;
+            let source = validate_and_write(&module, &name)?;
+            self.variants.insert(key.clone(), source);
+        }
+        Ok(&self.variants[key])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the given files into a new empty directory for one test, returning the directory.
+    fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("forte_composer_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (path, source) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        dir.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn compose_file_resolves_nested_imports() {
+        let dir = fixture("nested", &[
+            ("main.wgsl", "#import ./lib/outer.wgsl as Outer\n\nfn main_value() -> f32 { return Outer::outer_value(); }\n"),
+            ("lib/outer.wgsl", "#import ./inner/inner.wgsl as Inner\n\nfn outer_value() -> f32 { return Inner::inner_value() * 2.0; }\n"),
+            ("lib/inner/inner.wgsl", "fn inner_value() -> f32 { return 21.0; }\n")
+        ]);
+        let composed = compose_file(dir.join("main.wgsl")).unwrap();
+
+        // the shader comes first, then its imports with the imports they depend on first
+        assert_eq!(composed.files, vec![dir.join("main.wgsl"), dir.join("lib/inner/inner.wgsl"), dir.join("lib/outer.wgsl")]);
+        assert!(composed.source.contains("21f"), "{}", composed.source);
+        assert!(composed.source.contains("fn main_value"), "{}", composed.source);
+    }
+
+    #[test]
+    fn compose_file_shares_files_imported_twice() {
+        let dir = fixture("shared", &[
+            ("main.wgsl", "#import ./a.wgsl as A\n#import ./common.wgsl as Common\n\nfn main_value() -> f32 { return A::a_value() + Common::common_value(); }\n"),
+            ("a.wgsl", "#import ./common.wgsl as Common\n\nfn a_value() -> f32 { return Common::common_value(); }\n"),
+            ("common.wgsl", "fn common_value() -> f32 { return 3.0; }\n")
+        ]);
+        let composed = compose_file(dir.join("main.wgsl")).unwrap();
+        assert_eq!(composed.files, vec![dir.join("main.wgsl"), dir.join("common.wgsl"), dir.join("a.wgsl")]);
+        assert_eq!(composed.source.matches("3f").count(), 1, "{}", composed.source);
+    }
+
+    #[test]
+    fn compose_file_renames_imports_with_same_file_stem() {
+        let dir = fixture("stem", &[
+            ("main.wgsl", "#import ./first/common.wgsl as First\n#import ./second/common.wgsl as Second\n\nfn main_value() -> f32 { return First::value() + Second::value(); }\n"),
+            ("first/common.wgsl", "fn value() -> f32 { return 5.0; }\n"),
+            ("second/common.wgsl", "fn value() -> f32 { return 7.0; }\n")
+        ]);
+        let composed = compose_file(dir.join("main.wgsl")).unwrap();
+        assert_eq!(composed.files, vec![dir.join("main.wgsl"), dir.join("first/common.wgsl"), dir.join("second/common.wgsl")]);
+        assert!(composed.source.contains("5f") && composed.source.contains("7f"), "{}", composed.source);
+
+        // the second file is imported as a module with a numbered name
+        let names = HashMap::from([(dir.join("first/common.wgsl"), "common".to_string()), (dir.join("second/common.wgsl"), "common_1".to_string())]);
+        let replaced = replace_imports(&dir.join("main.wgsl"), &std::fs::read_to_string(dir.join("main.wgsl")).unwrap(), &names);
+        assert!(replaced.starts_with("#import common as First\n#import common_1 as Second\n"), "{}", replaced);
+    }
+
+    #[test]
+    fn compose_file_rejects_import_cycles() {
+        let dir = fixture("cycle", &[
+            ("main.wgsl", "#import ./a.wgsl as A\n\nfn main_value() -> f32 { return A::a_value(); }\n"),
+            ("a.wgsl", "#import ./b.wgsl as B\n\nfn a_value() -> f32 { return B::b_value(); }\n"),
+            ("b.wgsl", "#import ./a.wgsl as A\n\nfn b_value() -> f32 { return A::a_value(); }\n")
+        ]);
+        let error = compose_file(dir.join("main.wgsl")).unwrap_err();
+        assert!(error.to_string().contains("cycle"), "{:#}", error);
+    }
+
+    #[test]
+    fn compose_file_reports_missing_files_and_invalid_shaders() {
+        let dir = fixture("errors", &[
+            ("missing.wgsl", "#import ./nowhere.wgsl as Nowhere\n\nfn value() -> f32 { return Nowhere::value(); }\n"),
+            ("invalid.wgsl", "fn value() -> f32 { return true; }\n")
+        ]);
+        let error = compose_file(dir.join("missing.wgsl")).unwrap_err();
+        assert!(error.to_string().contains("nowhere.wgsl"), "{:#}", error);
+        assert!(compose_file(dir.join("invalid.wgsl")).is_err());
+        assert!(compose_file(dir.join("not_a_file.wgsl")).is_err());
+    }
+
+    #[test]
+    fn import_paths_only_finds_file_imports() {
+        let source = "#import ./a.wgsl as A\n#import registered_module\n  #import ../b.wgsl as B\nfn value() {}\n";
+        assert_eq!(import_paths(source).collect::<Vec<_>>(), vec!["./a.wgsl", "../b.wgsl"]);
+    }
+
+    #[test]
+    fn compose_file_composes_engine_shaders() {
+        for shader in ["shaders/gltf.wgsl", "shaders/gltf_skinned_morph.wgsl", "shaders/post_vignette.wgsl"] {
+            let composed = compose_file(crate::render::pipelines::engine_shader_path(shader)).unwrap();
+            assert!(composed.files.len() > 1, "{} has no imports", shader);
+        }
+    }
+
+    const VARIANT_SHADER: &str = "
+fn value() -> f32 {
+#ifdef DOUBLE
+    return 2.0 * f32(#COUNT);
+#else
+    return 1.0;
+#endif
+}
+";
+
+    const MODULE_SHADER: &str = "
+#import numbers
+
+fn value() -> f32 { return numbers::number(); }
+";
+
+    #[test]
+    fn shader_key_display_lists_sorted_defines() {
+        assert_eq!(ShaderKey::new("forte.gltf").to_string(), "forte.gltf");
+
+        let key = ShaderKey::new("forte.gltf")
+            .with_define("MAX_LIGHTS", ShaderDefine::UInt(4))
+            .with_flag("NORMAL_MAP")
+            .with_define("BIAS", ShaderDefine::Int(-2))
+            .with_define("DEBUG", ShaderDefine::Bool(false));
+        assert_eq!(key.to_string(), "forte.gltf[BIAS=-2, DEBUG=false, MAX_LIGHTS=4, NORMAL_MAP]");
+
+        // the order defines are set in does not change the key
+        let reordered = ShaderKey::new("forte.gltf")
+            .with_define("DEBUG", ShaderDefine::Bool(false))
+            .with_flag("NORMAL_MAP")
+            .with_define("BIAS", ShaderDefine::Int(-2))
+            .with_define("MAX_LIGHTS", ShaderDefine::UInt(4));
+        assert_eq!(key, reordered);
+        assert_eq!(key.to_string(), reordered.to_string());
+    }
+
+    #[test]
+    fn defines_compose_different_variants() {
+        let mut composer = ShaderComposer::new();
+        composer.add_shader("variants", VARIANT_SHADER);
+
+        let plain = composer.compose(&ShaderKey::new("variants")).unwrap().to_string();
+        let doubled = composer.compose(&ShaderKey::new("variants").with_flag("DOUBLE").with_define("COUNT", ShaderDefine::UInt(3))).unwrap().to_string();
+        assert!(plain.contains("return 1f;"), "{}", plain);
+        assert!(doubled.contains("return 6f;"), "{}", doubled);
+
+        // the value of a define is substituted
+        let tenfold = composer.compose(&ShaderKey::new("variants").with_flag("DOUBLE").with_define("COUNT", ShaderDefine::UInt(5))).unwrap().to_string();
+        assert!(tenfold.contains("return 10f;"), "{}", tenfold);
+    }
+
+    #[test]
+    fn compose_caches_variants() {
+        let mut composer = ShaderComposer::new();
+        composer.add_shader("variants", VARIANT_SHADER);
+        let key = ShaderKey::new("variants").with_flag("DOUBLE").with_define("COUNT", ShaderDefine::UInt(2));
+
+        let first = composer.compose(&key).unwrap().as_ptr();
+        let other = composer.compose(&ShaderKey::new("variants")).unwrap().as_ptr();
+        assert_ne!(first, other);
+        assert_eq!(composer.compose(&key).unwrap().as_ptr(), first);
+    }
+
+    #[test]
+    fn add_shader_replaces_cached_variants() {
+        let mut composer = ShaderComposer::new();
+        composer.add_shader("variants", VARIANT_SHADER);
+        composer.add_shader("other", "fn other() -> f32 { return 4.0; }");
+        let other = composer.compose(&ShaderKey::new("other")).unwrap().as_ptr();
+        assert!(composer.compose(&ShaderKey::new("variants")).unwrap().contains("return 1f;"));
+
+        composer.add_shader("variants", "fn value() -> f32 { return 6.0; }");
+        assert!(composer.compose(&ShaderKey::new("variants")).unwrap().contains("return 6f;"));
+
+        // variants of other shaders are kept
+        assert_eq!(composer.compose(&ShaderKey::new("other")).unwrap().as_ptr(), other);
+    }
+
+    #[test]
+    fn add_module_replaces_every_cached_variant() {
+        let mut composer = ShaderComposer::new();
+        composer.add_module("numbers", "fn number() -> f32 { return 8.0; }").unwrap();
+        composer.add_shader("uses_module", MODULE_SHADER);
+        assert!(composer.has_module("numbers"));
+        assert!(composer.compose(&ShaderKey::new("uses_module")).unwrap().contains("8f"));
+
+        composer.add_module("numbers", "fn number() -> f32 { return 9.0; }").unwrap();
+        let source = composer.compose(&ShaderKey::new("uses_module")).unwrap();
+        assert!(source.contains("9f") && !source.contains("8f"), "{}", source);
+    }
+
+    #[test]
+    fn generation_changes_with_shader_and_modules() {
+        let mut composer = ShaderComposer::new();
+        assert_eq!(composer.generation("first"), None);
+
+        composer.add_shader("first", VARIANT_SHADER);
+        composer.add_shader("second", VARIANT_SHADER);
+        let first = composer.generation("first").unwrap();
+        let second = composer.generation("second").unwrap();
+
+        // registering a shader only changes its own generation
+        composer.add_shader("first", VARIANT_SHADER);
+        assert!(composer.generation("first").unwrap() > first);
+        assert_eq!(composer.generation("second"), Some(second));
+
+        // registering a module changes every shader's generation
+        let first = composer.generation("first").unwrap();
+        composer.add_module("numbers", "fn number() -> f32 { return 1.0; }").unwrap();
+        assert!(composer.generation("first").unwrap() > first);
+        assert!(composer.generation("second").unwrap() > second);
+        assert_eq!(composer.generation("first"), composer.generation("second"));
+    }
+
+    #[test]
+    fn compose_reports_errors() {
+        let mut composer = ShaderComposer::new();
+        assert!(!composer.has_shader("missing"));
+        assert!(composer.compose(&ShaderKey::new("missing")).is_err());
+
+        // the module is not registered
+        composer.add_shader("uses_module", MODULE_SHADER);
+        assert!(composer.compose(&ShaderKey::new("uses_module")).is_err());
+
+        // the define is used without a value
+        composer.add_shader("variants", VARIANT_SHADER);
+        assert!(composer.compose(&ShaderKey::new("variants").with_flag("DOUBLE")).is_err());
+        assert!(composer.add_module("broken", "#import unregistered\n\nfn number() -> f32 { return unregistered::number(); }").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use wgpu::BindGroupLayout;
+
+use crate::render::render_engine::RenderEngine;
+
+/// The forte representation of a compute pipeline.  This is effectively a compute shader with the layouts of the resources it binds.  Like render pipelines, these are cached by the render engine, see `RenderEngine::verify_compute_pipeline_exists`.
+#[derive(Debug)]
+pub struct ComputePipeline {
+    pub compute_pipeline: wgpu::ComputePipeline
+}
+
+impl ComputePipeline {
+    /// Create a new compute pipeline with a given name, render engine, shader code, bind group layouts and entry point.
+    /// 
+    /// Arguments:
+    /// * name: &str - The name of the pipeline for debugging purposes.
+    /// * engine: &RenderEngine - The render engine that will be used to create the pipeline.
+    /// * shader_code: &str - The WGSL shader code for this pipeline.
+    /// * layouts: &[wgpu::BindGroupLayout] - An array of bind group layouts for the shader.
+    /// * entry_point: &str - The compute entry point of the shader, like `cs_main`.
+    /// 
+    /// Returns the new compute pipeline.
+    pub fn new(
+        name: &str,
+        engine: &RenderEngine,
+        shader_code: &str,
+        layouts: &[&wgpu::BindGroupLayout],
+        entry_point: &str
+    ) -> Self {
+        let shader = engine.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(name),
+            source: wgpu::ShaderSource::Wgsl(shader_code.into())
+        });
+        let layout = engine.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(name),
+            bind_group_layouts: layouts,
+            push_constant_ranges: &[]
+        });
+        let compute_pipeline = engine.device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(name),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point
+        });
+        Self { compute_pipeline }
+    }
+
+    /// Get a bind group at a given index
+    /// 
+    /// Arguments:
+    /// * index: u32 - the index of the bind group
+    /// 
+    /// Returns the bind group layout
+    pub fn get_layout(&self, index: u32) -> BindGroupLayout {
+        self.compute_pipeline.get_bind_group_layout(index)
+    }
+
+    /// Binds this pipeline to the given compute pass.
+    /// 
+    /// Arguments:
+    /// * &self - The pipeline to bind.
+    /// * pass: &mut ComputePass - The compute pass to bind this pipeline too.
+    pub fn bind<'pass>(
+        &'pass self,
+        pass: &mut wgpu::ComputePass<'pass>
+    ) {
+        pass.set_pipeline(&self.compute_pipeline);
+    }
+}
+
+/// Used to create and initialize a compute pipeline, like `create_pipeline!` does for render pipelines.
+/// 
+/// Example:
+/// ```rust
+/// create_compute_pipeline! {
+///     NAME => "forte.light_clusters",
+///     ENGINE => engine,
+///     SHADER => light_clusters_shader::SOURCE,
+///     BIND_GROUPS => [LightClusters::COMPUTE_BIND_LAYOUT],
+///     ENTRY_POINT => "cs_main"
+/// }
+/// ```
+#[macro_export]
+macro_rules! create_compute_pipeline {
+    {
+        NAME => $name: expr,
+        ENGINE => $engine: expr,
+        SHADER => $shader: expr,
+        BIND_GROUPS => [$($bind_group:expr),*],
+        ENTRY_POINT => $entry_point: expr
+    } => {
+        $engine.verify_compute_pipeline_exists($name, |engine| {
+            $crate::render::pipelines::compute::ComputePipeline::new(
+                $name, engine, $shader,
+                &[$(&engine.device.create_bind_group_layout(&$bind_group)),*],
+                $entry_point
+            )
+        });
+    };
+}
@@ -0,0 +1,601 @@
+use std::{path::{Path, PathBuf}, time::SystemTime};
+
+use anyhow::*;
+use wgpu::BindGroupLayout;
+
+use crate::{log, primitives::textures::depth_textures::DepthTexture, render::{graph::DepthConvention, render_engine::RenderEngine}};
+
+pub mod composer;
+pub mod compute;
+
+/// The forte representation of a render pipeline.  This is effectively a shader with some necessary WGPU layouts.
+#[derive(Debug)]
+pub struct Pipeline {
+    pub render_pipeline: wgpu::RenderPipeline,
+    recipe: PipelineRecipe,
+    follows_sample_count: bool,
+    follows_depth_convention: bool
+}
+
+/// How the colors a pipeline outputs are blended with the colors already in its targets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The output replaces what is in the target.
+    Opaque,
+
+    /// The output is blended over the target by its alpha.
+    #[default]
+    Alpha,
+
+    /// The output is added to the target, useful for particles and glows.
+    Additive,
+
+    /// The output is blended over the target by its alpha, with its color already multiplied by its alpha.
+    Premultiplied
+}
+
+impl BlendMode {
+    /// The WGPU blend state of this blend mode, none if the output replaces the target.
+    pub fn state(&self) -> Option<wgpu::BlendState> {
+        match self {
+            BlendMode::Opaque => None,
+            BlendMode::Alpha => Some(wgpu::BlendState::ALPHA_BLENDING),
+            BlendMode::Additive => {
+                let add = wgpu::BlendComponent { src_factor: wgpu::BlendFactor::One, dst_factor: wgpu::BlendFactor::One, operation: wgpu::BlendOperation::Add };
+                Some(wgpu::BlendState { color: add, alpha: add })
+            },
+            BlendMode::Premultiplied => Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING)
+        }
+    }
+}
+
+/// A color target of a pipeline.
+///
+/// Arguments:
+/// * format: Option<wgpu::TextureFormat> - The format of the target, or none for the render engine's scene format.
+/// * blend: BlendMode - How the output is blended with what is already in the target.
+/// * write_mask: wgpu::ColorWrites - Which channels of the target are written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorTarget {
+    pub format: Option<wgpu::TextureFormat>,
+    pub blend: BlendMode,
+    pub write_mask: wgpu::ColorWrites
+}
+
+impl Default for ColorTarget {
+    fn default() -> Self { Self { format: None, blend: BlendMode::Alpha, write_mask: wgpu::ColorWrites::ALL } }
+}
+
+impl ColorTarget {
+    /// Creates an alpha blended color target with the given format.
+    pub fn new(format: wgpu::TextureFormat) -> Self { Self { format: Some(format), ..Default::default() } }
+
+    /// Sets how the output is blended with what is already in this target.
+    pub fn with_blend(mut self, blend: BlendMode) -> Self { self.blend = blend; self }
+}
+
+/// Builds a `Pipeline` with full control over its state.  By default, a pipeline draws alpha blended triangle lists with back faces culled into one target with the render engine's scene format and sample count, using the `vs_main` and `fs_main` entry points and no depth.
+///
+/// Example:
+/// ```rust
+/// let pipeline = PipelineBuilder::new("particles", include_str!("particles.wgsl"))
+///     .with_buffers(&[Vertex::desc()])
+///     .with_layouts(&[&engine.device.create_bind_group_layout(&Camera::BIND_LAYOUT)])
+///     .with_blend(BlendMode::Additive)
+///     .with_cull_mode(None)
+///     .with_depth(true)
+///     .with_depth_write(false)
+///     .build(engine);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PipelineBuilder<'a> {
+    name: &'a str,
+    shader_code: &'a str,
+    buffers: &'a [wgpu::VertexBufferLayout<'a>],
+    layouts: &'a [&'a wgpu::BindGroupLayout],
+    push_constants: &'a [wgpu::PushConstantRange],
+    vertex_entry: &'a str,
+    fragment_entry: Option<&'a str>,
+    targets: Vec<ColorTarget>,
+    primitive: wgpu::PrimitiveState,
+    use_depth: bool,
+    depth: wgpu::DepthStencilState,
+    follows_depth_convention: bool,
+    single_sampled: bool,
+    source_path: Option<PathBuf>
+}
+
+impl<'a> PipelineBuilder<'a> {
+    /// Starts building a pipeline with the default state.
+    ///
+    /// Arguments:
+    /// * name: &str - The name of the pipeline for debugging purposes.
+    /// * shader_code: &str - The WGSL shader code for this pipeline.
+    pub fn new(name: &'a str, shader_code: &'a str) -> Self {
+        Self {
+            name, shader_code,
+            buffers: &[],
+            layouts: &[],
+            push_constants: &[],
+            vertex_entry: "vs_main",
+            fragment_entry: Some("fs_main"),
+            targets: vec![ColorTarget::default()],
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            use_depth: false,
+            depth: wgpu::DepthStencilState {
+                format: DepthTexture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default()
+            },
+            follows_depth_convention: true,
+            single_sampled: false,
+            source_path: None
+        }
+    }
+
+    /// Sets the vertex buffer layouts of the shader.
+    pub fn with_buffers(mut self, buffers: &'a [wgpu::VertexBufferLayout<'a>]) -> Self { self.buffers = buffers; self }
+
+    /// Sets the bind group layouts of the shader.
+    pub fn with_layouts(mut self, layouts: &'a [&'a wgpu::BindGroupLayout]) -> Self { self.layouts = layouts; self }
+
+    /// Sets the push constant ranges of the shader, this needs the device to support `wgpu::Features::PUSH_CONSTANTS`.
+    pub fn with_push_constants(mut self, push_constants: &'a [wgpu::PushConstantRange]) -> Self { self.push_constants = push_constants; self }
+
+    /// Sets the name of the vertex shader's entry point, `vs_main` by default.
+    pub fn with_vertex_entry(mut self, entry_point: &'a str) -> Self { self.vertex_entry = entry_point; self }
+
+    /// Sets the name of the fragment shader's entry point, `fs_main` by default, or none for pipelines that only write depth.
+    pub fn with_fragment_entry(mut self, entry_point: Option<&'a str>) -> Self { self.fragment_entry = entry_point; self }
+
+    /// Sets the format of the first color target.
+    pub fn with_format(mut self, format: wgpu::TextureFormat) -> Self { self.first_target().format = Some(format); self }
+
+    /// Sets how the output is blended with what is already in the first color target.
+    pub fn with_blend(mut self, blend: BlendMode) -> Self { self.first_target().blend = blend; self }
+
+    /// Adds another color target, rendered too from the next `@location` of the fragment shader's output.
+    pub fn with_target(mut self, target: ColorTarget) -> Self { self.targets.push(target); self }
+
+    /// Replaces every color target.
+    pub fn with_targets(mut self, targets: Vec<ColorTarget>) -> Self { self.targets = targets; self }
+
+    /// Sets how vertices are assembled into primitives, like lines, points or strips.
+    pub fn with_topology(mut self, topology: wgpu::PrimitiveTopology) -> Self { self.primitive.topology = topology; self }
+
+    /// Sets the index format of strip topologies drawn with an index buffer, so the maximum index restarts the strip.
+    pub fn with_strip_index_format(mut self, format: Option<wgpu::IndexFormat>) -> Self { self.primitive.strip_index_format = format; self }
+
+    /// Sets which faces are culled, none draws both sides.
+    pub fn with_cull_mode(mut self, cull_mode: Option<wgpu::Face>) -> Self { self.primitive.cull_mode = cull_mode; self }
+
+    /// Sets which winding order faces the camera.
+    pub fn with_front_face(mut self, front_face: wgpu::FrontFace) -> Self { self.primitive.front_face = front_face; self }
+
+    /// Sets how triangles are rasterized.  Wireframe and point modes fall back to filled triangles on devices without `wgpu::Features::POLYGON_MODE_LINE` or `wgpu::Features::POLYGON_MODE_POINT`.
+    pub fn with_polygon_mode(mut self, polygon_mode: wgpu::PolygonMode) -> Self { self.primitive.polygon_mode = polygon_mode; self }
+
+    /// Sets if the pipeline uses a depth texture.
+    pub fn with_depth(mut self, use_depth: bool) -> Self { self.use_depth = use_depth; self }
+
+    /// Sets the format of the depth texture, `DepthTexture::DEPTH_FORMAT` by default.  Use a format with a stencil aspect when using a stencil state.
+    pub fn with_depth_format(mut self, format: wgpu::TextureFormat) -> Self { self.depth.format = format; self }
+
+    /// Sets how the depth of each fragment is compared against the depth texture.  By default, closer fragments pass, comparing with `Less` or `Greater` to follow the render engine's depth convention (see `RenderEngine::set_depth_convention`).
+    pub fn with_depth_compare(mut self, compare: wgpu::CompareFunction) -> Self { self.depth.depth_compare = compare; self.follows_depth_convention = false; self }
+
+    /// Sets if fragments write their depth to the depth texture, true by default.
+    pub fn with_depth_write(mut self, write: bool) -> Self { self.depth.depth_write_enabled = write; self }
+
+    /// Sets the stencil state used with the depth texture.  The depth texture must have a stencil, like a render graph texture with the format `DepthTexture::DEPTH_STENCIL_FORMAT`, see `with_depth_format`.
+    pub fn with_stencil(mut self, stencil: wgpu::StencilState) -> Self { self.depth.stencil = stencil; self }
+
+    /// Sets the bias added to the depth of each fragment.
+    pub fn with_depth_bias(mut self, bias: wgpu::DepthBiasState) -> Self { self.depth.bias = bias; self }
+
+    /// Makes the pipeline always render with one sample per pixel, instead of following the render engine's sample count.  Use this for passes recorded onto the frame's encoder into textures outside of the render graph.
+    pub fn single_sampled(mut self) -> Self { self.single_sampled = true; self }
+
+    /// Sets the WGSL file the shader code was loaded from, so the pipeline is recreated when the file or anything it imports changes while shader hot reloading is enabled (see `RenderEngine::set_shader_hot_reload`).
+    pub fn with_source_path(mut self, path: impl Into<PathBuf>) -> Self { self.source_path = Some(path.into()); self }
+
+    /// Creates the pipeline.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine that will be used to create the pipeline.
+    ///
+    /// Returns the new pipeline.
+    pub fn build(self, engine: &RenderEngine) -> Pipeline {
+        // create shader
+        let shader = engine.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(self.name),
+            source: wgpu::ShaderSource::Wgsl(self.shader_code.into())
+        });
+
+        // create layout
+        let layout = engine.device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some(format!("{}_layout", self.name).as_str()),
+                bind_group_layouts: self.layouts,
+                push_constant_ranges: self.push_constants
+            }
+        );
+
+        // wireframe and point rasterization are optional features
+        let mut primitive = self.primitive;
+        let required = match primitive.polygon_mode {
+            wgpu::PolygonMode::Fill => wgpu::Features::empty(),
+            wgpu::PolygonMode::Line => wgpu::Features::POLYGON_MODE_LINE,
+            wgpu::PolygonMode::Point => wgpu::Features::POLYGON_MODE_POINT
+        };
+        if !engine.device.features().contains(required) {
+            log!("Pipeline {} cannot use polygon mode {:?} on this device, drawing filled polygons instead.", self.name, primitive.polygon_mode);
+            primitive.polygon_mode = wgpu::PolygonMode::Fill;
+        }
+
+        // unless given a compare function, closer fragments pass with the engine's depth convention
+        let mut depth = self.depth;
+        if self.follows_depth_convention { depth.depth_compare = engine.depth_convention().compare(); }
+
+        // keep what the pipeline was created from so it can be recreated
+        let recipe = PipelineRecipe {
+            name: self.name.to_string(),
+            shader, layout,
+            buffers: self.buffers.iter().map(|buffer| (buffer.array_stride, buffer.step_mode, buffer.attributes.to_vec())).collect(),
+            vertex_entry: self.vertex_entry.to_string(),
+            fragment_entry: self.fragment_entry.map(str::to_string),
+            targets: self.targets.iter().map(|target| Some(wgpu::ColorTargetState {
+                format: target.format.unwrap_or(engine.scene_format()),
+                blend: target.blend.state(),
+                write_mask: target.write_mask
+            })).collect(),
+            primitive,
+            depth_stencil: self.use_depth.then_some(depth),
+            sample_count: if self.single_sampled { 1 } else { engine.sample_count() },
+            source_path: self.source_path,
+            watched: Vec::new()
+        };
+        Pipeline { render_pipeline: recipe.create(&engine.device), recipe, follows_sample_count: !self.single_sampled, follows_depth_convention: self.follows_depth_convention }
+    }
+
+    /// The first color target, added if there are none.
+    fn first_target(&mut self) -> &mut ColorTarget {
+        if self.targets.is_empty() { self.targets.push(ColorTarget::default()); }
+        &mut self.targets[0]
+    }
+}
+
+/// What a pipeline was created from, so it can be recreated when the sample count, depth convention or its source file changes.
+#[derive(Debug)]
+struct PipelineRecipe {
+    name: String,
+    shader: wgpu::ShaderModule,
+    layout: wgpu::PipelineLayout,
+    buffers: Vec<(wgpu::BufferAddress, wgpu::VertexStepMode, Vec<wgpu::VertexAttribute>)>,
+    vertex_entry: String,
+    fragment_entry: Option<String>,
+    targets: Vec<Option<wgpu::ColorTargetState>>,
+    primitive: wgpu::PrimitiveState,
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    sample_count: u32,
+    source_path: Option<PathBuf>,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    watched: Vec<(PathBuf, Option<SystemTime>)>
+}
+
+/// The path of one of the engine's own shader files, relative to the root of the engine's source, so they can be hot reloaded while developing the engine.
+pub(crate) fn engine_shader_path(path: &str) -> PathBuf { Path::new(env!("CARGO_MANIFEST_DIR")).join(path) }
+
+impl Pipeline {
+    /// Create a new shader with a given name, render engine, shader code, buffer layouts, and bind group layouts.  The pipeline renders to the render engine's scene format (see `RenderEngine::scene_format`) with its sample count (see `RenderEngine::sample_count`).  Use a `PipelineBuilder` to change any other state.
+    /// 
+    /// Arguments:
+    /// * name: &str - The name of the pipeline for debugging purposes.
+    /// * engine: &RenderEngine - The render engine that will be used to create the pipeline.
+    /// * shader_code: &str - The WGSL shader code for this pipeline.
+    /// * buffers: &[wgpu::VertexBufferLayout] - An array of vertex buffer layouts for the shader.
+    /// * layouts: &[wgpu::BindGroupLayout] - An array of bind group layouts for the shader.
+    /// 
+    /// Returns the new pipeline that is generated from the above arguments.
+    /// 
+    /// Example:
+    /// ```rust
+    /// Pipeline::new(
+    ///     "shader", 
+    ///     engine, 
+    ///     include_str!("path_to_file"),
+    ///     &[
+    ///         Vertex::desc(), 
+    ///         TransformRaw::desc()
+    ///     ],
+    ///     &[
+    ///         &engine.device.create_bind_group_layout(&Camera::BIND_LAYOUT),
+    ///         &engine.device.create_bind_group_layout(&Texture::BIND_LAYOUT),
+    ///         &engine.device.create_bind_group_layout(&LightUniform::BIND_LAYOUT)
+    ///     ]
+    /// );
+    /// ```
+    pub fn new(
+        name: &str, 
+        engine: &RenderEngine, 
+        shader_code: &str, 
+        buffers: &[wgpu::VertexBufferLayout], 
+        layouts: &[&wgpu::BindGroupLayout],
+        use_depth: bool
+    ) -> Self {
+        Self::new_with_format(name, engine, shader_code, buffers, layouts, use_depth, engine.scene_format())
+    }
+
+    /// Create a new shader like `Pipeline::new` that renders to the given color format instead of the render engine's scene format.  For example, pipelines that draw straight to the surface use `engine.config.format`.
+    /// 
+    /// Arguments:
+    /// * name: &str - The name of the pipeline for debugging purposes.
+    /// * engine: &RenderEngine - The render engine that will be used to create the pipeline.
+    /// * shader_code: &str - The WGSL shader code for this pipeline.
+    /// * buffers: &[wgpu::VertexBufferLayout] - An array of vertex buffer layouts for the shader.
+    /// * layouts: &[wgpu::BindGroupLayout] - An array of bind group layouts for the shader.
+    /// * use_depth: bool - True if the pipeline reads and writes the depth texture.
+    /// * format: wgpu::TextureFormat - The format of the color target the pipeline renders too.
+    /// 
+    /// Returns the new pipeline that is generated from the above arguments.
+    pub fn new_with_format(
+        name: &str, 
+        engine: &RenderEngine, 
+        shader_code: &str, 
+        buffers: &[wgpu::VertexBufferLayout], 
+        layouts: &[&wgpu::BindGroupLayout],
+        use_depth: bool,
+        format: wgpu::TextureFormat
+    ) -> Self {
+        PipelineBuilder::new(name, shader_code)
+            .with_buffers(buffers)
+            .with_layouts(layouts)
+            .with_format(format)
+            .with_depth(use_depth)
+            .build(engine)
+    }
+
+    /// Create a new shader like `Pipeline::new_with_format` that always renders with one sample per pixel, instead of following the render engine's sample count.  Use this for passes recorded onto the frame's encoder into textures outside of the render graph.
+    /// 
+    /// Arguments:
+    /// * name: &str - The name of the pipeline for debugging purposes.
+    /// * engine: &RenderEngine - The render engine that will be used to create the pipeline.
+    /// * shader_code: &str - The WGSL shader code for this pipeline.
+    /// * buffers: &[wgpu::VertexBufferLayout] - An array of vertex buffer layouts for the shader.
+    /// * layouts: &[wgpu::BindGroupLayout] - An array of bind group layouts for the shader.
+    /// * use_depth: bool - True if the pipeline reads and writes a depth texture.
+    /// * format: wgpu::TextureFormat - The format of the color target the pipeline renders too.
+    /// 
+    /// Returns the new pipeline that is generated from the above arguments.
+    pub fn new_single_sampled(
+        name: &str, 
+        engine: &RenderEngine, 
+        shader_code: &str, 
+        buffers: &[wgpu::VertexBufferLayout], 
+        layouts: &[&wgpu::BindGroupLayout],
+        use_depth: bool,
+        format: wgpu::TextureFormat
+    ) -> Self {
+        PipelineBuilder::new(name, shader_code)
+            .with_buffers(buffers)
+            .with_layouts(layouts)
+            .with_format(format)
+            .with_depth(use_depth)
+            .single_sampled()
+            .build(engine)
+    }
+
+    /// Recreates this pipeline with the given sample count if it follows the render engine's sample count.  This is called on every cached pipeline by `RenderEngine::set_sample_count`.
+    ///
+    /// Arguments:
+    /// * device: &wgpu::Device - The device to recreate the pipeline with.
+    /// * sample_count: u32 - The new number of samples per pixel.
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        if !self.follows_sample_count || self.recipe.sample_count == sample_count { return }
+        self.recipe.sample_count = sample_count;
+        self.render_pipeline = self.recipe.create(device);
+    }
+
+    /// Recreates this pipeline to compare depth with the given depth convention if it uses depth and was not given its own compare function.  This is called on every cached pipeline by `RenderEngine::set_depth_convention`.
+    ///
+    /// Arguments:
+    /// * device: &wgpu::Device - The device to recreate the pipeline with.
+    /// * convention: DepthConvention - The new depth convention.
+    pub fn set_depth_convention(&mut self, device: &wgpu::Device, convention: DepthConvention) {
+        if !self.follows_depth_convention { return }
+        let Some(depth) = self.recipe.depth_stencil.as_mut() else { return };
+        if depth.depth_compare == convention.compare() { return }
+
+        depth.depth_compare = convention.compare();
+        self.render_pipeline = self.recipe.create(device);
+    }
+
+    /// The WGSL file this pipeline's shader was loaded from, if it was given one with `PipelineBuilder::with_source_path`.
+    pub fn source_path(&self) -> Option<&Path> { self.recipe.source_path.as_deref() }
+
+    /// Recreates this pipeline from its source file if the file or any file it imports changed since the last check.  The first check only remembers when the files were modified.  If the new shader cannot be composed, is invalid or does not match the pipeline's layouts, the pipeline is kept as it was.  On the web there are no shader files to watch, so this never recreates the pipeline.
+    ///
+    /// Arguments:
+    /// * device: &wgpu::Device - The device to recreate the pipeline with.
+    ///
+    /// Returns a result containing true if the pipeline was recreated, or an error explaining why the changed shader could not be used.
+    #[cfg(target_arch = "wasm32")]
+    pub fn reload_if_changed(&mut self, _device: &wgpu::Device) -> Result<bool> { Ok(false) }
+
+    /// Recreates this pipeline from its source file if the file or any file it imports changed since the last check.  The first check only remembers when the files were modified.  If the new shader cannot be composed, is invalid or does not match the pipeline's layouts, the pipeline is kept as it was.  On the web there are no shader files to watch, so this never recreates the pipeline.
+    ///
+    /// Arguments:
+    /// * device: &wgpu::Device - The device to recreate the pipeline with.
+    ///
+    /// Returns a result containing true if the pipeline was recreated, or an error explaining why the changed shader could not be used.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload_if_changed(&mut self, device: &wgpu::Device) -> Result<bool> {
+        let Some(path) = self.recipe.source_path.clone() else { return Ok(false) };
+        let modified = |file: &PathBuf| (file.clone(), std::fs::metadata(file).and_then(|metadata| metadata.modified()).ok());
+
+        // remember the files the shader is made of the first time
+        if self.recipe.watched.is_empty() {
+            let files = composer::compose_file(&path).map(|composed| composed.files).unwrap_or_else(|_| vec![path]);
+            self.recipe.watched = files.iter().map(modified).collect();
+            return Ok(false)
+        }
+        if self.recipe.watched.iter().all(|watched| modified(&watched.0) == *watched) { return Ok(false) }
+
+        // remember the new modification times even if the shader is broken, so it is only composed again after the next change
+        let composed = composer::compose_file(&path);
+        let files: Vec<PathBuf> = match &composed {
+            Result::Ok(composed) => composed.files.clone(),
+            Err(_) => self.recipe.watched.iter().map(|(file, _)| file.clone()).collect()
+        };
+        self.recipe.watched = files.iter().map(modified).collect();
+        let composed = composed?;
+
+        // wgpu panics on invalid shaders and pipelines by default, so catch those errors instead
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&self.recipe.name),
+            source: wgpu::ShaderSource::Wgsl(composed.source.into())
+        });
+        let render_pipeline = self.recipe.create_with(device, &shader);
+        if let Some(error) = pollster::block_on(device.pop_error_scope()) {
+            bail!("Could not recreate pipeline {} from {:?}: {}", self.recipe.name, path, error);
+        }
+
+        self.recipe.shader = shader;
+        self.render_pipeline = render_pipeline;
+        Ok(true)
+    }
+
+    /// Create a new pipeline that only writes depth, like those used to draw shadow maps.  The shader only needs a `vs_main` vertex entry point.
+    ///
+    /// Arguments:
+    /// * name: &str - The name of the pipeline for debugging purposes.
+    /// * engine: &RenderEngine - The render engine that will be used to create the pipeline.
+    /// * shader_code: &str - The WGSL shader code for this pipeline.
+    /// * buffers: &[wgpu::VertexBufferLayout] - An array of vertex buffer layouts for the shader.
+    /// * layouts: &[wgpu::BindGroupLayout] - An array of bind group layouts for the shader.
+    /// * bias: wgpu::DepthBiasState - The bias added to the depth of each fragment.
+    ///
+    /// Returns the new pipeline that is generated from the above arguments.
+    pub fn new_depth_only(
+        name: &str,
+        engine: &RenderEngine,
+        shader_code: &str,
+        buffers: &[wgpu::VertexBufferLayout],
+        layouts: &[&wgpu::BindGroupLayout],
+        bias: wgpu::DepthBiasState
+    ) -> Self {
+        PipelineBuilder::new(name, shader_code)
+            .with_buffers(buffers)
+            .with_layouts(layouts)
+            .with_fragment_entry(None)
+            .with_targets(Vec::new())
+            .with_depth(true)
+            .with_depth_bias(bias)
+            .single_sampled()
+            .build(engine)
+    }
+
+    /// Get a bind group at a given index
+    /// 
+    /// Arguments:
+    /// * index: u32 - the index of the bind group
+    /// 
+    /// Returns the bind group layout
+    pub fn get_layout(&self, index: u32) -> BindGroupLayout {
+        self.render_pipeline.get_bind_group_layout(index)
+    }
+
+    /// Binds this pipeline to the given render pass.
+    /// 
+    /// Arguments:
+    /// * &self - The pipeline to bind.
+    /// * pass: &mut RenderPass - The render pass to bind this pipeline too.
+    pub fn bind<'rpass>(
+        &'rpass self,
+        pass: &mut wgpu::RenderPass<'rpass>
+    ) {
+        pass.set_pipeline(&self.render_pipeline);
+    }
+}
+
+impl PipelineRecipe {
+    /// Creates the render pipeline described by this recipe.
+    fn create(&self, device: &wgpu::Device) -> wgpu::RenderPipeline { self.create_with(device, &self.shader) }
+
+    /// Creates the render pipeline described by this recipe with the given shader.
+    fn create_with(&self, device: &wgpu::Device, shader: &wgpu::ShaderModule) -> wgpu::RenderPipeline {
+        let buffers: Vec<wgpu::VertexBufferLayout> = self.buffers.iter()
+            .map(|(array_stride, step_mode, attributes)| wgpu::VertexBufferLayout { array_stride: *array_stride, step_mode: *step_mode, attributes })
+            .collect();
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(format!("{}_pipeline", self.name).as_str()),
+            layout: Some(&self.layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: &self.vertex_entry,
+                buffers: &buffers,
+            },
+            fragment: self.fragment_entry.as_ref().map(|entry_point| wgpu::FragmentState {
+                module: shader,
+                entry_point,
+                targets: &self.targets
+            }),
+            primitive: self.primitive,
+            depth_stencil: self.depth_stencil.clone(),
+            multisample: wgpu::MultisampleState {
+                count: self.sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false
+            },
+            multiview: None
+        })
+    }
+}
+
+/// Used to create and initialize a pipeline shader.  The pipeline is built with a `PipelineBuilder`, the optional `OPTIONS` are calls to its methods.
+/// 
+/// Example:
+/// ```rust
+/// create_pipeline! {
+///     NAME => "forte.gltf",
+///     ENGINE => engine,
+///     SHADER => gltf_shader::SOURCE,
+///     BUFFER_LAYOUTS => [Vertex::desc(), TransformRaw::desc()],
+///     BIND_GROUPS => [Camera::BIND_LAYOUT, Texture::BIND_LAYOUT, LightUniform::BIND_LAYOUT],
+///     HAS_DEPTH => true,
+///     OPTIONS => [with_blend(BlendMode::Opaque), with_cull_mode(None)]
+/// }
+/// ```
+#[macro_export]
+macro_rules! create_pipeline {
+    {
+        NAME => $name: literal,
+        ENGINE => $engine: expr,
+        SHADER => $shader: expr,
+        BUFFER_LAYOUTS => [$($buffer:expr),*],
+        BIND_GROUPS => [$($bind_group:expr),*],
+        HAS_DEPTH => $depth: literal
+        $(, OPTIONS => [$($option:ident($($argument:expr),*)),* $(,)?])?
+    } => {
+        $engine.verify_pipeline_exists($name, |engine| {
+            $crate::render::pipelines::PipelineBuilder::new($name, $shader)
+                .with_buffers(&[$($buffer),*])
+                .with_layouts(&[$(&engine.device.create_bind_group_layout(&$bind_group)),*])
+                .with_depth($depth)
+                $($(.$option($($argument),*))*)?
+                .build(engine)
+        });
+    };
+}
@@ -0,0 +1,721 @@
+use anyhow::*;
+use image::GenericImageView;
+use wgpu::util::DeviceExt;
+
+use crate::{component_app::EngineComponent, primitives::textures::Texture};
+
+use super::{graph::{create_texture, GraphPass, GraphTexture, GraphTextureDescriptor, TextureSize, SURFACE_TEXTURE}, hdr::HdrEngine, pipelines::{engine_shader_path, Pipeline, PipelineBuilder}, render_engine::RenderEngine};
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/post_copy.wgsl")]
+mod copy_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/post_bloom_downsample.wgsl")]
+mod bloom_downsample_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/post_bloom_upsample.wgsl")]
+mod bloom_upsample_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/post_bloom_composite.wgsl")]
+mod bloom_composite_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/post_fxaa.wgsl")]
+mod fxaa_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/post_vignette.wgsl")]
+mod vignette_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/post_chromatic_aberration.wgsl")]
+mod chromatic_aberration_shader {}
+
+#[include_wgsl_oil::include_wgsl_oil("../../shaders/post_color_grading.wgsl")]
+mod color_grading_shader {}
+
+/// The largest number of mips a bloom effect can blur over.
+const MAX_BLOOM_MIPS: u32 = 12;
+
+/// Bloom blurs the parts of the image brighter than a threshold over a chain of smaller and smaller textures, then adds them back onto the image.
+///
+/// Arguments:
+/// * threshold: f32 - The brightness above which colors start to bloom, colors only go above 1 when rendering in HDR.
+/// * knee: f32 - The softness of the threshold as a fraction of it, 0 is a hard cutoff.
+/// * intensity: f32 - How much of the bloom is added back onto the image.
+/// * radius: f32 - How far each mip is blurred in texels when it is upsampled.
+/// * mips: u32 - The number of mips to blur over, more mips spread the bloom further.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bloom {
+    pub threshold: f32,
+    pub knee: f32,
+    pub intensity: f32,
+    pub radius: f32,
+    pub mips: u32
+}
+
+impl Default for Bloom {
+    fn default() -> Self { Self { threshold: 1.0, knee: 0.5, intensity: 0.3, radius: 1.0, mips: 6 } }
+}
+
+/// Fast approximate anti-aliasing, which blurs the image along the edges it finds.
+///
+/// Arguments:
+/// * span_max: f32 - The longest distance in pixels that is blurred along an edge.
+/// * reduce_multiplier: f32 - How much bright areas shorten the blur.
+/// * reduce_min: f32 - The least the blur is shortened by, this stops noise in dark areas being blurred.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fxaa {
+    pub span_max: f32,
+    pub reduce_multiplier: f32,
+    pub reduce_min: f32
+}
+
+impl Default for Fxaa {
+    fn default() -> Self { Self { span_max: 8.0, reduce_multiplier: 1.0 / 8.0, reduce_min: 1.0 / 128.0 } }
+}
+
+/// A vignette fades the edges of the image towards a color.
+///
+/// Arguments:
+/// * intensity: f32 - How far the edges are faded, from 0 to 1.
+/// * radius: f32 - The distance from the center where the fade starts, 1 is the corners of the image.
+/// * smoothness: f32 - The distance over which the fade goes from nothing to its full intensity.
+/// * color: [f32; 3] - The color faded too.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vignette {
+    pub intensity: f32,
+    pub radius: f32,
+    pub smoothness: f32,
+    pub color: [f32; 3]
+}
+
+impl Default for Vignette {
+    fn default() -> Self { Self { intensity: 0.5, radius: 0.5, smoothness: 0.5, color: [0.0, 0.0, 0.0] } }
+}
+
+/// Chromatic aberration splits the red and blue channels apart towards the edges of the image, like a cheap lens.
+///
+/// Arguments:
+/// * intensity: f32 - How far the channels are split at the edges, as a fraction of the image size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChromaticAberration {
+    pub intensity: f32
+}
+
+impl Default for ChromaticAberration {
+    fn default() -> Self { Self { intensity: 0.01 } }
+}
+
+/// Color grading replaces every color in the image with the color it maps to in a 3D lookup table.
+///
+/// Arguments:
+/// * lut: ColorGradingLut - The lookup table.
+/// * intensity: f32 - How much of the graded color is used, from 0 to 1.
+#[derive(Debug)]
+pub struct ColorGrading {
+    pub lut: ColorGradingLut,
+    pub intensity: f32
+}
+
+/// An effect with a custom shader, registered with `PostProcessStack::register_effect`.
+///
+/// Arguments:
+/// * pipeline: String - The name the effect's pipeline was registered under.
+/// * params: [[f32; 4]; 4] - The parameters passed to the shader in its `@group(1) @binding(0)` uniform.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomEffect {
+    pub pipeline: String,
+    pub params: [[f32; 4]; 4]
+}
+
+/// A full-screen effect in a `PostProcessStack`.
+#[derive(Debug)]
+pub enum PostEffect {
+    Bloom(Bloom),
+    Fxaa(Fxaa),
+    Vignette(Vignette),
+    ChromaticAberration(ChromaticAberration),
+    ColorGrading(ColorGrading),
+    Custom(CustomEffect)
+}
+
+impl PostEffect {
+    /// The path of the pipeline that draws this effect into its target.
+    fn pipeline(&self) -> &str {
+        match self {
+            PostEffect::Bloom(_) => PostProcessStack::BLOOM_COMPOSITE_PIPELINE,
+            PostEffect::Fxaa(_) => "forte.post_process.fxaa",
+            PostEffect::Vignette(_) => "forte.post_process.vignette",
+            PostEffect::ChromaticAberration(_) => "forte.post_process.chromatic_aberration",
+            PostEffect::ColorGrading(_) => "forte.post_process.color_grading",
+            PostEffect::Custom(custom) => &custom.pipeline
+        }
+    }
+
+    /// The parameters of this effect in the format its shader expects.
+    fn params(&self) -> [[f32; 4]; 4] {
+        let mut params = [[0.0; 4]; 4];
+        match self {
+            PostEffect::Bloom(bloom) => params[0] = [bloom.threshold, bloom.knee, bloom.intensity / bloom_mips(bloom) as f32, bloom.radius],
+            PostEffect::Fxaa(fxaa) => params[0] = [fxaa.span_max, fxaa.reduce_multiplier, fxaa.reduce_min, 0.0],
+            PostEffect::Vignette(vignette) => {
+                params[0] = [vignette.intensity, vignette.radius, vignette.smoothness, 0.0];
+                params[1] = [vignette.color[0], vignette.color[1], vignette.color[2], 1.0];
+            },
+            PostEffect::ChromaticAberration(aberration) => params[0] = [aberration.intensity, 0.0, 0.0, 0.0],
+            PostEffect::ColorGrading(grading) => params[0] = [grading.intensity, 0.0, 0.0, 0.0],
+            PostEffect::Custom(custom) => params = custom.params
+        }
+        params
+    }
+}
+
+/// A 3D lookup table used for color grading, stored as a 3D texture.  Tables are indexed by gamma encoded colors and store sRGB colors, like the images color grading tools export.
+///
+/// Arguments:
+/// * texture: wgpu::Texture - The 3D texture of the table.
+/// * view: wgpu::TextureView - A view of the texture.
+/// * sampler: wgpu::Sampler - A linear sampler for the texture.
+/// * bind_group: wgpu::BindGroup - A bind group of the view and sampler using `PostProcessStack::LUT_BIND_LAYOUT`.
+#[derive(Debug)]
+pub struct ColorGradingLut {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub bind_group: wgpu::BindGroup
+}
+
+impl ColorGradingLut {
+    /// Creates a lookup table that maps every color to itself, useful as a starting point for grading.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to create the table with.
+    /// * size: u32 - The number of entries along each axis, 16 or 32 are common.
+    ///
+    /// Returns the new table.
+    pub fn identity(engine: &RenderEngine, size: u32) -> Self {
+        let scale = 255.0 / (size.max(2) - 1) as f32;
+        let data: Vec<u8> = (0 .. size * size * size)
+            .flat_map(|index| {
+                let (r, g, b) = (index % size, index / size % size, index / (size * size));
+                [(r as f32 * scale).round() as u8, (g as f32 * scale).round() as u8, (b as f32 * scale).round() as u8, 255]
+            })
+            .collect();
+        Self::from_raw(engine, size, &data)
+    }
+
+    /// Creates a lookup table from the bytes of an image, see `ColorGradingLut::from_image` for the layout.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to create the table with.
+    /// * bytes: &[u8] - The bytes of the image.
+    ///
+    /// Returns a result containing the table, or an error if the image could not be loaded or is not laid out like a table.
+    pub fn from_bytes(engine: &RenderEngine, bytes: &[u8]) -> Result<Self> {
+        let image = image::load_from_memory(bytes)?;
+        Self::from_image(engine, &image)
+    }
+
+    /// Creates a lookup table from an image of a horizontal strip of squares, for example, a 16 entry table is a 256 by 16 image.  Red increases to the right within each square, green increases downwards, and blue increases from one square to the next.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to create the table with.
+    /// * image: &image::DynamicImage - The image of the table.
+    ///
+    /// Returns a result containing the table, or an error if the image is not as wide as its height squared.
+    pub fn from_image(engine: &RenderEngine, image: &image::DynamicImage) -> Result<Self> {
+        let (width, size) = image.dimensions();
+        if size == 0 || width != size * size { bail!("A color grading table image must be as wide as its height squared, found a {}x{} image.", width, size); }
+
+        // rearrange the strip of squares into the slices of the 3d texture
+        let rgba = image.to_rgba8();
+        let data: Vec<u8> = (0 .. size * size * size)
+            .flat_map(|index| {
+                let (r, g, b) = (index % size, index / size % size, index / (size * size));
+                rgba.get_pixel(b * size + r, g).0
+            })
+            .collect();
+        Ok(Self::from_raw(engine, size, &data))
+    }
+
+    /// Creates a lookup table from raw RGBA bytes, ordered by red, then green, then blue.
+    ///
+    /// Arguments:
+    /// * engine: &RenderEngine - The render engine to create the table with.
+    /// * size: u32 - The number of entries along each axis.
+    /// * data: &[u8] - The entries of the table, 4 bytes each.
+    ///
+    /// Returns the new table.
+    pub fn from_raw(engine: &RenderEngine, size: u32, data: &[u8]) -> Self {
+        let texture = engine.device.create_texture_with_data(
+            &engine.queue,
+            &wgpu::TextureDescriptor {
+                label: Some("color_grading_lut"),
+                size: wgpu::Extent3d { width: size, height: size, depth_or_array_layers: size },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D3,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[]
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            data
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = engine.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group = engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("color_grading_lut_bind_group"),
+            layout: &engine.device.create_bind_group_layout(&PostProcessStack::LUT_BIND_LAYOUT),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&sampler) }
+            ]
+        });
+
+        Self { texture, view, sampler, bind_group }
+    }
+}
+
+/// The rust representation of an effect's parameters passed to its shader.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct EffectUniform {
+    params: [[f32; 4]; 4]
+}
+
+/// The textures and buffers a bloom effect blurs over.
+#[derive(Debug)]
+struct BloomChain {
+    prefilter_buffer: wgpu::Buffer,
+    prefilter_bind_group: wgpu::BindGroup,
+    size: (u32, u32),
+    down: Vec<GraphTexture>,
+    up: Vec<GraphTexture>
+}
+
+impl BloomChain {
+    /// The texture holding the finished bloom.
+    fn result(&self) -> Option<&GraphTexture> { self.up.first().or(self.down.first()) }
+}
+
+/// The GPU resources of an effect in a stack.
+#[derive(Debug)]
+struct EffectResources {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    bloom: Option<BloomChain>
+}
+
+/// An effect in a stack.
+#[derive(Debug)]
+struct EffectSlot {
+    name: String,
+    effect: PostEffect,
+    enabled: bool,
+    resources: Option<EffectResources>
+}
+
+impl EffectSlot {
+    /// Draws this effect into the given pass.
+    fn draw<'rpass>(&'rpass self, engine: &'rpass RenderEngine, pass: &mut wgpu::RenderPass<'rpass>, input: &'rpass wgpu::BindGroup) {
+        let Some(resources) = &self.resources else { return };
+        let Some(pipeline) = engine.pipeline_path(self.effect.pipeline()) else { return };
+        pipeline.bind(pass);
+        pass.set_bind_group(0, input, &[]);
+        pass.set_bind_group(1, &resources.bind_group, &[]);
+        match &self.effect {
+            PostEffect::Bloom(_) => {
+                let Some(bloom) = resources.bloom.as_ref().and_then(|chain| chain.result()).and_then(|texture| texture.bind_group.as_ref()) else { return };
+                pass.set_bind_group(2, bloom, &[]);
+            },
+            PostEffect::ColorGrading(grading) => pass.set_bind_group(2, &grading.lut.bind_group, &[]),
+            _ => {}
+        }
+        pass.draw(0 .. 3, 0 .. 1);
+    }
+}
+
+/// A engine that runs an ordered list of full-screen effects over the rendered scene, before UI passes draw on top.  On creation, it adds the `PostProcessStack::TEXTURE` texture to the render graph for 3D passes to render into.  The last effect writes to the surface, or to the HDR texture if a `HdrEngine` was created first, so that bloom spreads HDR colors and tonemapping happens after every effect.
+///
+/// Effects are run in the order they were added and can be changed, disabled or removed at any time by name.  Custom effects are registered with `PostProcessStack::register_effect` and added as `PostEffect::Custom`.
+///
+/// Example:
+/// ```rust
+/// create_app! {
+///     CLEAR_COLOR = wgpu::Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 },
+///
+///     APP {
+///         hdr: HdrEngine[render_engine],
+///         post_process: PostProcessStack[render_engine],
+///         test: TestComponent[render_engine, post_process]
+///     },
+///
+///     PASSES {
+///         "main": {
+///             COLOR: ["forte.post_process"],
+///             DEPTH: "forte.depth",
+///             PARTS: [...]
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PostProcessStack {
+    effects: Vec<EffectSlot>
+}
+
+impl PostProcessStack {
+    /// The name of the texture the scene is rendered into before effects run, 3D passes should render into it.
+    pub const TEXTURE: &'static str = "forte.post_process";
+
+    /// The name of the texture the first, third and every other odd effect before the last renders into.
+    pub const PING_TEXTURE: &'static str = "forte.post_process.ping";
+
+    /// The name of the texture the second, fourth and every other even effect before the last renders into.
+    pub const PONG_TEXTURE: &'static str = "forte.post_process.pong";
+
+    /// The name of the render graph pass that runs every effect but the last.
+    pub const EFFECTS_PASS: &'static str = "forte.post_process.effects";
+
+    /// The name of the render graph pass that runs the last effect into the output.
+    pub const PASS: &'static str = "forte.post_process";
+
+    /// The WGSL source of the vertex shader every effect uses, a `vs_main` that draws a triangle covering the screen and outputs a `FullscreenOutput` with the uv of each pixel.
+    pub const VERTEX_SHADER: &'static str = include_str!("../../shaders/post_process.wgsl");
+
+    const COPY_PIPELINE: &'static str = "forte.post_process.copy";
+    const BLOOM_DOWNSAMPLE_PIPELINE: &'static str = "forte.post_process.bloom_downsample";
+    const BLOOM_UPSAMPLE_PIPELINE: &'static str = "forte.post_process.bloom_upsample";
+    const BLOOM_COMPOSITE_PIPELINE: &'static str = "forte.post_process.bloom_composite";
+
+    /// The bind group layout of an effect's parameters.
+    pub const EFFECT_BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("post_effect_bind_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }
+        ]
+    };
+
+    /// The bind group layout of a color grading lookup table.
+    pub const LUT_BIND_LAYOUT: wgpu::BindGroupLayoutDescriptor<'static> = wgpu::BindGroupLayoutDescriptor {
+        label: Some("color_grading_lut_bind_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D3,
+                    multisampled: false
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None
+            }
+        ]
+    };
+
+    /// Registers the pipeline of a custom effect, so it can be added as a `PostEffect::Custom` with the same name.  The shader is created with `Pipeline::new` after `PostProcessStack::VERTEX_SHADER`, so it only needs a `fs_main` that takes a `FullscreenOutput`.  Its input is bound at group 0 with `Texture::BIND_LAYOUT`, and its parameters at group 1 as a uniform of 4 `vec4<f32>`.
+    ///
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine to cache the pipeline in.
+    /// * name: &str - The name of the pipeline.
+    /// * shader_code: &str - The WGSL code of the effect's fragment shader.
+    pub fn register_effect(engine: &mut RenderEngine, name: &str, shader_code: &str) {
+        let shader_code = format!("{}\n{}", Self::VERTEX_SHADER, shader_code);
+        engine.verify_pipeline_exists(name, |engine| {
+            Pipeline::new(
+                name, engine, &shader_code, &[],
+                &[
+                    &engine.device.create_bind_group_layout(&Texture::BIND_LAYOUT),
+                    &engine.device.create_bind_group_layout(&Self::EFFECT_BIND_LAYOUT)
+                ],
+                false
+            )
+        });
+    }
+
+    /// Adds an effect to the end of this stack, replacing any effect with the same name in place.
+    ///
+    /// Arguments:
+    /// * name: impl Into<String> - The name used to find the effect later.
+    /// * effect: PostEffect - The effect.
+    pub fn add_effect(&mut self, name: impl Into<String>, effect: PostEffect) {
+        let name = name.into();
+        match self.effects.iter_mut().find(|slot| slot.name == name) {
+            Some(slot) => slot.effect = effect,
+            None => self.effects.push(EffectSlot { name, effect, enabled: true, resources: None })
+        }
+    }
+
+    /// Inserts an effect at the given index of this stack, removing any effect with the same name first.
+    ///
+    /// Arguments:
+    /// * index: usize - The index in the stack, clamped to the number of effects.
+    /// * name: impl Into<String> - The name used to find the effect later.
+    /// * effect: PostEffect - The effect.
+    pub fn insert_effect(&mut self, index: usize, name: impl Into<String>, effect: PostEffect) {
+        let name = name.into();
+        self.effects.retain(|slot| slot.name != name);
+        self.effects.insert(index.min(self.effects.len()), EffectSlot { name, effect, enabled: true, resources: None });
+    }
+
+    /// Removes the effect with the given name from this stack.
+    ///
+    /// Returns an option containing the removed effect, or none if there was no effect with that name.
+    pub fn remove_effect(&mut self, name: &str) -> Option<PostEffect> {
+        let index = self.effects.iter().position(|slot| slot.name == name)?;
+        Some(self.effects.remove(index).effect)
+    }
+
+    /// Returns an option containing the effect with the given name.
+    pub fn effect(&self, name: &str) -> Option<&PostEffect> { self.effects.iter().find(|slot| slot.name == name).map(|slot| &slot.effect) }
+
+    /// Returns an option containing a mutable reference to the effect with the given name, changes are used from the next frame.
+    pub fn effect_mut(&mut self, name: &str) -> Option<&mut PostEffect> { self.effects.iter_mut().find(|slot| slot.name == name).map(|slot| &mut slot.effect) }
+
+    /// Enables or disables the effect with the given name, disabled effects are skipped.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        if let Some(slot) = self.effects.iter_mut().find(|slot| slot.name == name) { slot.enabled = enabled; }
+    }
+
+    /// Returns true if an effect with the given name exists and is enabled.
+    pub fn is_enabled(&self, name: &str) -> bool { self.effects.iter().any(|slot| slot.name == name && slot.enabled) }
+
+    /// The number of effects in this stack, including disabled ones.
+    pub fn num_effects(&self) -> usize { self.effects.len() }
+
+    /// Creates any missing resources of the given effect and uploads its parameters.
+    fn prepare(slot: &mut EffectSlot, engine: &RenderEngine) {
+        let resources = slot.resources.get_or_insert_with(|| {
+            let buffer = engine.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("post_effect_buffer"),
+                size: std::mem::size_of::<EffectUniform>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false
+            });
+            let bind_group = create_effect_bind_group(engine, &buffer);
+            EffectResources { buffer, bind_group, bloom: None }
+        });
+
+        let params = slot.effect.params();
+        engine.queue.write_buffer(&resources.buffer, 0, bytemuck::cast_slice(&[EffectUniform { params }]));
+
+        // the first bloom downsample uses the same parameters with its prefilter flag set
+        if let PostEffect::Bloom(_) = slot.effect {
+            let chain = resources.bloom.get_or_insert_with(|| {
+                let prefilter_buffer = engine.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("bloom_prefilter_buffer"),
+                    contents: bytemuck::cast_slice(&[EffectUniform { params }]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+                });
+                let prefilter_bind_group = create_effect_bind_group(engine, &prefilter_buffer);
+                BloomChain { prefilter_buffer, prefilter_bind_group, size: (0, 0), down: Vec::new(), up: Vec::new() }
+            });
+            let mut params = params;
+            params[1] = [1.0, 0.0, 0.0, 0.0];
+            engine.queue.write_buffer(&chain.prefilter_buffer, 0, bytemuck::cast_slice(&[EffectUniform { params }]));
+        }
+    }
+
+    /// Blurs the bright parts of the input over the given bloom effect's chain of textures.
+    fn encode_bloom(slot: &mut EffectSlot, engine: &RenderEngine, encoder: &mut wgpu::CommandEncoder, input: &GraphTexture) {
+        let PostEffect::Bloom(bloom) = &slot.effect else { return };
+        let Some(resources) = &mut slot.resources else { return };
+        let Some(chain) = &mut resources.bloom else { return };
+        let (Some(downsample), Some(upsample)) = (engine.pipeline_path(Self::BLOOM_DOWNSAMPLE_PIPELINE), engine.pipeline_path(Self::BLOOM_UPSAMPLE_PIPELINE)) else { return };
+        let Some(input_bind_group) = &input.bind_group else { return };
+
+        // reallocate the chain when the input or number of mips change, each mip is half the size of the last
+        let size = input.texture.size();
+        let mips = bloom_mips(bloom) as usize;
+        if chain.size != (size.width, size.height) || chain.down.len() != mips {
+            let mip_size = |mip: usize| (size.width >> (mip + 1), size.height >> (mip + 1));
+            let create_mip = |mip: usize| {
+                let (width, height) = mip_size(mip);
+                create_texture(&engine.device, "bloom_mip", &GraphTextureDescriptor { size: TextureSize::Fixed(width, height), ..GraphTextureDescriptor::new(input.texture.format()) }, (0, 0), 1)
+            };
+            chain.size = (size.width, size.height);
+            chain.down = (0 .. mips).map(create_mip).collect();
+            chain.up = (0 .. mips - 1).map(create_mip).collect();
+        }
+
+        // downsample from the input, then upsample back adding each mip to the blurred mip below it
+        for mip in 0 .. mips {
+            let (source, params) = match mip {
+                0 => (input_bind_group, &chain.prefilter_bind_group),
+                _ => (chain.down[mip - 1].bind_group.as_ref().unwrap(), &resources.bind_group)
+            };
+            let mut pass = begin_effect_pass(encoder, &chain.down[mip].view);
+            downsample.bind(&mut pass);
+            pass.set_bind_group(0, source, &[]);
+            pass.set_bind_group(1, params, &[]);
+            pass.draw(0 .. 3, 0 .. 1);
+        }
+        for mip in (0 .. mips - 1).rev() {
+            let lower = if mip + 2 == mips { &chain.down[mip + 1] } else { &chain.up[mip + 1] };
+            let mut pass = begin_effect_pass(encoder, &chain.up[mip].view);
+            upsample.bind(&mut pass);
+            pass.set_bind_group(0, lower.bind_group.as_ref().unwrap(), &[]);
+            pass.set_bind_group(1, &resources.bind_group, &[]);
+            pass.set_bind_group(2, chain.down[mip].bind_group.as_ref().unwrap(), &[]);
+            pass.draw(0 .. 3, 0 .. 1);
+        }
+    }
+}
+
+/// The number of mips a bloom effect blurs over.
+fn bloom_mips(bloom: &Bloom) -> u32 { bloom.mips.clamp(1, MAX_BLOOM_MIPS) }
+
+/// Creates the bind group of an effect's parameters.
+fn create_effect_bind_group(engine: &RenderEngine, buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+    engine.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("post_effect_bind_group"),
+        layout: &engine.device.create_bind_group_layout(&PostProcessStack::EFFECT_BIND_LAYOUT),
+        entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }]
+    })
+}
+
+/// Begins a render pass that draws an effect over the whole of the given single sampled view.
+fn begin_effect_pass<'a>(encoder: &'a mut wgpu::CommandEncoder, view: &'a wgpu::TextureView) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("post_effect_pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store }
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None
+    })
+}
+
+impl EngineComponent<&mut RenderEngine> for PostProcessStack {
+    /// Creates a new empty post processing stack, adding its textures and passes to the render graph.
+    ///
+    /// Arguments:
+    /// * engine: &mut RenderEngine - The render engine to post process.
+    fn create(engine: &mut RenderEngine) -> Self {
+        // effects write to the hdr texture if there is one, so that it is still tonemapped afterwards
+        let output = if engine.render_graph.has_texture(HdrEngine::TEXTURE) { HdrEngine::TEXTURE } else { SURFACE_TEXTURE };
+        let format = engine.scene_format();
+        engine.render_graph.add_texture(Self::TEXTURE, GraphTextureDescriptor { clear_color: engine.render_graph.clear_color(), ..GraphTextureDescriptor::new(format) });
+        engine.render_graph.add_texture(Self::PING_TEXTURE, GraphTextureDescriptor { clear_color: wgpu::Color::BLACK, ..GraphTextureDescriptor::new(format) });
+        engine.render_graph.add_texture(Self::PONG_TEXTURE, GraphTextureDescriptor { clear_color: wgpu::Color::BLACK, ..GraphTextureDescriptor::new(format) });
+        engine.render_graph.add_pass(GraphPass::encoder(Self::EFFECTS_PASS).with_read(Self::TEXTURE).with_write(Self::PING_TEXTURE).with_write(Self::PONG_TEXTURE));
+        engine.render_graph.add_pass(GraphPass::new(Self::PASS).with_color(output).with_read(Self::TEXTURE).with_read(Self::PING_TEXTURE).with_read(Self::PONG_TEXTURE));
+
+        // create the pipelines of the built in effects, the bloom and color grading effects bind another texture at group 2
+        // bloom blurs into its own single sampled textures, every other effect draws into the graph's textures with its sample count
+        let pipelines = [
+            (Self::COPY_PIPELINE, copy_shader::SOURCE, "shaders/post_copy.wgsl", None),
+            (Self::BLOOM_DOWNSAMPLE_PIPELINE, bloom_downsample_shader::SOURCE, "shaders/post_bloom_downsample.wgsl", None),
+            (Self::BLOOM_UPSAMPLE_PIPELINE, bloom_upsample_shader::SOURCE, "shaders/post_bloom_upsample.wgsl", Some(Texture::BIND_LAYOUT)),
+            (Self::BLOOM_COMPOSITE_PIPELINE, bloom_composite_shader::SOURCE, "shaders/post_bloom_composite.wgsl", Some(Texture::BIND_LAYOUT)),
+            ("forte.post_process.fxaa", fxaa_shader::SOURCE, "shaders/post_fxaa.wgsl", None),
+            ("forte.post_process.vignette", vignette_shader::SOURCE, "shaders/post_vignette.wgsl", None),
+            ("forte.post_process.chromatic_aberration", chromatic_aberration_shader::SOURCE, "shaders/post_chromatic_aberration.wgsl", None),
+            ("forte.post_process.color_grading", color_grading_shader::SOURCE, "shaders/post_color_grading.wgsl", Some(Self::LUT_BIND_LAYOUT))
+        ];
+        for (name, source, path, extra) in pipelines {
+            engine.verify_pipeline_exists(name, |engine| {
+                let input = engine.device.create_bind_group_layout(&Texture::BIND_LAYOUT);
+                let params = engine.device.create_bind_group_layout(&Self::EFFECT_BIND_LAYOUT);
+                let extra = extra.as_ref().map(|layout| engine.device.create_bind_group_layout(layout));
+                let layouts: Vec<&wgpu::BindGroupLayout> = match (name, &extra) {
+                    (Self::COPY_PIPELINE, _) => vec![&input],
+                    (_, Some(extra)) => vec![&input, &params, extra],
+                    (_, None) => vec![&input, &params]
+                };
+                let builder = PipelineBuilder::new(name, source)
+                    .with_layouts(&layouts)
+                    .with_source_path(engine_shader_path(path));
+                match name {
+                    Self::BLOOM_DOWNSAMPLE_PIPELINE | Self::BLOOM_UPSAMPLE_PIPELINE => builder.single_sampled().build(engine),
+                    _ => builder.build(engine)
+                }
+            });
+        }
+
+        Self { effects: Vec::new() }
+    }
+
+    /// On the render graph's `EFFECTS_PASS`, this stack runs every enabled effect but the last, alternating between its two textures.
+    ///
+    /// Arguments:
+    /// * name: &str - The name of the pass being run.
+    /// * engine: &RenderEngine - The render engine being rendered with.
+    /// * encoder: &mut wgpu::CommandEncoder - The frame's command encoder.
+    fn encode_graph_pass(&mut self, name: &str, engine: &RenderEngine, encoder: &mut wgpu::CommandEncoder) {
+        if name != Self::EFFECTS_PASS { return }
+        let graph = &engine.render_graph;
+        let (Some(scene), Some(ping), Some(pong)) = (graph.texture(Self::TEXTURE), graph.texture(Self::PING_TEXTURE), graph.texture(Self::PONG_TEXTURE)) else { return };
+
+        let count = self.effects.iter().filter(|slot| slot.enabled).count();
+        let mut input = scene;
+        for (index, slot) in self.effects.iter_mut().filter(|slot| slot.enabled).enumerate() {
+            Self::prepare(slot, engine);
+            Self::encode_bloom(slot, engine, encoder, input);
+
+            // the last effect is drawn in `PASS` straight into the output
+            if index + 1 == count { break }
+            let (target_name, target) = if index % 2 == 0 { (Self::PING_TEXTURE, ping) } else { (Self::PONG_TEXTURE, pong) };
+            let Some(input_bind_group) = &input.bind_group else { return };
+            let Some(mut pass) = graph.begin_texture_pass(encoder, target_name) else { return };
+            slot.draw(engine, &mut pass, input_bind_group);
+            drop(pass);
+            input = target;
+        }
+    }
+
+    /// On the render graph's `PASS`, this stack draws its last enabled effect into the output, or copies the scene if no effects are enabled.
+    ///
+    /// Arguments:
+    /// * name: &str - The name of the pass being run.
+    /// * engine: &RenderEngine - The render engine being rendered with.
+    /// * pass: &mut wgpu::RenderPass - The render pass to draw too.
+    fn render_graph_pass<'rpass>(&'rpass mut self, name: &str, engine: &'rpass RenderEngine, pass: &mut wgpu::RenderPass<'rpass>) {
+        if name != Self::PASS { return }
+        let count = self.effects.iter().filter(|slot| slot.enabled).count();
+        let input = match count {
+            0 | 1 => Self::TEXTURE,
+            _ if count % 2 == 0 => Self::PING_TEXTURE,
+            _ => Self::PONG_TEXTURE
+        };
+        let Some(input) = engine.render_graph.texture(input).and_then(|texture| texture.bind_group.as_ref()) else { return };
+
+        match self.effects.iter().filter(|slot| slot.enabled).last() {
+            Some(slot) => slot.draw(engine, pass, input),
+            None => {
+                let Some(pipeline) = engine.pipeline_path(Self::COPY_PIPELINE) else { return };
+                pipeline.bind(pass);
+                pass.set_bind_group(0, input, &[]);
+                pass.draw(0 .. 3, 0 .. 1);
+            }
+        }
+    }
+
+    fn update(&mut self, _: &mut RenderEngine) {}
+    fn render<'rpass>(&'rpass mut self, _: &'rpass RenderEngine, _: &mut wgpu::RenderPass<'rpass>) {}
+    fn start(&mut self, _: &mut RenderEngine) {}
+    fn exit(&mut self, _: &mut RenderEngine) {}
+}
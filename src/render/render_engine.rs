@@ -1,10 +1,10 @@
-use std::marker::PhantomData;
+use std::{collections::HashMap, marker::PhantomData, path::Path};
 
-use winit::window::Window;
+use winit::window::{CursorGrabMode, Window};
 
 use crate::{log, primitives::{mesh::Mesh, textures::{depth_textures::DepthTexture, Texture}, vertices::Vertex}, utils::{files::Files, resources::{Handle, ResourceCache}}};
 
-use super::pipelines::Pipeline;
+use super::{graph::{DepthConvention, RenderGraph}, pipelines::{composer::{ShaderComposer, ShaderKey}, compute::ComputePipeline, Pipeline}};
 
 /// A struct with all required information to render to a given window.
 /// 
@@ -12,7 +12,7 @@ use super::pipelines::Pipeline;
 /// DO NOT try to modify any values in this struct, this will only cause errors unless you know what you are doing.
 #[derive(Debug)]
 pub struct RenderEngine {
-    pub surface: wgpu::Surface<'static>,
+    pub surface: Option<wgpu::Surface<'static>>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
@@ -21,17 +21,114 @@ pub struct RenderEngine {
     mesh_cache: ResourceCache<Mesh>,
     texture_cache: ResourceCache<Texture>,
     pipeline_cache: ResourceCache<Pipeline>,
+    compute_pipeline_cache: ResourceCache<ComputePipeline>,
     pub depth_texture: DepthTexture,
+
+    /// The passes rendered each frame and the textures they render too.
+    pub render_graph: RenderGraph,
+
+    /// The modules and shaders variant pipelines are composed from, see `verify_variant_pipeline_exists`.
+    pub shader_composer: ShaderComposer,
+    variant_generations: HashMap<String, u64>,
+    pub(crate) scene_format: wgpu::TextureFormat,
+    sample_count: u32,
+    adapter: wgpu::Adapter,
+    shader_poll_time: Option<f32>,
     pub(crate) start_time: u128,
     pub time_since_start: f32,
     pub delta_time: f32,
 
-    pub window: Window // must be declared after surface due to unsafe code in windows resources
+    /// If set, time will advance by this many seconds every frame instead of following the clock.  This is useful to render the same frames every run, for example, in tests.
+    pub fixed_time_step: Option<f32>,
+
+    /// The texture rendered too when this engine was created with `new_headless`.
+    pub headless_texture: Option<wgpu::Texture>,
+    pub window: Option<Window> // must be declared after surface due to unsafe code in windows resources
 }
 
 impl RenderEngine {
-    /// Get a reference to the WGPU window used by the render engine.
-    pub fn window(&self) -> &Window { &self.window }
+    /// How many seconds `reload_changed_shaders` waits between checking shader files for changes.
+    pub const SHADER_POLL_INTERVAL: f32 = 0.5;
+
+    /// Get a reference to the WGPU window used by the render engine.  This will be none if the engine is headless.
+    pub fn window(&self) -> Option<&Window> { self.window.as_ref() }
+
+    /// The color format 3D scenes are rendered in and `Pipeline::new` creates pipelines for.  This is the surface format unless HDR rendering is enabled with a `HdrEngine`.
+    pub fn scene_format(&self) -> wgpu::TextureFormat { self.scene_format }
+
+    /// The number of samples per pixel every pass of the render graph renders with, 1 unless MSAA was enabled with `set_sample_count`.
+    pub fn sample_count(&self) -> u32 { self.sample_count }
+
+    /// Returns true if the adapter can render the surface, scene and depth formats with the given number of samples per pixel.
+    pub fn supports_sample_count(&self, sample_count: u32) -> bool {
+        let features = self.device.features();
+        [self.config.format, self.scene_format, DepthTexture::DEPTH_FORMAT].into_iter().all(|format| {
+            let format_features = if features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) { self.adapter.get_texture_format_features(format) } else { format.guaranteed_format_features(features) };
+            format_features.flags.sample_count_supported(sample_count)
+        })
+    }
+
+    /// Returns every sample count `set_sample_count` accepts.
+    pub fn supported_sample_counts(&self) -> Vec<u32> { [1, 2, 4, 8, 16].into_iter().filter(|count| self.supports_sample_count(*count)).collect() }
+
+    /// The features the adapter supports beyond WebGL, like compute shaders and indirect draws.
+    pub fn downlevel_capabilities(&self) -> wgpu::DownlevelCapabilities { self.adapter.get_downlevel_capabilities() }
+
+    /// Sets the number of samples per pixel every pass of the render graph renders with, a count above 1 enables MSAA.  The depth texture and the render graph's multisampled textures are reallocated, and every cached pipeline that follows the sample count is recreated.  Components with their own renderers, like `UIEngine` and `EguiEngine`, recreate them the next time they update.
+    ///
+    /// Arguments:
+    /// * sample_count: u32 - The number of samples per pixel, 4 is supported almost everywhere.
+    ///
+    /// Returns a result that is an error if the adapter does not support the sample count.
+    pub fn set_sample_count(&mut self, sample_count: u32) -> anyhow::Result<()> {
+        if !self.supports_sample_count(sample_count) {
+            anyhow::bail!("Cannot render with {} samples per pixel, the adapter supports {:?}.", sample_count, self.supported_sample_counts());
+        }
+        if self.sample_count == sample_count { return Ok(()) }
+
+        self.sample_count = sample_count;
+        self.depth_texture = DepthTexture::new(&self.config, &self.device, "depth_texture", sample_count);
+        self.render_graph.set_sample_count(&self.device, &self.config, sample_count);
+        self.pipeline_cache.iter_mut().for_each(|pipeline| pipeline.set_sample_count(&self.device, sample_count));
+        Ok(())
+    }
+
+    /// How depth is stored in the depth textures of the render graph and compared by pipelines, `DepthConvention::Standard` unless changed with `set_depth_convention`.
+    pub fn depth_convention(&self) -> DepthConvention { self.render_graph.depth_convention() }
+
+    /// Sets how depth is stored in the depth textures of the render graph, use `DepthConvention::Reverse` with reverse-Z projections like `Projection::InfinitePerspective`.  Depth textures are cleared with the new convention, and every cached pipeline that does not set its own depth compare (see `PipelineBuilder::with_depth_compare`) is recreated to compare depth with it.
+    ///
+    /// Arguments:
+    /// * convention: DepthConvention - The new depth convention.
+    pub fn set_depth_convention(&mut self, convention: DepthConvention) {
+        if self.render_graph.depth_convention() == convention { return }
+
+        self.render_graph.set_depth_convention(convention);
+        self.pipeline_cache.iter_mut().for_each(|pipeline| pipeline.set_depth_convention(&self.device, convention));
+    }
+
+    /// Enables or disables shader hot reloading for development.  While enabled, `reload_changed_shaders` recreates every cached pipeline whose source file (see `PipelineBuilder::with_source_path`) or any file it imports changed.  The engine's own pipelines are given the paths of their shaders in the engine's source tree.  Hot reloading reads the shader files and waits for the new pipelines to validate, so it does nothing on the web.
+    pub fn set_shader_hot_reload(&mut self, enabled: bool) { self.shader_poll_time = enabled.then_some(f32::NEG_INFINITY); }
+
+    /// Returns true if shader hot reloading is enabled.
+    pub fn shader_hot_reload(&self) -> bool { self.shader_poll_time.is_some() }
+
+    /// Checks the source files of every cached pipeline for changes if shader hot reloading is enabled, at most every `SHADER_POLL_INTERVAL` seconds.  Changed pipelines are recreated, if a changed shader cannot be used the error is logged and the old pipeline is kept.  This is called every frame by `create_app!`.
+    pub fn reload_changed_shaders(&mut self) {
+        let Some(last_poll) = self.shader_poll_time else { return };
+        if self.time_since_start - last_poll < Self::SHADER_POLL_INTERVAL { return }
+        self.shader_poll_time = Some(self.time_since_start);
+
+        let device = &self.device;
+        self.pipeline_cache.iter_mut().for_each(|pipeline| match pipeline.reload_if_changed(device) {
+            Ok(true) => { log!("Reloaded shader {:?}", pipeline.source_path().unwrap_or(Path::new(""))); },
+            Ok(false) => {},
+            Err(error) => { log!("Could not reload shader, keeping the old pipeline: {:#}", error); }
+        });
+    }
+
+    /// Returns true if this render engine renders into its own texture instead of a window surface.
+    pub fn is_headless(&self) -> bool { self.surface.is_none() }
 
     /// Get a texture from the texture cache using a handle.
     /// 
@@ -86,15 +183,7 @@ impl RenderEngine {
         ).await.unwrap();
 
         log!("Creating device and queue...");
-        // create device and queue
-        let (device, queue) = adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                required_features: adapter.features(),
-                required_limits: adapter.limits(),//wgpu::Limits::default(),
-                label: None
-            },
-            None
-        ).await.unwrap();
+        let (device, queue) = Self::request_device(&adapter).await;
 
         log!("Performing final capabilities creation...");
 
@@ -113,8 +202,83 @@ impl RenderEngine {
         };
         surface.configure(&device, &config);
 
+        Self::from_parts(Some(window), Some(surface), None, adapter, device, queue, config)
+    }
+
+    /// Create a new render engine that renders into its own texture instead of a window surface.  This is useful for tests, servers and other places where no window is available.
+    /// 
+    /// A hardware adapter will be used if available, otherwise, a software adapter (like lavapipe or llvmpipe) will be requested.
+    /// 
+    /// Arguments
+    /// * width - The width of the texture to render too.
+    /// * height - The height of the texture to render too.
+    /// * format - The format of the texture to render too.  Use a 4 byte per pixel format like `Rgba8UnormSrgb` if you want to read frames back with `read_frame`.
+    pub async fn new_headless(width: u32, height: u32, format: wgpu::TextureFormat) -> Self {
+        log!("Creating headless instance...");
+
+        // create wgpu instance
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+
+        log!("Creating adapter...");
+
+        // create adapter, falling back to a software adapter if no hardware adapter is available
+        let mut adapter = None;
+        for force_fallback_adapter in [false, true] {
+            adapter = instance.request_adapter(
+                &wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: None,
+                    force_fallback_adapter
+                }
+            ).await;
+            if adapter.is_some() { break }
+        }
+        let adapter = adapter.expect("Could not find an adapter for headless rendering!");
+
+        log!("Creating device and queue...");
+        let (device, queue) = Self::request_device(&adapter).await;
+
+        // create a configuration matching what a surface would use so the rest of the engine can treat both the same
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format, width: width.max(1), height: height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2
+        };
+        let texture = create_headless_texture(&device, &config);
+
+        Self::from_parts(None, None, Some(texture), adapter, device, queue, config)
+    }
+
+    /// Creates a device and queue from the given adapter.
+    async fn request_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+        adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                required_features: adapter.features(),
+                required_limits: adapter.limits(),//wgpu::Limits::default(),
+                label: None
+            },
+            None
+        ).await.unwrap()
+    }
+
+    /// Finishes creating a render engine from the given output targets, device, queue and configuration.
+    fn from_parts(
+        window: Option<Window>,
+        surface: Option<wgpu::Surface<'static>>,
+        headless_texture: Option<wgpu::Texture>,
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        config: wgpu::SurfaceConfiguration
+    ) -> Self {
         // setup depth texture
-        let depth_texture = DepthTexture::new(&config, &device, "depth_texture");
+        let depth_texture = DepthTexture::new(&config, &device, "depth_texture", 1);
 
         // setup timing
         let now = web_time::SystemTime::now();
@@ -125,13 +289,24 @@ impl RenderEngine {
 
         Self {
             window, surface, device,
-            queue, config, size, depth_texture,
+            queue, depth_texture, headless_texture,
+            render_graph: RenderGraph::default(),
+            shader_composer: ShaderComposer::new(),
+            variant_generations: HashMap::new(),
+            scene_format: config.format,
+            sample_count: 1,
+            adapter,
+            shader_poll_time: None,
+            size: winit::dpi::PhysicalSize::new(config.width, config.height),
+            config,
             start_time,
             time_since_start: 0.0,
             delta_time: 0.0,
+            fixed_time_step: None,
             mesh_cache: ResourceCache::new(),
             texture_cache: ResourceCache::new(),
-            pipeline_cache: ResourceCache::new()
+            pipeline_cache: ResourceCache::new(),
+            compute_pipeline_cache: ResourceCache::new()
         }
     }
     
@@ -145,14 +320,106 @@ impl RenderEngine {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            match &self.surface {
+                Some(surface) => surface.configure(&self.device, &self.config),
+                None => self.headless_texture = Some(create_headless_texture(&self.device, &self.config))
+            }
+
+            self.depth_texture = DepthTexture::new(&self.config, &self.device, "depth_texture", self.sample_count);
+            self.render_graph.resize(&self.device, &self.config);
+        }
+    }
 
-            self.depth_texture = DepthTexture::new(&self.config, &self.device, "depth_texture");
+    /// Requests the next frame from the window.  Headless engines have no window, so this does nothing for them.
+    pub fn next_frame(&self) { 
+        if let Some(window) = self.window() { window.request_redraw(); }
+    }
+
+    /// Grabs or releases the cursor of the window, for mouse look.  A grabbed cursor is hidden and locked in place where the platform supports it, or kept inside the window otherwise, and `Inputs::mouse_delta` keeps reporting how the mouse moves.  Headless engines have no window, so this does nothing for them.
+    ///
+    /// Arguments:
+    /// * grabbed: bool - True to grab the cursor, false to release it.
+    ///
+    /// Returns a result that is an error if the platform cannot grab the cursor.
+    pub fn set_cursor_grab(&self, grabbed: bool) -> anyhow::Result<()> {
+        let Some(window) = self.window() else { return Ok(()) };
+        if grabbed {
+            window.set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+                .map_err(|error| anyhow::anyhow!("Could not grab the cursor: {}", error))?;
+        } else {
+            window.set_cursor_grab(CursorGrabMode::None).map_err(|error| anyhow::anyhow!("Could not release the cursor: {}", error))?;
         }
+        window.set_cursor_visible(!grabbed);
+        Ok(())
     }
 
-    /// Requests the next frame from the window.
-    pub fn next_frame(&self) { self.window().request_redraw(); }
+    /// Reads the last finished frame of a headless render engine back into an image.
+    /// 
+    /// Only engines created with `new_headless` using a 4 byte per pixel rgba or bgra format can be read back.
+    /// 
+    /// Returns a result:
+    /// * Ok - image::RgbaImage - The last rendered frame.
+    /// * Error - Why the frame could not be read.
+    pub fn read_frame(&self) -> anyhow::Result<image::RgbaImage> {
+        let texture = self.headless_texture.as_ref().ok_or_else(|| anyhow::anyhow!("Only headless render engines can read frames back."))?;
+
+        // make sure we know how to convert the texture format to rgba
+        let swap_red_blue = match self.config.format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            format => anyhow::bail!("Cannot read back frames with the texture format {:?}.", format)
+        };
+
+        // rows copied out of a texture must be aligned, so pad each row
+        let width = self.config.width;
+        let height = self.config.height;
+        let unpadded_bytes_per_row = width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        // copy the texture into a buffer we can read
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("headless_read_buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("headless_read_encoder") });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height)
+                }
+            },
+            texture.size()
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        // wait for the buffer to be mapped
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| { let _ = sender.send(result); });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv()??;
+
+        // remove padding and convert to rgba
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[.. unpadded_bytes_per_row as usize]);
+            }
+        }
+        buffer.unmap();
+        if swap_red_blue { pixels.chunks_mut(4).for_each(|pixel| pixel.swap(0, 2)); }
+
+        image::RgbaImage::from_raw(width, height, pixels).ok_or_else(|| anyhow::anyhow!("Frame data did not match the frame size."))
+    }
 
     /// Creates a texture from the given bytes and the given path ID
     /// 
@@ -172,6 +439,19 @@ impl RenderEngine {
         })
     }
 
+    /// Inserts the given texture into the texture cache, replacing any texture with the same path ID.
+    /// 
+    /// Arguments
+    /// * path - The path ID so that this texture can be identified in the cache
+    /// * texture - The texture to insert
+    /// 
+    /// Returns a resource handle for the texture
+    pub fn insert_texture(&mut self, path: impl Into<String>, texture: Texture) -> Handle<Texture> {
+        let hash = ResourceCache::<Texture>::hash_path(path.into());
+        self.texture_cache.insert(hash, texture);
+        Handle { hash, data: PhantomData }
+    }
+
     /// Creates a texture from the given local path
     /// 
     /// Arguments
@@ -199,7 +479,7 @@ impl RenderEngine {
     /// Returns a resource handle for the mesh
     pub fn create_mesh(&mut self, path: &str, vertices: &[Vertex], indices: &[u16]) -> Handle<Mesh> { 
         self.mesh_cache.load(path, || { 
-            Mesh::from_raw(&self.device, vertices, indices) 
+            Mesh::from_vertices(&self.device, vertices, indices) 
         }) 
     }
 
@@ -261,4 +541,61 @@ impl RenderEngine {
             self.pipeline_cache.insert(hash, create(&self));
         }
     }
-}
\ No newline at end of file
+
+    pub fn compute_pipeline(&self, handle: &Handle<ComputePipeline>) -> Option<&ComputePipeline> { self.compute_pipeline_cache.get(handle) }
+    pub fn compute_pipeline_path(&self, path: impl Into<String>) -> Option<&ComputePipeline> {
+        self.compute_pipeline_cache.get(&Handle { hash: ResourceCache::<ComputePipeline>::hash_path(path.into()), data: PhantomData })
+    }
+
+    pub fn register_compute_pipeline(&mut self, path: impl Into<String>, pipeline: ComputePipeline) {
+        self.compute_pipeline_cache.insert(ResourceCache::<ComputePipeline>::hash_path(path.into()), pipeline);
+    }
+
+    /// Makes sure the compute pipeline with the given path exists, creating it with the given function if it does not.
+    ///
+    /// Arguments:
+    /// * path: impl Into<String> - The name the compute pipeline is cached under.
+    /// * create: F - Creates the compute pipeline if it does not exist yet.
+    pub fn verify_compute_pipeline_exists<F>(&mut self, path: impl Into<String>, create: F) where F: Fn(&RenderEngine) -> ComputePipeline {
+        let hash = ResourceCache::<ComputePipeline>::hash_path(path.into());
+        if self.compute_pipeline_cache.get(&Handle { hash, data: PhantomData }).is_none() {
+            self.compute_pipeline_cache.insert(hash, create(self));
+        }
+    }
+
+    /// Makes sure the pipeline of a variant of a shader registered with `shader_composer` exists and is up to date.  If it does not exist, or the shader or any module was registered again since it was created, the shader is composed with the key's defines and the given function creates the pipeline from the composed source, usually with a `PipelineBuilder`.  The pipeline is registered under the key's string form, so it can be used by name like any other pipeline.
+    ///
+    /// Arguments:
+    /// * key: &ShaderKey - The shader and the defines of the variant.
+    /// * create: F - Creates the pipeline from the render engine and the composed WGSL source.
+    ///
+    /// Returns a result containing the name the pipeline is registered under, or an error if the shader could not be composed.
+    pub fn verify_variant_pipeline_exists<F>(&mut self, key: &ShaderKey, create: F) -> anyhow::Result<String> where F: Fn(&RenderEngine, &str) -> Pipeline {
+        let name = key.to_string();
+        let generation = self.shader_composer.generation(&key.shader);
+        if self.pipeline_path(name.clone()).is_none() || self.variant_generations.get(&name).copied() != generation {
+            let source = self.shader_composer.compose(key)?.to_string();
+            let pipeline = create(self, &source);
+            self.register_pipeline(name.clone(), pipeline);
+            if let Some(generation) = generation { self.variant_generations.insert(name.clone(), generation); }
+        }
+        Ok(name)
+    }
+
+    /// Get the pipeline of a shader variant created with `verify_variant_pipeline_exists`.  After its shader or a module is registered again, this is the old pipeline until `verify_variant_pipeline_exists` recreates it.
+    pub fn variant_pipeline(&self, key: &ShaderKey) -> Option<&Pipeline> { self.pipeline_path(key.to_string()) }
+}
+
+/// Creates the texture a headless render engine renders too.
+fn create_headless_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("headless_texture"),
+        size: wgpu::Extent3d { width: config.width, height: config.height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[]
+    })
+}
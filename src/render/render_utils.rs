@@ -5,11 +5,11 @@ use super::render_engine::RenderEngine;
 /// A container struct for the necessary resources for WGPU to render.
 /// 
 /// Arguments:
-/// * output: SurfaceTexture - The WGPU surface texture to render too.
+/// * output: Option<SurfaceTexture> - The WGPU surface texture to render too.  This is none when rendering to a headless engine's texture.
 /// * view: TextureView - The WGPU texture view of the output texture.
 /// * encoder: CommandEncoder - The command encoder WGPU uses to actually render too.
 pub struct RenderResources {
-    pub output: SurfaceTexture,
+    pub output: Option<SurfaceTexture>,
     pub view: TextureView,
     pub encoder: CommandEncoder
 }
@@ -25,9 +25,16 @@ pub struct RenderResources {
 pub fn prepare_render(
     engine: &RenderEngine
 ) -> Result<RenderResources, wgpu::SurfaceError> {
-    // create output, view, and encoder
-    let output = engine.surface.get_current_texture()?;
-    let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+    // create output, view, and encoder, headless engines render straight to their own texture
+    let (output, view) = match (&engine.surface, &engine.headless_texture) {
+        (Some(surface), _) => {
+            let output = surface.get_current_texture()?;
+            let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+            (Some(output), view)
+        },
+        (None, Some(texture)) => (None, texture.create_view(&wgpu::TextureViewDescriptor::default())),
+        (None, None) => return Err(wgpu::SurfaceError::Lost)
+    };
     let encoder = engine.device.create_command_encoder(
         &wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
@@ -55,7 +62,14 @@ pub fn finalize_render(
 ) {
     // render the queue and wrap up
     engine.queue.submit(std::iter::once(resources.encoder.finish()));
-    resources.output.present();
+    if let Some(output) = resources.output { output.present(); }
+
+    // if a fixed time step is given, use that instead of the clock
+    if let Some(step) = engine.fixed_time_step {
+        engine.time_since_start += step;
+        engine.delta_time = step;
+        return;
+    }
 
     // update time since start and delta time
     let now = web_time::SystemTime::now().duration_since(web_time::UNIX_EPOCH).unwrap().as_millis();
@@ -108,9 +122,13 @@ macro_rules! pass {
     ($engine: expr, $resources: expr, $color: expr) => {
         {
             use forte_engine::render::render_utils;
+            // with msaa, draw into the render graph's multisampled surface and resolve it into the output
+            let (view, resolve_target) = match $engine.render_graph.multisampled_surface() {
+                Some(multisampled) => (multisampled, Some(&$resources.view)),
+                None => (&$resources.view, None)
+            };
             let color_attachment = wgpu::RenderPassColorAttachment {
-                view: &$resources.view,
-                resolve_target: None,
+                view, resolve_target,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear($color),
                     store: wgpu::StoreOp::Store,
@@ -122,7 +140,7 @@ macro_rules! pass {
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                     view: &$engine.depth_texture.view,
                     depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
+                        load: wgpu::LoadOp::Clear($engine.render_graph.clear_depth()),
                         store: wgpu::StoreOp::Store
                     }),
                     stencil_ops: None
@@ -2,7 +2,7 @@ use cgmath::{Quaternion, Vector2, Vector3, Zero};
 use glyphon::*;
 use wgpu::MultisampleState;
 
-use crate::{component_app::EngineComponent, create_pipeline, math::{quaternion::QuaternionExt, transforms::Transform}, primitives::{mesh::Mesh, textures::Texture, transforms::TransformRaw, vertices::Vertex}, render::{pipelines::Pipeline, render_engine::RenderEngine}, utils::resources::Handle};
+use crate::{component_app::EngineComponent, math::{quaternion::QuaternionExt, transforms::Transform}, primitives::{mesh::Mesh, textures::Texture, transforms::TransformRaw, vertices::Vertex}, render::{graph::{GraphPass, SURFACE_TEXTURE, UI_PASS_ORDER}, pipelines::{engine_shader_path, PipelineBuilder}, render_engine::RenderEngine}, utils::resources::Handle};
 
 use self::{elements::{ElementInfo, UIElement}, style::PositionSetting, uniforms::UIInstance};
 
@@ -34,7 +34,20 @@ pub struct UIEngine {
     font_system: FontSystem,
     font_cache: SwashCache,
     text_atlas: TextAtlas,
-    text_renderer: TextRenderer
+    text_renderer: TextRenderer,
+    sample_count: u32
+}
+
+impl UIEngine {
+    /// The name of the render graph pass this engine draws its elements in, over everything else drawn to the surface.
+    pub const PASS: &'static str = "forte.ui";
+
+    /// Recreates the text renderer if the render engine's sample count changed, as it must match the pass it draws in.  Text is not drawn until it is prepared again next update.
+    fn follow_sample_count(&mut self, engine: &RenderEngine) {
+        if self.sample_count == engine.sample_count() { return }
+        self.sample_count = engine.sample_count();
+        self.text_renderer = TextRenderer::new(&mut self.text_atlas, &engine.device, MultisampleState { count: self.sample_count, ..Default::default() }, None);
+    }
 }
 
 // Some info used for rendering
@@ -51,14 +64,17 @@ mod ui_shader {}
 
 impl EngineComponent<&mut RenderEngine> for UIEngine {
     fn create(engine: &mut RenderEngine) -> Self {
-        create_pipeline! {
-            NAME => "forte.ui",
-            ENGINE => engine,
-            SHADER => ui_shader::SOURCE,
-            BUFFER_LAYOUTS => [Vertex::desc(), UIInstance::desc()],
-            BIND_GROUPS => [Texture::BIND_LAYOUT],
-            HAS_DEPTH => false
-        }
+        // the ui is always drawn straight to the surface
+        engine.verify_pipeline_exists("forte.ui", |engine| {
+            PipelineBuilder::new("forte.ui", ui_shader::SOURCE)
+                .with_buffers(&[Vertex::desc(), UIInstance::desc()])
+                .with_layouts(&[&engine.device.create_bind_group_layout(&Texture::BIND_LAYOUT)])
+                .with_format(engine.config.format)
+                .with_source_path(engine_shader_path("src/ui/ui.wgsl"))
+                .build(engine)
+        });
+
+        engine.render_graph.add_pass(GraphPass::new(Self::PASS).with_color(SURFACE_TEXTURE).with_order(UI_PASS_ORDER));
 
         let mesh = engine.create_mesh("ui_engine_mesh", VERTICES, INDICES);
         let default_texture = engine.create_texture("ui.blank", include_bytes!("empty.png"));
@@ -67,17 +83,19 @@ impl EngineComponent<&mut RenderEngine> for UIEngine {
         let font_system = FontSystem::new();
         let font_cache = SwashCache::new();
         let mut text_atlas = TextAtlas::new(&engine.device, &engine.queue, engine.config.format);
-        let text_renderer = TextRenderer::new(&mut text_atlas, &engine.device, MultisampleState::default(), None);
+        let sample_count = engine.sample_count();
+        let text_renderer = TextRenderer::new(&mut text_atlas, &engine.device, MultisampleState { count: sample_count, ..Default::default() }, None);
         
         Self { 
             mesh, 
             default_texture, elements: Vec::new(), 
             font_system, font_cache, 
-            text_atlas, text_renderer
+            text_atlas, text_renderer, sample_count
         }
     }
 
     fn update(&mut self, render_engine: &mut RenderEngine) {
+        self.follow_sample_count(render_engine);
         let size = Vector2 { x: render_engine.size.width as f32, y: render_engine.size.height as f32 };
         let mut text_areas = Vec::<TextArea>::new();
         update_ui(render_engine, &UIRenderInfo { position: Vector2::zero(), size, display_size: size }, &self.elements, &mut text_areas, 0.5);
@@ -97,6 +115,13 @@ impl EngineComponent<&mut RenderEngine> for UIEngine {
         let _ = self.text_renderer.render(&self.text_atlas, pass);
     }
 
+    fn render_graph_pass<'rpass>(&'rpass mut self, name: &str, render_engine: &'rpass RenderEngine, pass: &mut wgpu::RenderPass<'rpass>) {
+        if name != Self::PASS { return }
+        self.follow_sample_count(render_engine);
+        render_engine.pipeline_path("forte.ui").unwrap().bind(pass);
+        self.render(render_engine, pass);
+    }
+
     fn start(&mut self, _: &mut RenderEngine) {}
     fn exit(&mut self, _: &mut RenderEngine) {}
 }
@@ -110,7 +135,7 @@ fn render_ui<'rpass>(engine: &'rpass RenderEngine, pass: &mut wgpu::RenderPass<'
         pass.set_bind_group(0, &texture.bind_group, &[]);
         pass.set_vertex_buffer(0, mesh.vertex_buf.slice(..));
         pass.set_vertex_buffer(1, element.buffer.slice(..));
-        pass.set_index_buffer(mesh.index_buf.slice(..), wgpu::IndexFormat::Uint16);
+        pass.set_index_buffer(mesh.index_buf.slice(..), mesh.index_format);
         pass.draw_indexed(0 .. mesh.num_indices, 0, 0 .. 1);
 
         render_ui(engine, pass, mesh, default_texture, &element.children);
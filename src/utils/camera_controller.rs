@@ -1,9 +1,9 @@
 use cgmath::*;
-use winit::keyboard::KeyCode;
+use winit::{event::MouseButton, keyboard::KeyCode};
 
-use crate::primitives::cameras::Camera;
+use crate::{inputs::Inputs, log, primitives::cameras::Camera, render::render_engine::RenderEngine};
 
-/// A basic flying camera controller.
+/// A basic flying camera controller, driven by key events.  See `FlyCameraController` for mouse look.
 #[derive(Debug)]
 pub struct CameraController {
     pub speed: f32,
@@ -74,3 +74,174 @@ impl CameraController {
         else if self.is_right_pressed { camera.rotation = Quaternion::from_angle_y(cgmath::Deg(5.0 * self.speed)) * camera.rotation; }
     }
 }
+
+/// A flying camera controller with mouse look, for free cameras in editors and debug views.  W, A, S and D move along the camera's view, E or Space move up, Q or left Control move down, and left Shift moves faster.  Movement is scaled by `RenderEngine::delta_time`, so it is the same at any frame rate.
+///
+/// Arguments:
+/// * speed: f32 - How far the camera moves per second.
+/// * boost: f32 - What the speed is multiplied by while left Shift is held.
+/// * sensitivity: f32 - How many degrees the camera turns per unit of mouse movement.
+/// * look_button: Option<MouseButton> - The mouse button that must be held to look around, or none to always look around.
+/// * grab_cursor: bool - True if the cursor is grabbed while looking around, so it stays hidden in place.
+#[derive(Debug)]
+pub struct FlyCameraController {
+    pub speed: f32,
+    pub boost: f32,
+    pub sensitivity: f32,
+    pub look_button: Option<MouseButton>,
+    pub grab_cursor: bool,
+    yaw: f32,
+    pitch: f32,
+    grabbed: bool
+}
+
+impl FlyCameraController {
+    /// How far up or down, in degrees, cameras can look.
+    pub const MAX_PITCH: f32 = 89.0;
+
+    /// Creates a new fly camera controller starting from the direction the given camera looks in.  The cursor is grabbed while the right mouse button is held.
+    ///
+    /// Arguments:
+    /// * camera: &Camera - The camera that will be controlled.
+    /// * speed: f32 - How far the camera moves per second.
+    ///
+    /// Returns a new fly camera controller.
+    pub fn new(camera: &Camera, speed: f32) -> Self {
+        let (yaw, pitch) = yaw_pitch(camera.forward());
+        Self { speed, boost: 4.0, sensitivity: 0.1, look_button: Some(MouseButton::Right), grab_cursor: true, yaw, pitch, grabbed: false }
+    }
+
+    /// Turns and moves the given camera with this frame's inputs.
+    ///
+    /// Arguments:
+    /// * camera: &mut Camera - The camera to be updated.
+    /// * engine: &RenderEngine - The render engine whose delta time scales movement and whose window's cursor is grabbed.
+    /// * inputs: &Inputs - The inputs of this frame.
+    pub fn update(&mut self, camera: &mut Camera, engine: &RenderEngine, inputs: &Inputs) {
+        // look around while the look button is held
+        let looking = self.look_button.is_none_or(|button| inputs.is_mouse_button_down(&button));
+        update_cursor_grab(engine, &mut self.grabbed, self.grab_cursor && looking);
+        if looking {
+            let delta = inputs.mouse_delta() * self.sensitivity;
+            self.yaw = (self.yaw - delta.x) % 360.0;
+            self.pitch = (self.pitch - delta.y).clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+        }
+        camera.look_to(look_direction(self.yaw, self.pitch), Vector3::unit_y());
+
+        // move relative to the new view
+        let axis = |positive: &[KeyCode], negative: &[KeyCode]| {
+            let down = |keys: &[KeyCode]| keys.iter().any(|key| inputs.is_key_down(key));
+            down(positive) as i32 as f32 - down(negative) as i32 as f32
+        };
+        let movement = camera.forward() * axis(&[KeyCode::KeyW], &[KeyCode::KeyS])
+            + camera.right() * axis(&[KeyCode::KeyD], &[KeyCode::KeyA])
+            + Vector3::unit_y() * axis(&[KeyCode::KeyE, KeyCode::Space], &[KeyCode::KeyQ, KeyCode::ControlLeft]);
+        if movement.magnitude2() <= f32::EPSILON { return }
+
+        let speed = if inputs.is_key_down(&KeyCode::ShiftLeft) { self.speed * self.boost } else { self.speed };
+        camera.position += movement.normalize() * speed * engine.delta_time;
+    }
+}
+
+/// A camera controller that orbits a target, for model viewers and level editors.  Dragging with the left mouse button orbits, dragging with the middle mouse button, or the left while left Shift is held, pans the target, and the scroll wheel zooms in and out.
+///
+/// Arguments:
+/// * target: Vector3<f32> - The point the camera orbits and looks at.
+/// * distance: f32 - How far the camera is from the target.
+/// * min_distance: f32 - The closest the camera can zoom to the target.
+/// * max_distance: f32 - The furthest the camera can zoom from the target.
+/// * sensitivity: f32 - How many degrees the camera orbits per unit of mouse movement.
+/// * pan_speed: f32 - How far the target pans per unit of mouse movement, as a fraction of the distance to it.
+/// * zoom_speed: f32 - How much the distance changes per pixel scrolled, as a fraction of the distance.
+/// * grab_cursor: bool - True if the cursor is grabbed while dragging, so it stays hidden in place.
+#[derive(Debug)]
+pub struct OrbitCameraController {
+    pub target: Vector3<f32>,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub sensitivity: f32,
+    pub pan_speed: f32,
+    pub zoom_speed: f32,
+    pub grab_cursor: bool,
+    yaw: f32,
+    pitch: f32,
+    grabbed: bool
+}
+
+impl OrbitCameraController {
+    /// Creates a new orbit camera controller around the given target, starting from where the given camera is.
+    ///
+    /// Arguments:
+    /// * camera: &Camera - The camera that will be controlled.
+    /// * target: Vector3<f32> - The point the camera orbits and looks at.
+    ///
+    /// Returns a new orbit camera controller.
+    pub fn new(camera: &Camera, target: Vector3<f32>) -> Self {
+        let offset = target - camera.position;
+        let (yaw, pitch) = if offset.magnitude2() > f32::EPSILON { yaw_pitch(offset.normalize()) } else { yaw_pitch(camera.forward()) };
+        Self {
+            target,
+            distance: offset.magnitude(),
+            min_distance: 0.1,
+            max_distance: 1000.0,
+            sensitivity: 0.3,
+            pan_speed: 0.002,
+            zoom_speed: 0.01,
+            grab_cursor: false,
+            yaw, pitch,
+            grabbed: false
+        }
+    }
+
+    /// Orbits, pans and zooms the given camera with this frame's inputs.
+    ///
+    /// Arguments:
+    /// * camera: &mut Camera - The camera to be updated.
+    /// * engine: &RenderEngine - The render engine whose window's cursor is grabbed.
+    /// * inputs: &Inputs - The inputs of this frame.
+    pub fn update(&mut self, camera: &mut Camera, engine: &RenderEngine, inputs: &Inputs) {
+        let left = inputs.is_mouse_button_down(&MouseButton::Left);
+        let panning = inputs.is_mouse_button_down(&MouseButton::Middle) || (left && inputs.is_key_down(&KeyCode::ShiftLeft));
+        let orbiting = left && !panning;
+        update_cursor_grab(engine, &mut self.grabbed, self.grab_cursor && (orbiting || panning));
+
+        let delta = inputs.mouse_delta();
+        if orbiting {
+            self.yaw = (self.yaw - delta.x * self.sensitivity) % 360.0;
+            self.pitch = (self.pitch + delta.y * self.sensitivity).clamp(-FlyCameraController::MAX_PITCH, FlyCameraController::MAX_PITCH);
+        }
+        if panning {
+            let pan = (camera.up() * delta.y - camera.right() * delta.x) * self.distance * self.pan_speed;
+            self.target += pan;
+        }
+
+        // zoom by a fraction of the distance, so it feels the same near and far
+        let scroll = inputs.mouse_scroll_delta().y;
+        self.distance = (self.distance * (-scroll * self.zoom_speed).exp()).clamp(self.min_distance, self.max_distance);
+
+        let direction = look_direction(self.yaw, self.pitch);
+        camera.position = self.target - direction * self.distance;
+        camera.look_to(direction, Vector3::unit_y());
+    }
+}
+
+/// The direction looked in with the given yaw and pitch in degrees, a yaw of 0 looks down -Z and a positive yaw turns left.
+fn look_direction(yaw: f32, pitch: f32) -> Vector3<f32> {
+    let (yaw, pitch) = (Deg(yaw), Deg(pitch));
+    Vector3::new(-yaw.sin() * pitch.cos(), pitch.sin(), -yaw.cos() * pitch.cos())
+}
+
+/// The yaw and pitch in degrees of the given normalized direction, the inverse of `look_direction`.
+fn yaw_pitch(direction: Vector3<f32>) -> (f32, f32) {
+    let pitch = Deg::from(Rad(direction.y.clamp(-1.0, 1.0).asin())).0.clamp(-FlyCameraController::MAX_PITCH, FlyCameraController::MAX_PITCH);
+    let yaw = Deg::from(Rad((-direction.x).atan2(-direction.z))).0;
+    (yaw, pitch)
+}
+
+/// Grabs or releases the cursor when whether it should be grabbed changes.  Platforms that cannot grab the cursor log why and leave it free.
+fn update_cursor_grab(engine: &RenderEngine, grabbed: &mut bool, wanted: bool) {
+    if *grabbed == wanted { return }
+    if let Err(error) = engine.set_cursor_grab(wanted) { log!("{:#}", error); }
+    *grabbed = wanted;
+}
@@ -1,4 +1,4 @@
-use std::{fs::File, io::Read};
+use std::{fs::File, io::Read, path::PathBuf};
 
 /// A struct to contain functions for loading files.
 pub struct Files;
@@ -23,4 +23,36 @@ impl Files {
         // return the buffer
         return Ok(buffer);
     }
-}
\ No newline at end of file
+}
+
+/// A trait for anything that can load the bytes of a file referenced by another asset, for example, the buffers and images referenced by a glTF file.
+/// 
+/// This is implemented for `DirectoryReader` to read files from disk and for any `Fn(&str) -> anyhow::Result<Vec<u8>>` so custom readers can be used, for example, on wasm.
+pub trait AssetReader {
+    /// Read the bytes of the file at the given uri.
+    /// 
+    /// Arguments:
+    /// * uri: &str - The uri of the file relative to the asset that references it.  Percent encoding has already been decoded.
+    /// 
+    /// Returns a result:
+    /// * Ok - Vec<u8> - The bytes of the file.
+    /// * Error - Why the file could not be read.
+    fn read(&self, uri: &str) -> anyhow::Result<Vec<u8>>;
+}
+
+impl <F: Fn(&str) -> anyhow::Result<Vec<u8>>> AssetReader for F {
+    fn read(&self, uri: &str) -> anyhow::Result<Vec<u8>> { self(uri) }
+}
+
+/// An `AssetReader` that reads files relative to a directory on disk.
+#[derive(Debug, Clone)]
+pub struct DirectoryReader {
+    pub directory: PathBuf
+}
+
+impl AssetReader for DirectoryReader {
+    fn read(&self, uri: &str) -> anyhow::Result<Vec<u8>> {
+        let path = self.directory.join(uri);
+        std::fs::read(&path).map_err(|error| anyhow::anyhow!("Could not read {:?}: {}", path, error))
+    }
+}
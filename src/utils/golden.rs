@@ -0,0 +1,255 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::*;
+
+use crate::{render::render_engine::RenderEngine, EngineApp, RenderEngineApp};
+
+/// The environment variable that, when set, makes `GoldenImage::check` write the rendered frame as the new reference image instead of comparing against it.
+pub const UPDATE_ENV_VAR: &str = "FORTE_UPDATE_GOLDEN";
+
+/// Settings used to render an app offscreen and compare the result against a stored reference image.
+///
+/// Example:
+/// ```rust
+/// create_app! { ... }
+///
+/// // inside a test function
+/// let golden = GoldenImage { frames: 10, tolerance: 2, ..Default::default() };
+/// pollster::block_on(golden.check::<App>("tests/golden/lit_cube.png")).unwrap();
+/// ```
+///
+/// Arguments:
+/// * width: u32 - The width of the rendered frame.
+/// * height: u32 - The height of the rendered frame.
+/// * format: wgpu::TextureFormat - The format of the texture rendered too.
+/// * frames: u32 - The number of frames to render before reading back the result.
+/// * time_step: f32 - The number of seconds that pass each frame, so every run renders the same frames.
+/// * tolerance: u8 - The maximum difference allowed in any channel of a pixel before the pixel counts as different.
+/// * max_failed_pixels: usize - The number of pixels that may be different before the comparison fails.
+#[derive(Debug, Clone)]
+pub struct GoldenImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub frames: u32,
+    pub time_step: f32,
+    pub tolerance: u8,
+    pub max_failed_pixels: usize
+}
+
+impl Default for GoldenImage {
+    fn default() -> Self {
+        Self {
+            width: 256,
+            height: 256,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            frames: 1,
+            time_step: 1.0 / 60.0,
+            tolerance: 0,
+            max_failed_pixels: 0
+        }
+    }
+}
+
+impl GoldenImage {
+    /// Creates the app given as a generic argument with a headless render engine, runs it for the set number of frames and reads back the last frame.
+    ///
+    /// Returns a result:
+    /// * Ok - image::RgbaImage - The last rendered frame.
+    /// * Error - Why the frame could not be read back.
+    pub async fn render<T: EngineApp + RenderEngineApp>(&self) -> Result<image::RgbaImage> {
+        // setup headless engine with a fixed time step
+        let mut engine = RenderEngine::new_headless(self.width, self.height, self.format).await;
+        engine.fixed_time_step = Some(self.time_step);
+
+        // run the app
+        let mut app = T::create(engine);
+        app.start();
+        for _ in 0 .. self.frames.max(1) { app.update(); }
+        let frame = app.render_engine().read_frame();
+        app.exit();
+
+        frame
+    }
+
+    /// Compares the given image against the reference image at the given path.
+    ///
+    /// On failure, the given image and an image highlighting the failing pixels in red are written next to the reference as `<name>.actual.png` and `<name>.diff.png`.
+    ///
+    /// Arguments:
+    /// * actual: &image::RgbaImage - The rendered image.
+    /// * reference: impl AsRef<Path> - The path to the reference png.
+    ///
+    /// Returns a result that is an error with a description of the differences if the comparison fails.
+    pub fn compare(&self, actual: &image::RgbaImage, reference: impl AsRef<Path>) -> Result<()> {
+        let reference = reference.as_ref();
+
+        // load the reference image, if it does not exist, write out the actual image so it can be inspected
+        let expected = match image::open(reference) {
+            std::result::Result::Ok(image) => image.to_rgba8(),
+            Err(error) => {
+                let actual_path = save_output(reference, "actual", actual)?;
+                bail!("Could not load reference image {:?} ({}), the rendered image was written to {:?}.", reference, error, actual_path);
+            }
+        };
+
+        // compare sizes
+        if expected.dimensions() != actual.dimensions() {
+            let actual_path = save_output(reference, "actual", actual)?;
+            bail!(
+                "Rendered image size {:?} does not match reference image {:?} size {:?}, the rendered image was written to {:?}.",
+                actual.dimensions(), reference, expected.dimensions(), actual_path
+            );
+        }
+
+        // compare pixels
+        let (diff, failed) = diff_images(&expected, actual, self.tolerance);
+        if failed > self.max_failed_pixels {
+            let actual_path = save_output(reference, "actual", actual)?;
+            let diff_path = save_output(reference, "diff", &diff)?;
+            bail!(
+                "{} pixels differ from reference image {:?} by more than {} (max allowed {}), see {:?} and {:?}.",
+                failed, reference, self.tolerance, self.max_failed_pixels, actual_path, diff_path
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Renders the app given as a generic argument and compares the result against the reference image at the given path.
+    ///
+    /// If the `FORTE_UPDATE_GOLDEN` environment variable is set, the rendered frame is saved as the new reference image instead.
+    ///
+    /// Arguments:
+    /// * reference: impl AsRef<Path> - The path to the reference png.
+    ///
+    /// Returns a result that is an error if rendering or the comparison failed.
+    pub async fn check<T: EngineApp + RenderEngineApp>(&self, reference: impl AsRef<Path>) -> Result<()> {
+        let reference = reference.as_ref();
+        let actual = self.render::<T>().await?;
+
+        // if requested, update the reference image
+        if std::env::var_os(UPDATE_ENV_VAR).is_some() {
+            if let Some(parent) = reference.parent() { std::fs::create_dir_all(parent)?; }
+            actual.save(reference)?;
+            return Ok(());
+        }
+
+        self.compare(&actual, reference)
+    }
+}
+
+/// Compares two images of the same size pixel by pixel.
+///
+/// Arguments:
+/// * expected: &image::RgbaImage - The reference image.
+/// * actual: &image::RgbaImage - The image to compare against the reference.
+/// * tolerance: u8 - The maximum difference allowed in any channel of a pixel before the pixel counts as different.
+///
+/// Returns a diff image where failing pixels are red and passing pixels are a faded copy of the expected image, and the number of failing pixels.
+pub fn diff_images(expected: &image::RgbaImage, actual: &image::RgbaImage, tolerance: u8) -> (image::RgbaImage, usize) {
+    let mut failed = 0;
+    let diff = image::RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+        let a = expected.get_pixel(x, y);
+        let b = actual.get_pixel(x, y);
+        let different = a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > tolerance);
+        if different {
+            failed += 1;
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            image::Rgba([a[0] / 4, a[1] / 4, a[2] / 4, 255])
+        }
+    });
+    (diff, failed)
+}
+
+/// Saves the given image next to the given reference path with the given suffix, for example, `cube.png` with suffix `diff` becomes `cube.diff.png`.
+/// 
+/// Returns a result with the path the image was saved too.
+fn save_output(reference: &Path, suffix: &str, image: &image::RgbaImage) -> Result<PathBuf> {
+    let stem = reference.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let path = reference.with_file_name(format!("{}.{}.png", stem, suffix));
+    if let Some(parent) = path.parent() { std::fs::create_dir_all(parent)?; }
+    image.save(&path).with_context(|| format!("Could not save {:?}", path))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A new empty directory for the outputs of one test.
+    fn output_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("forte_golden_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn solid(value: u8) -> image::RgbaImage { image::RgbaImage::from_pixel(4, 4, image::Rgba([value, value, value, 255])) }
+
+    #[test]
+    fn diff_images_respects_tolerance() {
+        let mut actual = solid(100);
+        actual.put_pixel(1, 2, image::Rgba([105, 100, 100, 255]));
+        actual.put_pixel(3, 0, image::Rgba([100, 94, 100, 255]));
+
+        let (diff, failed) = diff_images(&solid(100), &actual, 5);
+        assert_eq!(failed, 1);
+        assert_eq!(*diff.get_pixel(3, 0), image::Rgba([255, 0, 0, 255]));
+        assert_eq!(*diff.get_pixel(1, 2), image::Rgba([25, 25, 25, 255]));
+
+        let (_, failed) = diff_images(&solid(100), &actual, 6);
+        assert_eq!(failed, 0);
+    }
+
+    #[test]
+    fn save_output_names_files_after_reference() {
+        let dir = output_dir("save");
+        let path = save_output(&dir.join("nested").join("cube.png"), "diff", &solid(0)).unwrap();
+        assert_eq!(path, dir.join("nested").join("cube.diff.png"));
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn compare_passes_within_limits() {
+        let dir = output_dir("pass");
+        let reference = dir.join("cube.png");
+        solid(100).save(&reference).unwrap();
+
+        let mut actual = solid(102);
+        actual.put_pixel(0, 0, image::Rgba([0, 0, 0, 255]));
+        GoldenImage { tolerance: 2, max_failed_pixels: 1, ..Default::default() }.compare(&actual, &reference).unwrap();
+        assert!(!dir.join("cube.actual.png").exists());
+        assert!(!dir.join("cube.diff.png").exists());
+    }
+
+    #[test]
+    fn compare_writes_actual_and_diff_on_failure() {
+        let dir = output_dir("fail");
+        let reference = dir.join("cube.png");
+        solid(100).save(&reference).unwrap();
+
+        let mut actual = solid(100);
+        actual.put_pixel(2, 1, image::Rgba([200, 100, 100, 255]));
+        let error = GoldenImage::default().compare(&actual, &reference).unwrap_err();
+        assert!(error.to_string().starts_with("1 pixels differ"));
+
+        assert_eq!(image::open(dir.join("cube.actual.png")).unwrap().to_rgba8(), actual);
+        let diff = image::open(dir.join("cube.diff.png")).unwrap().to_rgba8();
+        assert_eq!(*diff.get_pixel(2, 1), image::Rgba([255, 0, 0, 255]));
+        assert_eq!(*diff.get_pixel(0, 0), image::Rgba([25, 25, 25, 255]));
+    }
+
+    #[test]
+    fn compare_fails_on_missing_reference_or_size() {
+        let dir = output_dir("missing");
+        let reference = dir.join("cube.png");
+        assert!(GoldenImage::default().compare(&solid(0), &reference).is_err());
+        assert!(dir.join("cube.actual.png").exists());
+
+        solid(0).save(&reference).unwrap();
+        let error = GoldenImage::default().compare(&image::RgbaImage::new(2, 2), &reference).unwrap_err();
+        assert!(error.to_string().contains("does not match"));
+    }
+}
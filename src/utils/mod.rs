@@ -1,3 +1,4 @@
 pub mod camera_controller;
 pub mod files;
+pub mod golden;
 pub mod resources;
\ No newline at end of file
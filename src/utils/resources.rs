@@ -69,6 +69,9 @@ impl<T> ResourceCache<T> {
     /// Returns an option that will contain a mutable reference to the resource referenced by the handle if it is present in the cache.
     pub fn get_mut(&mut self, handle: &Handle<T>) -> Option<&mut T> { self.assets.get_mut(&handle.hash) }
 
+    /// Returns an iterator over mutable references to every resource in the cache.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> { self.assets.values_mut() }
+
     /// Inserts an object into the resource cache with the given hash value.
     /// 
     /// Arguments:
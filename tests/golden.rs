@@ -0,0 +1,104 @@
+use cgmath::Quaternion;
+use forte_engine::{component_app::EngineComponent, create_app, math::{quaternion::QuaternionExt, transforms::Transform}, primitives::{cameras::{Camera, Projection}, mesh::Mesh, textures::Texture, transforms::TransformRaw, vertices::Vertex}, render::pipelines::Pipeline, utils::{golden::GoldenImage, resources::Handle}};
+
+const VERTICES: &[Vertex] = &[
+    Vertex { position: [ 0.5, -0.5, -0.5], tex_coords: [0.4131759, 0.00759614], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [ 0.5, -0.5,  0.5], tex_coords: [0.0048659444, 0.43041354], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [-0.5, -0.5,  0.5], tex_coords: [0.28081453, 0.949397], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [-0.5, -0.5, -0.5], tex_coords: [0.85967, 0.84732914], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [ 0.5,  0.5, -0.5], tex_coords: [0.9414737, 0.2652641], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [ 0.5,  0.5,  0.5], tex_coords: [0.28081453, 0.949397], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [-0.5,  0.5,  0.5], tex_coords: [0.85967, 0.84732914], normal: [0.0, 0.0, 0.0] },
+    Vertex { position: [-0.5,  0.5, -0.5], tex_coords: [0.9414737, 0.2652641], normal: [0.0, 0.0, 0.0] },
+];
+
+const INDICES: &[u16] = &[
+    1, 2, 3,
+    4, 7, 6,
+    4, 5, 1,
+    1, 5, 6,
+    6, 7, 3,
+    4, 0, 3,
+    0, 1, 3,
+    5, 4, 6,
+    0, 4, 1,
+    2, 1, 6,
+    2, 6, 3,
+    7, 4, 3
+];
+
+/// Draws a textured cube turned so three of its faces are visible.
+pub struct CubeComponent {
+    mesh: Handle<Mesh>,
+    texture: Handle<Texture>,
+    camera: Camera,
+    instance_buffer: wgpu::Buffer
+}
+
+impl EngineComponent<&mut RenderEngine> for CubeComponent {
+    fn create(engine: &mut RenderEngine) -> Self {
+        let mut camera = Camera::new(engine, Projection::Perspective { fovy: 45.0, znear: 0.1, zfar: 100.0 });
+        camera.position = (0.0, 0.0, 3.0).into();
+        camera.update(engine);
+
+        engine.verify_pipeline_exists("forte.golden", |engine| {
+            Pipeline::new(
+                "std", engine, include_str!("../examples/rotating_cube.wgsl"),
+                &[Vertex::desc(), TransformRaw::desc()],
+                &[
+                    &engine.device.create_bind_group_layout(&Camera::BIND_LAYOUT),
+                    &engine.device.create_bind_group_layout(&Texture::BIND_LAYOUT),
+                ],
+                true
+            )
+        });
+
+        let instances = [Transform { rotation: Quaternion::euler_deg(30.0, 45.0, 0.0), ..Default::default() }];
+        Self {
+            mesh: engine.create_mesh("golden_cube", VERTICES, INDICES),
+            texture: engine.create_texture("golden_cube", include_bytes!("../examples/rotating_cube.png")),
+            camera,
+            instance_buffer: TransformRaw::buffer_from_generic(engine, &instances)
+        }
+    }
+
+    fn start(&mut self, _: &mut RenderEngine) {}
+
+    fn update(&mut self, _: &mut RenderEngine) {}
+
+    fn render<'rpass>(&'rpass mut self, engine: &'rpass RenderEngine, pass: &mut wgpu::RenderPass<'rpass>) {
+        self.camera.bind(pass, engine, 0);
+        engine.draw_textured_mesh(pass, &self.mesh, &self.texture, &self.instance_buffer, 1);
+    }
+
+    fn exit(&mut self, _: &mut RenderEngine) {}
+}
+
+create_app! {
+    CLEAR_COLOR = wgpu::Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 },
+
+    APP {
+        cube: CubeComponent[render_engine]
+    },
+
+    PASSES {
+        "main": {
+            COLOR: ["forte.surface"],
+            DEPTH: "forte.depth",
+            PARTS: [
+                {
+                    PIPELINE: "forte.golden",
+                    PREPARE: [],
+                    RENDER: cube,
+                }
+            ]
+        }
+    }
+}
+
+#[test]
+fn textured_cube() {
+    // allow small differences between GPUs in filtering and rasterization
+    let golden = GoldenImage { width: 128, height: 128, frames: 2, tolerance: 8, max_failed_pixels: 64, ..Default::default() };
+    pollster::block_on(golden.check::<App>("tests/golden/textured_cube.png")).unwrap();
+}